@@ -20,6 +20,7 @@ extern crate winit;
 
 use hal::{buffer, command, format as f, image as i, memory as m, pass, pso, pool, window::Extent2D};
 use hal::{Device, Instance, PhysicalDevice, Surface, Swapchain};
+use hal::adapter::{select_adapter, Criteria};
 use hal::{
     DescriptorPool, FrameSync, Primitive,
     Backbuffer, SwapchainConfig,
@@ -69,7 +70,7 @@ fn main() {
         .with_title("quad".to_string());
     // instantiate backend
     #[cfg(not(feature = "gl"))]
-    let (window, _instance, mut adapters, mut surface) = {
+    let (window, _instance, adapters, mut surface) = {
         let window = wb.build(&events_loop).unwrap();
         let instance = back::Instance::create("gfx-rs quad", 1);
         let surface = instance.create_surface(&window);
@@ -77,7 +78,7 @@ fn main() {
         (window, instance, adapters, surface)
     };
     #[cfg(feature = "gl")]
-    let (mut adapters, mut surface) = {
+    let (adapters, mut surface) = {
         let window = {
             let builder =
                 back::config_context(back::glutin::ContextBuilder::new(), ColorFormat::SELF, None)
@@ -94,7 +95,10 @@ fn main() {
         println!("{:?}", adapter.info);
     }
 
-    let mut adapter = adapters.remove(0);
+    let mut adapter = {
+        let criteria = Criteria::<back::Backend, hal::Graphics>::new().with_surface(&surface);
+        select_adapter(adapters, &criteria).unwrap()
+    };
     let memory_types = adapter.physical_device.memory_properties().memory_types;
     let limits = adapter.physical_device.limits();
 
@@ -284,6 +288,7 @@ fn main() {
                     ..(i::Access::TRANSFER_WRITE, i::Layout::TransferDstOptimal),
                 target: &image_logo,
                 range: COLOR_RANGE.clone(),
+                families: None,
             };
 
             cmd_buffer.pipeline_barrier(
@@ -319,6 +324,7 @@ fn main() {
                     ..(i::Access::SHADER_READ, i::Layout::ShaderReadOnlyOptimal),
                 target: &image_logo,
                 range: COLOR_RANGE.clone(),
+                families: None,
             };
             cmd_buffer.pipeline_barrier(
                 PipelineStage::TRANSFER..PipelineStage::FRAGMENT_SHADER,
@@ -575,6 +581,7 @@ fn swapchain_stuff(
         map_or(f::Format::Rgba8Srgb, |formats| {
             formats
                 .into_iter()
+                .map(|(format, _color_space)| format)
                 .find(|format| format.base_format().1 == ChannelType::Srgb)
                 .unwrap()
         });
@@ -4,6 +4,8 @@ extern crate gfx_hal as hal;
 extern crate gfx_backend_vulkan as back;
 #[cfg(feature = "metal")]
 extern crate gfx_backend_metal as back;
+#[cfg(feature = "gl")]
+extern crate gfx_backend_gl as back;
 #[macro_use]
 extern crate gfx_render as gfx;
 
@@ -13,6 +15,8 @@ extern crate image;
 use std::io::Cursor;
 
 use hal::{command, format as f, image as i, pso};
+#[cfg(feature = "gl")]
+use hal::format::AsFormat;
 use hal::{Device, Instance, PhysicalDevice, Primitive};
 use gfx::format::{Rgba8Srgb as ColorFormat};
 use gfx::allocators::StackAllocator as Allocator;
@@ -49,23 +53,70 @@ gfx_graphics_pipeline! {
     }
 }
 
+fn create_targets(
+    device: &mut gfx::Device<back::Backend>,
+    pipeline: &pipe::Meta<back::Backend>,
+    backbuffers: &[gfx::Backbuffer<back::Backend, ColorFormat>],
+    extent: i::Extent,
+) -> (
+    Vec<gfx::handle::ImageView<back::Backend, ColorFormat>>,
+    Vec<gfx::handle::raw::Framebuffer<back::Backend>>,
+) {
+    let image_range = gfx::image::SubresourceRange {
+        aspects: f::Aspects::COLOR,
+        levels: 0 .. 1,
+        layers: 0 .. 1,
+    };
+    let rtvs = backbuffers.iter().map(|backbuffer| {
+        device
+            .create_image_view(&backbuffer.color, i::ViewKind::D2, image_range.clone())
+            .unwrap()
+    }).collect::<Vec<_>>();
+    let framebuffers = rtvs.iter().map(|rtv| {
+        device
+            .create_framebuffer(pipeline, &[rtv.as_ref()], extent)
+            .unwrap()
+    }).collect::<Vec<_>>();
+    (rtvs, framebuffers)
+}
+
+fn viewport(width: u16, height: u16) -> pso::Viewport {
+    pso::Viewport {
+        rect: pso::Rect { x: 0, y: 0, w: width, h: height },
+        depth: 0.0 .. 1.0,
+    }
+}
+
 fn main() {
     env_logger::init();
 
     let mut events_loop = winit::EventsLoop::new();
-    let window = winit::WindowBuilder::new()
+    let wb = winit::WindowBuilder::new()
         .with_dimensions(1024, 768)
-        .with_title("quad".to_string())
-        .build(&events_loop)
-        .unwrap();
-    let window_size = window.get_inner_size().unwrap();
-    let pixel_width = window_size.0 as u16;
-    let pixel_height = window_size.1 as u16;
+        .with_title("quad".to_string());
 
     // instantiate backend
-    let instance = back::Instance::create("gfx-rs quad", 1);
-    let surface = instance.create_surface(&window);
-    let mut adapters = instance.enumerate_adapters();
+    #[cfg(not(feature = "gl"))]
+    let (_window, _instance, mut adapters, surface) = {
+        let window = wb.build(&events_loop).unwrap();
+        let instance = back::Instance::create("gfx-rs quad", 1);
+        let surface = instance.create_surface(&window);
+        let adapters = instance.enumerate_adapters();
+        (window, instance, adapters, surface)
+    };
+    // the surface owns the window, presenting through its default framebuffer
+    #[cfg(feature = "gl")]
+    let (mut adapters, surface) = {
+        let window = {
+            let builder =
+                back::config_context(back::glutin::ContextBuilder::new(), ColorFormat::SELF, None)
+                    .with_vsync(true);
+            back::glutin::GlWindow::new(wb, builder, &events_loop).unwrap()
+        };
+        let surface = back::Surface::from_window(window);
+        let adapters = surface.enumerate_adapters();
+        (adapters, surface)
+    };
     for adapter in &adapters {
         println!("{:?}", adapter.info);
     }
@@ -107,17 +158,9 @@ fn main() {
     };
 
     // Framebuffer creation
-    let frame_rtvs = backbuffers.iter().map(|backbuffer| {
-        device
-            .create_image_view(&backbuffer.color, i::ViewKind::D2, image_range.clone())
-            .unwrap()
-    }).collect::<Vec<_>>();
-    let framebuffers = frame_rtvs.iter().map(|rtv| {
-        let extent = i::Extent { width: pixel_width as _, height: pixel_height as _, depth: 1 };
-        device
-            .create_framebuffer(&pipeline, &[rtv.as_ref()], extent)
-            .unwrap()
-    }).collect::<Vec<_>>();
+    let mut extent = context.backbuffer_extent().to_extent();
+    let (mut frame_rtvs, mut framebuffers) =
+        create_targets(&mut device, &pipeline, &backbuffers, extent);
 
     let mut upload = Allocator::new(
         gfx::memory::Usage::Upload,
@@ -201,14 +244,7 @@ fn main() {
         .finish();
 
     // Rendering setup
-    let scissor = pso::Rect {
-        x: 0, y: 0,
-        w: pixel_width, h: pixel_height,
-    };
-    let viewport = pso::Viewport {
-        rect: scissor,
-        depth: 0.0 .. 1.0,
-    };
+    let mut viewport = viewport(extent.width as _, extent.height as _);
 
     let mut encoder_pool = context.acquire_encoder_pool();
     let mut init_encoder = encoder_pool.acquire_encoder();
@@ -238,6 +274,7 @@ fn main() {
 
     let mut running = true;
     while running {
+        let mut resized = None;
         events_loop.poll_events(|event| {
             if let winit::Event::WindowEvent { event, .. } = event {
                 match event {
@@ -247,12 +284,43 @@ fn main() {
                             .. },
                         ..
                     } | winit::WindowEvent::CloseRequested => running = false,
+                    winit::WindowEvent::Resized(width, height) => resized = Some((width, height)),
                     _ => (),
                 }
             }
         });
 
-        let frame = context.acquire_frame();
+        let mut backbuffers = None;
+        match resized {
+            // nothing to render to while minimized
+            Some((0, _)) | Some((_, 0)) => continue,
+            Some((width, height)) => {
+                let extent = hal::window::Extent2D { width, height };
+                backbuffers = Some(context.resize::<ColorFormat>(extent).unwrap());
+            }
+            None => (),
+        }
+
+        let frame = match context.acquire_frame() {
+            Ok(frame) => Some(frame),
+            Err(gfx::SwapchainRecreated) => {
+                backbuffers = Some(context.backbuffers::<ColorFormat>());
+                None
+            }
+        };
+
+        if let Some(backbuffers) = backbuffers {
+            extent = context.backbuffer_extent().to_extent();
+            let targets = create_targets(&mut device, &pipeline, &backbuffers, extent);
+            frame_rtvs = targets.0;
+            framebuffers = targets.1;
+            viewport = self::viewport(extent.width as _, extent.height as _);
+        }
+
+        let frame = match frame {
+            Some(frame) => frame,
+            None => continue,
+        };
         let mut encoder_pool = context.acquire_encoder_pool();
         let mut encoder = encoder_pool.acquire_encoder();
 
@@ -262,14 +330,20 @@ fn main() {
                 color: &frame_rtvs[frame.id()],
                 vertices: &vertex_buffer,
                 viewports: &[viewport.clone()],
-                scissors: &[scissor],
+                scissors: &[viewport.rect],
                 framebuffer: &framebuffers[frame.id()],
             };
-            encoder.draw(0..6, &pipeline, data);
+            encoder.draw(&gfx::Slice::from(0..6), &pipeline, data);
         }
 
         submits.push(encoder.finish());
-        context.present(submits.drain(..).collect::<Vec<_>>());
+        if let Err(gfx::SwapchainRecreated) = context.present(submits.drain(..).collect::<Vec<_>>()) {
+            extent = context.backbuffer_extent().to_extent();
+            let targets = create_targets(&mut device, &pipeline, &context.backbuffers(), extent);
+            frame_rtvs = targets.0;
+            framebuffers = targets.1;
+            viewport = self::viewport(extent.width as _, extent.height as _);
+        }
     }
 
     println!("cleanup!");
@@ -0,0 +1,52 @@
+use std::sync::Mutex;
+
+use hal::{MemoryProperties, MemoryTypeId};
+use hal::memory::HeapBudget;
+
+/// Tracks the amount of memory allocated by a device from each memory heap.
+///
+/// Used for answering `memory_budget` queries when the native API
+/// doesn't expose the heap usage.
+#[derive(Debug)]
+pub struct HeapUsage {
+    /// Heap index of each memory type.
+    type_heaps: Vec<usize>,
+    /// Size and allocated bytes of each heap.
+    heaps: Vec<(u64, Mutex<u64>)>,
+}
+
+impl HeapUsage {
+    pub fn new(properties: &MemoryProperties) -> Self {
+        HeapUsage {
+            type_heaps: properties.memory_types
+                .iter()
+                .map(|ty| ty.heap_index)
+                .collect(),
+            heaps: properties.memory_heaps
+                .iter()
+                .map(|&size| (size, Mutex::new(0)))
+                .collect(),
+        }
+    }
+
+    pub fn allocate(&self, memory_type: MemoryTypeId, size: u64) {
+        let heap = self.type_heaps[memory_type.0];
+        *self.heaps[heap].1.lock().unwrap() += size;
+    }
+
+    pub fn free(&self, memory_type: MemoryTypeId, size: u64) {
+        let heap = self.type_heaps[memory_type.0];
+        *self.heaps[heap].1.lock().unwrap() -= size;
+    }
+
+    /// Budget of each heap, assuming the whole heap is available to the application.
+    pub fn budget(&self) -> Vec<HeapBudget> {
+        self.heaps
+            .iter()
+            .map(|&(size, ref usage)| HeapBudget {
+                budget: size,
+                usage: *usage.lock().unwrap(),
+            })
+            .collect()
+    }
+}
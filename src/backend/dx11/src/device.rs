@@ -24,11 +24,13 @@ use {
 };
 
 use {conv, internal, shader};
+use heap_usage::HeapUsage;
 
 pub struct Device {
     raw: ComPtr<d3d11::ID3D11Device>,
     pub(crate) context: ComPtr<d3d11::ID3D11DeviceContext>,
     memory_properties: hal::MemoryProperties,
+    heap_usage: HeapUsage,
     pub(crate) internal: internal::BufferImageCopy
 }
 
@@ -40,6 +42,7 @@ impl Device {
         Device {
             raw: device.clone(),
             context,
+            heap_usage: HeapUsage::new(&memory_properties),
             memory_properties,
             internal: internal::BufferImageCopy::new(device)
         }
@@ -320,10 +323,12 @@ impl hal::Device<Backend> for Device {
         mem_type: hal::MemoryTypeId,
         size: u64,
     ) -> Result<Memory, device::OutOfMemory> {
+        self.heap_usage.allocate(mem_type, size);
         // TODO:
         Ok(Memory {
             properties: self.memory_properties.memory_types[mem_type.0].properties,
             buffer: RefCell::new(None),
+            ty: mem_type,
             size,
         })
     }
@@ -478,6 +483,8 @@ impl hal::Device<Backend> for Device {
                 size,
                 alignment: 1,
                 type_mask: 0x7,
+                prefers_dedicated: false,
+                requires_dedicated: false,
             }
         })
     }
@@ -715,6 +722,8 @@ impl hal::Device<Backend> for Device {
                 size: size,
                 alignment: 1,
                 type_mask: 0x1,
+                prefers_dedicated: false,
+                requires_dedicated: false,
             },
         })
     }
@@ -1041,6 +1050,17 @@ impl hal::Device<Backend> for Device {
         }
     }
 
+    fn memory_coherency(&self, memory: &Memory) -> mapping::Coherency {
+        if memory.properties.contains(memory::Properties::COHERENT) {
+            mapping::Coherency::Coherent
+        } else {
+            mapping::Coherency::NonCoherent {
+                atom_size: 1,
+                memory_size: memory.size,
+            }
+        }
+    }
+
     fn unmap_memory(&self, memory: &Memory) {
         let (buffer, device_buffer) = match memory.buffer.borrow().clone().unwrap() {
             InternalBuffer::Coherent(buf) => (buf, None),
@@ -1109,7 +1129,12 @@ impl hal::Device<Backend> for Device {
     }
 
     fn free_memory(&self, memory: Memory) {
-        unimplemented!()
+        self.heap_usage.free(memory.ty, memory.size);
+        // The internal buffer is released on drop.
+    }
+
+    fn memory_budget(&self) -> Vec<memory::HeapBudget> {
+        self.heap_usage.budget()
     }
 
     fn create_query_pool(&self, query_ty: query::QueryType, count: u32) -> QueryPool {
@@ -38,6 +38,9 @@ use std::borrow::{BorrowMut, Borrow};
 
 use std::os::raw::c_void;
 
+#[path = "../../auxil/heap_usage.rs"]
+mod heap_usage;
+
 mod conv;
 mod dxgi;
 mod shader;
@@ -311,6 +314,7 @@ impl hal::Instance for Instance {
                 framebuffer_depth_samples_count: 1,     // TODO
                 framebuffer_stencil_samples_count: 1,   // TODO
                 non_coherent_atom_size: 0,              // TODO
+                buffer_image_granularity: 1,
             };
 
             let features = get_features(device.clone(), feature_level);
@@ -607,7 +611,9 @@ impl hal::Surface<Backend> for Surface {
         image::Kind::D2(self.width, self.height, 1, 1)
     }
 
-    fn capabilities_and_formats(&self, _: &PhysicalDevice) -> (hal::SurfaceCapabilities, Option<Vec<format::Format>>) {
+    fn capabilities_and_formats(
+        &self, _: &PhysicalDevice,
+    ) -> (hal::SurfaceCapabilities, Option<Vec<(format::Format, hal::window::ColorSpace)>>) {
         let extent = hal::window::Extent2D {
             width: self.width,
             height: self.height,
@@ -631,6 +637,11 @@ impl hal::Surface<Backend> for Surface {
             format::Format::A2b10g10r10Unorm,
             format::Format::Rgba16Float,
         ];
+        // TODO: HDR color spaces require `IDXGISwapChain3`
+        let formats = formats
+            .into_iter()
+            .map(|format| (format, hal::window::ColorSpace::SrgbNonlinear))
+            .collect();
 
         (capabilities, Some(formats))
     }
@@ -1136,6 +1147,7 @@ pub struct Memory {
     #[derivative(Debug="ignore")]
     // TODO: :-(
     buffer: RefCell<Option<InternalBuffer>>,
+    ty: hal::MemoryTypeId,
     size: u64,
 }
 
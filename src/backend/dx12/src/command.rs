@@ -1092,7 +1092,20 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
                     };
                     raw_barriers.push(bar);
                 }
-                memory::Barrier::Buffer { ref states, target } => {
+                memory::Barrier::Aliasing { before, after } => {
+                    let mut bar = d3d12::D3D12_RESOURCE_BARRIER {
+                        Type: d3d12::D3D12_RESOURCE_BARRIER_TYPE_ALIASING,
+                        Flags: d3d12::D3D12_RESOURCE_BARRIER_FLAG_NONE,
+                        u: unsafe { mem::zeroed() },
+                    };
+                    *unsafe { bar.u.Aliasing_mut() } = d3d12::D3D12_RESOURCE_ALIASING_BARRIER {
+                        pResourceBefore: before.map_or(ptr::null_mut(), |image| image.resource),
+                        pResourceAfter: after.resource,
+                    };
+                    raw_barriers.push(bar);
+                }
+                // D3D12 has no queue family ownership, so transfers are ignored.
+                memory::Barrier::Buffer { ref states, target, .. } => {
                     let state_src = conv::map_buffer_resource_state(states.start);
                     let state_dst = conv::map_buffer_resource_state(states.end);
 
@@ -1111,7 +1124,7 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
 
                     raw_barriers.push(bar);
                 }
-                memory::Barrier::Image { ref states, target, ref range } => {
+                memory::Barrier::Image { ref states, target, ref range, .. } => {
                     let _ = range; //TODO: use subresource range
                     let state_src = conv::map_image_resource_state(states.start.0, states.start.1);
                     let state_dst = conv::map_image_resource_state(states.end.0, states.end.1);
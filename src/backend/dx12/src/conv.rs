@@ -5,12 +5,13 @@ use spirv_cross::spirv;
 
 use winapi::shared::basetsd::UINT8;
 use winapi::shared::dxgiformat::*;
+use winapi::shared::dxgitype::*;
 use winapi::shared::minwindef::{FALSE, INT, TRUE};
 use winapi::um::d3d12::*;
 use winapi::um::d3dcommon::*;
 
 use hal::format::{Format, ImageFeature, SurfaceType};
-use hal::{buffer, image, pso, Primitive};
+use hal::{buffer, image, pso, window, Primitive};
 use hal::pso::DescriptorSetLayoutBinding;
 
 pub fn map_format(format: Format) -> Option<DXGI_FORMAT> {
@@ -112,6 +113,16 @@ pub fn map_format_dsv(surface: SurfaceType) -> Option<DXGI_FORMAT> {
     })
 }
 
+pub fn map_color_space(color_space: window::ColorSpace) -> Option<DXGI_COLOR_SPACE_TYPE> {
+    use hal::window::ColorSpace::*;
+    match color_space {
+        SrgbNonlinear => Some(DXGI_COLOR_SPACE_RGB_FULL_G22_NONE_P709),
+        ExtendedSrgbLinear => Some(DXGI_COLOR_SPACE_RGB_FULL_G10_NONE_P709),
+        Hdr10St2084 => Some(DXGI_COLOR_SPACE_RGB_FULL_G2084_NONE_P2020),
+        _ => None,
+    }
+}
+
 pub fn map_topology_type(primitive: Primitive) -> D3D12_PRIMITIVE_TOPOLOGY_TYPE {
     use hal::Primitive::*;
     match primitive {
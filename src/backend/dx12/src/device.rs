@@ -1050,6 +1050,8 @@ impl d::Device<B> for Device {
             None
         };
 
+        self.heap_usage.allocate(hal::MemoryTypeId(mem_type), size);
+
         Ok(n::Memory {
             heap: unsafe { ComPtr::from_raw(heap as _) },
             type_id: mem_type,
@@ -1813,6 +1815,8 @@ impl d::Device<B> for Device {
             size,
             alignment: d3d12::D3D12_DEFAULT_RESOURCE_PLACEMENT_ALIGNMENT as u64,
             type_mask: MEM_TYPE_MASK << type_mask_shift,
+            prefers_dedicated: false,
+            requires_dedicated: false,
         };
 
         Ok(UnboundBuffer {
@@ -2053,6 +2057,8 @@ impl d::Device<B> for Device {
                 size: alloc_info.SizeInBytes,
                 alignment: alloc_info.Alignment,
                 type_mask: MEM_TYPE_MASK << type_mask_shift,
+                prefers_dedicated: false,
+                requires_dedicated: false,
             },
             format,
             kind,
@@ -2669,6 +2675,11 @@ impl d::Device<B> for Device {
         }
     }
 
+    fn memory_coherency(&self, _memory: &n::Memory) -> mapping::Coherency {
+        // All CPU visible memory types are exposed as coherent.
+        mapping::Coherency::Coherent
+    }
+
     fn flush_mapped_memory_ranges<'a, I, R>(&self, ranges: I)
     where
         I: IntoIterator,
@@ -2812,11 +2823,42 @@ impl d::Device<B> for Device {
     }
 
     fn free_memory(&self, memory: n::Memory) {
+        self.heap_usage.free(hal::MemoryTypeId(memory.type_id), memory.size);
         if let Some(buffer) = memory.resource {
             unsafe { (*buffer).Release(); }
         }
     }
 
+    fn memory_budget(&self) -> Vec<memory::HeapBudget> {
+        let adapter = match self.adapter {
+            Some(ref adapter) => adapter,
+            None => return self.heap_usage.budget(),
+        };
+
+        // Heap 0 is the local segment, heap 1 (if any) the non-local one.
+        let segments = [
+            dxgi1_4::DXGI_MEMORY_SEGMENT_GROUP_LOCAL,
+            dxgi1_4::DXGI_MEMORY_SEGMENT_GROUP_NON_LOCAL,
+        ];
+        let fallback = self.heap_usage.budget();
+        let mut budget = Vec::with_capacity(fallback.len());
+        for &segment in segments.iter().take(fallback.len()) {
+            let mut mem_info: dxgi1_4::DXGI_QUERY_VIDEO_MEMORY_INFO = unsafe { mem::zeroed() };
+            let hr = unsafe {
+                adapter.QueryVideoMemoryInfo(0, segment, &mut mem_info)
+            };
+            if !winerror::SUCCEEDED(hr) {
+                warn!("QueryVideoMemoryInfo failed with {:x}, falling back to tracked usage", hr);
+                return fallback;
+            }
+            budget.push(memory::HeapBudget {
+                budget: mem_info.Budget,
+                usage: mem_info.CurrentUsage,
+            });
+        }
+        budget
+    }
+
     fn create_query_pool(&self, query_ty: query::QueryType, count: u32) -> n::QueryPool {
         let heap_ty = match query_ty {
             query::QueryType::Occlusion =>
@@ -2988,6 +3030,25 @@ impl d::Device<B> for Device {
 
         let swap_chain = unsafe { ComPtr::<dxgi1_4::IDXGISwapChain3>::from_raw(swap_chain as _) };
 
+        if config.color_space != window::ColorSpace::SrgbNonlinear {
+            let color_space = match conv::map_color_space(config.color_space) {
+                Some(color_space) => color_space,
+                None => panic!("Unsupported swapchain color space {:?}", config.color_space),
+            };
+            let mut support = 0;
+            let hr = unsafe { swap_chain.CheckColorSpaceSupport(color_space, &mut support) };
+            if !winerror::SUCCEEDED(hr) || support & dxgi1_4::DXGI_SWAP_CHAIN_COLOR_SPACE_SUPPORT_FLAG_PRESENT == 0 {
+                panic!(
+                    "Swapchain color space {:?} can't be presented with {:?}",
+                    config.color_space, config.color_format,
+                );
+            }
+            let hr = unsafe { swap_chain.SetColorSpace1(color_space) };
+            if !winerror::SUCCEEDED(hr) {
+                panic!("error on setting swapchain color space 0x{:x}", hr);
+            }
+        }
+
         // Get backbuffer images
         let mut resources: Vec<ComPtr<d3d12::ID3D12Resource>> = Vec::new();
         let images = (0 .. config.image_count).map(|i| {
@@ -12,6 +12,8 @@ extern crate winapi;
 extern crate winit;
 extern crate wio;
 
+#[path = "../../auxil/heap_usage.rs"]
+mod heap_usage;
 #[path = "../../auxil/range_alloc.rs"]
 mod range_alloc;
 mod command;
@@ -541,6 +543,10 @@ pub struct Device {
     queues: Vec<CommandQueue>,
     // Indicates that there is currently an active device.
     open: Arc<Mutex<bool>>,
+    // Adapter used for querying the memory budget of the heaps.
+    adapter: Option<ComPtr<dxgi1_4::IDXGIAdapter3>>,
+    // Fallback for the memory budget if `IDXGIAdapter3` is not available.
+    heap_usage: heap_usage::HeapUsage,
 }
 unsafe impl Send for Device {} //blocked by ComPtr
 unsafe impl Sync for Device {} //blocked by ComPtr
@@ -614,6 +620,8 @@ impl Device {
             present_queue,
             queues: Vec::new(),
             open: physical_device.is_open.clone(),
+            adapter: physical_device.adapter.cast::<dxgi1_4::IDXGIAdapter3>().ok(),
+            heap_usage: heap_usage::HeapUsage::new(&physical_device.memory_properties),
         }
     }
 
@@ -743,13 +751,6 @@ impl hal::Instance for Instance {
                 name.to_string_lossy().into_owned()
             };
 
-            let info = hal::AdapterInfo {
-                name: device_name,
-                vendor: desc.VendorId as usize,
-                device: desc.DeviceId as usize,
-                software_rendering: (desc.Flags & dxgi::DXGI_ADAPTER_FLAG_SOFTWARE) != 0,
-            };
-
             let mut features: d3d12::D3D12_FEATURE_DATA_D3D12_OPTIONS = unsafe { mem::zeroed() };
             assert_eq!(winerror::S_OK, unsafe {
                 device.CheckFeatureSupport(
@@ -768,6 +769,22 @@ impl hal::Instance for Instance {
                 )
             });
 
+            let software_rendering = (desc.Flags & dxgi::DXGI_ADAPTER_FLAG_SOFTWARE) != 0;
+            let info = hal::AdapterInfo {
+                name: device_name,
+                vendor: desc.VendorId as usize,
+                device: desc.DeviceId as usize,
+                device_type: if software_rendering {
+                    hal::DeviceType::Cpu
+                } else if features_architecture.UMA == TRUE {
+                    hal::DeviceType::IntegratedGpu
+                } else {
+                    hal::DeviceType::DiscreteGpu
+                },
+                driver_version: 0, // TODO
+                software_rendering,
+            };
+
             let depth_bounds_test_supported = {
                 let mut features2: d3d12::D3D12_FEATURE_DATA_D3D12_OPTIONS2 = unsafe { mem::zeroed() };
                 let hr = unsafe {
@@ -1040,6 +1057,7 @@ impl hal::Instance for Instance {
                     framebuffer_depth_samples_count: 0b101,
                     framebuffer_stencil_samples_count: 0b101,
                     non_coherent_atom_size: 1, //TODO: confirm
+                    buffer_image_granularity: 1,
                 },
                 format_properties: Arc::new(format_properties),
                 private_caps: Capabilities {
@@ -1,16 +1,18 @@
 use std::collections::VecDeque;
-use std::mem;
+use std::{mem, ptr};
 
 #[cfg(feature = "winit")]
 use winit;
 
-use winapi::shared::dxgi1_4;
+use winapi::shared::{dxgi, dxgi1_4, dxgi1_6, winerror};
+use winapi::shared::dxgitype::{DXGI_COLOR_SPACE_RGB_FULL_G2084_NONE_P2020, DXGI_COLOR_SPACE_TYPE};
 use winapi::shared::windef::{HWND, RECT};
-use winapi::um::winuser::GetClientRect;
+use winapi::um::winuser::{GetClientRect, MonitorFromWindow, MONITOR_DEFAULTTONEAREST};
 use winapi::um::d3d12;
 use wio::com::ComPtr;
 
 use hal::{self, format as f, image as i};
+use hal::window::ColorSpace;
 use {native as n, Backend, Instance, PhysicalDevice, QueueFamily};
 
 use std::os::raw::c_void;
@@ -50,6 +52,32 @@ pub struct Surface {
 unsafe impl Send for Surface { }
 unsafe impl Sync for Surface { }
 
+impl Surface {
+    /// Color space of the adapter output the window is displayed on.
+    fn output_color_space(&self, adapter: &dxgi::IDXGIAdapter) -> Option<DXGI_COLOR_SPACE_TYPE> {
+        let monitor = unsafe { MonitorFromWindow(self.wnd_handle, MONITOR_DEFAULTTONEAREST) };
+        for i in 0 .. {
+            let mut output: *mut dxgi::IDXGIOutput = ptr::null_mut();
+            let hr = unsafe { adapter.EnumOutputs(i, &mut output as *mut *mut _) };
+            if hr == winerror::DXGI_ERROR_NOT_FOUND || !winerror::SUCCEEDED(hr) {
+                break;
+            }
+
+            let output = unsafe { ComPtr::from_raw(output) };
+            let output6 = match output.cast::<dxgi1_6::IDXGIOutput6>() {
+                Ok(output6) => output6,
+                Err(_) => continue, // Windows 10 1703 or newer is required
+            };
+            let mut desc: dxgi1_6::DXGI_OUTPUT_DESC1 = unsafe { mem::zeroed() };
+            let hr = unsafe { output6.GetDesc1(&mut desc) };
+            if winerror::SUCCEEDED(hr) && desc.Monitor == monitor {
+                return Some(desc.ColorSpace);
+            }
+        }
+        None
+    }
+}
+
 impl hal::Surface<Backend> for Surface {
     fn supports_queue_family(&self, queue_family: &QueueFamily) -> bool {
         match queue_family {
@@ -63,8 +91,8 @@ impl hal::Surface<Backend> for Surface {
     }
 
     fn capabilities_and_formats(
-        &self, _: &PhysicalDevice,
-    ) -> (hal::SurfaceCapabilities, Option<Vec<f::Format>>) {
+        &self, physical_device: &PhysicalDevice,
+    ) -> (hal::SurfaceCapabilities, Option<Vec<(f::Format, ColorSpace)>>) {
         let extent = hal::window::Extent2D {
             width: self.width,
             height: self.height,
@@ -80,15 +108,23 @@ impl hal::Surface<Backend> for Surface {
         // Sticking to FLIP swap effects for the moment.
         // We also expose sRGB buffers but they are handled internally as UNORM.
         // Roughly ordered by popularity..
-        let formats = vec![
-            f::Format::Bgra8Srgb,
-            f::Format::Bgra8Unorm,
-            f::Format::Rgba8Srgb,
-            f::Format::Rgba8Unorm,
-            f::Format::A2b10g10r10Unorm,
-            f::Format::Rgba16Float,
+        let mut formats = vec![
+            (f::Format::Bgra8Srgb, ColorSpace::SrgbNonlinear),
+            (f::Format::Bgra8Unorm, ColorSpace::SrgbNonlinear),
+            (f::Format::Rgba8Srgb, ColorSpace::SrgbNonlinear),
+            (f::Format::Rgba8Unorm, ColorSpace::SrgbNonlinear),
+            (f::Format::A2b10g10r10Unorm, ColorSpace::SrgbNonlinear),
+            (f::Format::Rgba16Float, ColorSpace::SrgbNonlinear),
         ];
 
+        // HDR output, applied via `IDXGISwapChain3::SetColorSpace1`.
+        // Only presentable if the output the window is on is in HDR mode.
+        let output_color_space = self.output_color_space(&physical_device.adapter);
+        if output_color_space == Some(DXGI_COLOR_SPACE_RGB_FULL_G2084_NONE_P2020) {
+            formats.push((f::Format::A2b10g10r10Unorm, ColorSpace::Hdr10St2084));
+            formats.push((f::Format::Rgba16Float, ColorSpace::ExtendedSrgbLinear));
+        }
+
         (capabilities, Some(formats))
     }
 }
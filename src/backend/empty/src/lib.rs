@@ -279,6 +279,10 @@ impl hal::Device<Backend> for Device {
         unimplemented!()
     }
 
+    fn memory_coherency(&self, _: &()) -> mapping::Coherency {
+        unimplemented!()
+    }
+
     fn flush_mapped_memory_ranges<'a, I, R>(&self, _: I)
     where
         I: IntoIterator,
@@ -301,6 +305,10 @@ impl hal::Device<Backend> for Device {
         unimplemented!()
     }
 
+    fn memory_budget(&self) -> Vec<memory::HeapBudget> {
+        unimplemented!()
+    }
+
     fn destroy_shader_module(&self, _: ()) {
         unimplemented!()
     }
@@ -773,7 +781,7 @@ impl hal::Surface<Backend> for Surface {
 
     fn capabilities_and_formats(
         &self, _: &PhysicalDevice,
-    ) -> (hal::SurfaceCapabilities, Option<Vec<format::Format>>) {
+    ) -> (hal::SurfaceCapabilities, Option<Vec<(format::Format, hal::window::ColorSpace)>>) {
         unimplemented!()
     }
 
@@ -108,6 +108,8 @@ pub enum Command {
     CopySurfaceToBuffer(n::Surface, n::RawBuffer, command::BufferImageCopy),
     CopyImageToTexture(n::ImageKind, n::Texture, command::ImageCopy),
     CopyImageToSurface(n::ImageKind, n::Surface, command::ImageCopy),
+    /// Blit from the read framebuffer to the draw framebuffer.
+    BlitFramebuffer(command::ImageBlit, gl::types::GLenum),
 }
 
 pub type FrameBufferTarget = gl::types::GLenum;
@@ -684,6 +686,41 @@ impl command::RawCommandBuffer<Backend> for RawCommandBuffer {
         unimplemented!()
     }
 
+    fn blit_image_to_framebuffer<T>(
+        &mut self,
+        src: &n::Image,
+        _src_layout: image::Layout,
+        dst: &n::FrameBuffer,
+        filter: image::Filter,
+        regions: T,
+    ) where
+        T: IntoIterator,
+        T::Item: Borrow<command::ImageBlit>
+    {
+        let filter = match filter {
+            image::Filter::Nearest => gl::NEAREST,
+            image::Filter::Linear => gl::LINEAR,
+        };
+        let fbo = self.fbo;
+        self.push_cmd(Command::BindFrameBuffer(gl::READ_FRAMEBUFFER, fbo));
+        self.push_cmd(Command::BindFrameBuffer(gl::DRAW_FRAMEBUFFER, *dst));
+
+        for region in regions {
+            let r = region.borrow().clone();
+            let level = r.src_subresource.level;
+            let layer = r.src_subresource.layers.start;
+            let view = match src.kind {
+                n::ImageKind::Surface(id) => n::ImageView::Surface(id),
+                n::ImageKind::Texture(id) if layer == 0 => n::ImageView::Texture(id, level),
+                n::ImageKind::Texture(id) => n::ImageView::TextureLayer(id, level, layer),
+            };
+            self.push_cmd(Command::BindTargetView(gl::READ_FRAMEBUFFER, gl::COLOR_ATTACHMENT0, view));
+            self.push_cmd(Command::BlitFramebuffer(r, filter));
+        }
+        // don't leave the internal FBO bound for reading
+        self.push_cmd(Command::BindFrameBuffer(gl::READ_FRAMEBUFFER, 0));
+    }
+
     fn bind_index_buffer(&mut self, ibv: buffer::IndexBufferView<Backend>) {
         // TODO: how can we incorporate the buffer offset?
         if ibv.offset > 0 {
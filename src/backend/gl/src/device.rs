@@ -19,6 +19,7 @@ use spirv_cross::{glsl, spirv, ErrorCode as SpirvErrorCode};
 
 use {Backend as B, Share, Surface, Swapchain, Starc};
 use {conv, native as n, state};
+use heap_usage::HeapUsage;
 use info::LegacyFeatures;
 use pool::{BufferMemory, OwnedBuffer, RawCommandPool};
 
@@ -32,6 +33,19 @@ fn gen_unexpected_error(err: SpirvErrorCode) -> d::ShaderError {
     d::ShaderError::CompilationFailed(msg)
 }
 
+/// Raw value of a specialization constant, as expected by SPIRV-Cross.
+fn constant_bits(constant: &pso::Constant) -> u64 {
+    match *constant {
+        pso::Constant::Bool(v) => v as u64,
+        pso::Constant::U32(v) => v as u64,
+        pso::Constant::U64(v) => v,
+        pso::Constant::I32(v) => v as u32 as u64,
+        pso::Constant::I64(v) => v as u64,
+        pso::Constant::F32(v) => v.to_bits() as u64,
+        pso::Constant::F64(v) => v.to_bits(),
+    }
+}
+
 fn get_shader_iv(gl: &gl::Gl, name: n::Shader, query: GLenum) -> gl::types::GLint {
     let mut iv = 0;
     unsafe { gl.GetShaderiv(name, query, &mut iv) };
@@ -103,6 +117,7 @@ pub struct UnboundImage {
 #[derive(Debug)]
 pub struct Device {
     share: Starc<Share>,
+    heap_usage: HeapUsage,
 }
 
 impl Drop for Device {
@@ -113,9 +128,10 @@ impl Drop for Device {
 
 impl Device {
     /// Create a new `Device`.
-    pub(crate) fn new(share: Starc<Share>) -> Self {
+    pub(crate) fn new(share: Starc<Share>, memory_properties: c::MemoryProperties) -> Self {
         Device {
             share: share,
+            heap_usage: HeapUsage::new(&memory_properties),
         }
     }
 
@@ -229,16 +245,8 @@ impl Device {
             {
                 // Override specialization constant values
                 unsafe {
-                    let value = match constant.value {
-                        pso::Constant::Bool(v) => v as u64,
-                        pso::Constant::U32(v) => v as u64,
-                        pso::Constant::U64(v) => v,
-                        pso::Constant::I32(v) => *(&v as *const _ as *const u64),
-                        pso::Constant::I64(v) => *(&v as *const _ as *const u64),
-                        pso::Constant::F32(v) => *(&v as *const _ as *const u64),
-                        pso::Constant::F64(v) => *(&v as *const _ as *const u64),
-                    };
-                    ast.set_scalar_constant(spec_constant.id, value).map_err(gen_unexpected_error)?;
+                    ast.set_scalar_constant(spec_constant.id, constant_bits(&constant.value))
+                        .map_err(gen_unexpected_error)?;
                 }
             }
         }
@@ -286,19 +294,33 @@ impl Device {
 
     fn compile_shader(
         &self, point: &pso::EntryPoint<B>, stage: pso::Stage
-    ) -> n::Shader {
+    ) -> Result<n::Shader, d::ShaderError> {
         assert_eq!(point.entry, "main");
         match *point.module {
-            n::ShaderModule::Raw(raw) => raw,
-            n::ShaderModule::Spirv(ref spirv) => {
-                let mut ast = self.parse_spirv(spirv).unwrap();
-                self.specialize_ast(&mut ast, point.specialization).unwrap();
-                let glsl = self.translate_spirv(&mut ast).unwrap();
+            n::ShaderModule::Raw(raw) => Ok(raw),
+            n::ShaderModule::Spirv(ref module) => {
+                let mut specialization = point.specialization
+                    .iter()
+                    .map(|c| (c.id, constant_bits(&c.value)))
+                    .collect::<Vec<_>>();
+                specialization.sort();
+
+                let mut compiled = module.compiled.lock().unwrap();
+                let key = (stage, specialization);
+                if let Some(&shader) = compiled.get(&key) {
+                    return Ok(shader);
+                }
+
+                let mut ast = self.parse_spirv(&module.spirv)?;
+                self.specialize_ast(&mut ast, point.specialization)?;
+                let glsl = self.translate_spirv(&mut ast)?;
                 info!("Generated:\n{:?}", glsl);
-                match self.create_shader_module_from_source(glsl.as_bytes(), stage).unwrap() {
+                let shader = match self.create_shader_module_from_source(glsl.as_bytes(), stage)? {
                     n::ShaderModule::Raw(raw) => raw,
                     _ => panic!("Unhandled")
-                }
+                };
+                compiled.insert(key, shader);
+                Ok(shader)
             }
         }
     }
@@ -306,12 +328,14 @@ impl Device {
 
 impl d::Device<B> for Device {
     fn allocate_memory(
-        &self, _mem_type: c::MemoryTypeId, size: u64,
+        &self, mem_type: c::MemoryTypeId, size: u64,
     ) -> Result<n::Memory, d::OutOfMemory> {
+        self.heap_usage.allocate(mem_type, size);
         // TODO
         Ok(n::Memory {
             properties: memory::Properties::CPU_VISIBLE | memory::Properties::CPU_CACHED,
             first_bound_buffer: Cell::new(0),
+            ty: mem_type,
             size,
         })
     }
@@ -419,16 +443,23 @@ impl d::Device<B> for Device {
                 (pso::Stage::Fragment, desc.shaders.fragment.as_ref()),
             ];
 
-            let shader_names = &shaders
+            let shader_names = shaders
                 .iter()
                 .filter_map(|&(stage, point_maybe)| {
                     point_maybe.map(|point| {
-                        let shader_name = self.compile_shader(point, stage);
+                        let shader_name = self.compile_shader(point, stage)?;
                         unsafe { gl.AttachShader(name, shader_name); }
-                        shader_name
+                        Ok(shader_name)
                     })
                 })
-                .collect::<Vec<_>>();
+                .collect::<Result<Vec<_>, _>>();
+            let shader_names = match shader_names {
+                Ok(shader_names) => shader_names,
+                Err(err) => {
+                    unsafe { gl.DeleteProgram(name) };
+                    return Err(pso::CreationError::Shader(err));
+                }
+            };
 
             if !share.private_caps.program_interface && share.private_caps.frag_data_location {
                 for i in 0..subpass.color_attachments.len() {
@@ -445,11 +476,9 @@ impl d::Device<B> for Device {
                 panic!("Error linking program: {:?}", err);
             }
 
+            // Shaders are owned by their modules and reused across pipelines.
             for shader_name in shader_names {
-                unsafe {
-                    gl.DetachShader(name, *shader_name);
-                    gl.DeleteShader(*shader_name);
-                }
+                unsafe { gl.DetachShader(name, shader_name) };
             }
 
             let status = get_program_iv(gl, name, gl::LINK_STATUS);
@@ -510,7 +539,13 @@ impl d::Device<B> for Device {
         let program = {
             let name = unsafe { gl.CreateProgram() };
 
-            let shader = self.compile_shader(&desc.shader, pso::Stage::Compute);
+            let shader = match self.compile_shader(&desc.shader, pso::Stage::Compute) {
+                Ok(shader) => shader,
+                Err(err) => {
+                    unsafe { gl.DeleteProgram(name) };
+                    return Err(pso::CreationError::Shader(err));
+                }
+            };
             unsafe { gl.AttachShader(name, shader) };
 
             unsafe { gl.LinkProgram(name) };
@@ -519,10 +554,7 @@ impl d::Device<B> for Device {
                 panic!("Error linking program: {:?}", err);
             }
 
-            unsafe {
-                gl.DetachShader(name, shader);
-                gl.DeleteShader(shader);
-            }
+            unsafe { gl.DetachShader(name, shader) };
 
             let status = get_program_iv(gl, name, gl::LINK_STATUS);
             let log = get_program_log(gl, name);
@@ -604,7 +636,7 @@ impl d::Device<B> for Device {
         &self,
         raw_data: &[u8],
     ) -> Result<n::ShaderModule, d::ShaderError> {
-        Ok(n::ShaderModule::Spirv(raw_data.into()))
+        Ok(n::ShaderModule::Spirv(n::SpirvModule::new(raw_data.into())))
     }
 
     fn create_sampler(&self, info: i::SamplerInfo) -> n::FatSampler {
@@ -698,6 +730,8 @@ impl d::Device<B> for Device {
                 size,
                 alignment: 1, // TODO: do we need specific alignment for any use-case?
                 type_mask: 0x7,
+                prefers_dedicated: false,
+                requires_dedicated: false,
             },
         })
     }
@@ -797,6 +831,17 @@ impl d::Device<B> for Device {
         Ok(ptr)
     }
 
+    fn memory_coherency(&self, memory: &n::Memory) -> mapping::Coherency {
+        if memory.properties.contains(memory::Properties::COHERENT) {
+            mapping::Coherency::Coherent
+        } else {
+            mapping::Coherency::NonCoherent {
+                atom_size: 1,
+                memory_size: memory.size,
+            }
+        }
+    }
+
     fn unmap_memory(&self, memory: &n::Memory) {
         let gl = &self.share.context;
         let buffer = match memory.first_bound_buffer.get() {
@@ -905,6 +950,8 @@ impl d::Device<B> for Device {
                 size,
                 alignment: 1,
                 type_mask: 0x7,
+                prefers_dedicated: false,
+                requires_dedicated: false,
             }
         })
     }
@@ -1061,8 +1108,12 @@ impl d::Device<B> for Device {
         unimplemented!()
     }
 
-    fn free_memory(&self, _memory: n::Memory) {
-        // Nothing to do
+    fn free_memory(&self, memory: n::Memory) {
+        self.heap_usage.free(memory.ty, memory.size);
+    }
+
+    fn memory_budget(&self) -> Vec<memory::HeapBudget> {
+        self.heap_usage.budget()
     }
 
     fn create_query_pool(&self, _ty: query::QueryType, _count: u32) -> () {
@@ -1073,8 +1124,18 @@ impl d::Device<B> for Device {
         unimplemented!()
     }
 
-    fn destroy_shader_module(&self, _: n::ShaderModule) {
-        // Assumes compiled shaders are managed internally
+    fn destroy_shader_module(&self, module: n::ShaderModule) {
+        let gl = &self.share.context;
+        match module {
+            n::ShaderModule::Raw(raw) => unsafe {
+                gl.DeleteShader(raw);
+            },
+            n::ShaderModule::Spirv(module) => {
+                for (_, shader) in module.compiled.into_inner().unwrap() {
+                    unsafe { gl.DeleteShader(shader) };
+                }
+            }
+        }
     }
 
     fn destroy_render_pass(&self, _: n::RenderPass) {
@@ -1,7 +1,7 @@
 use std::collections::HashSet;
 use std::{ffi, fmt, mem, str};
 use gl;
-use hal::{Features, Limits};
+use hal::{DeviceType, Features, Limits};
 
 /// A version number for a specific component of an OpenGL implementation
 #[derive(Copy, Clone, Eq, Ord, PartialEq, PartialOrd)]
@@ -43,6 +43,25 @@ impl Version {
         (self.major, self.minor)
     }
 
+    /// Parse the driver version out of the vendor information,
+    /// packed as `major << 22 | minor << 12 | patch`. Returns 0 if not found.
+    pub fn driver_version(&self) -> u32 {
+        self.vendor_info
+            .split(' ')
+            .filter_map(|word| {
+                let mut it = word.split('.').map(|s| s.parse::<u32>().ok());
+                match (it.next(), it.next(), it.next()) {
+                    (Some(Some(major)), Some(Some(minor)), patch) => {
+                        let patch = patch.and_then(|p| p).unwrap_or(0);
+                        Some(((major & 0x3FF) << 22) | ((minor & 0x3FF) << 12) | (patch & 0xFFF))
+                    }
+                    _ => None,
+                }
+            })
+            .next()
+            .unwrap_or(0)
+    }
+
     /// According to the OpenGL specification, the version information is
     /// expected to follow the following syntax:
     ///
@@ -151,6 +170,46 @@ impl PlatformName {
             renderer: get_string(gl, gl::RENDERER),
         }
     }
+
+    fn contains_any(&self, patterns: &[&str]) -> bool {
+        let vendor = self.vendor.to_lowercase();
+        let renderer = self.renderer.to_lowercase();
+        patterns.iter().any(|p| vendor.contains(p) || renderer.contains(p))
+    }
+
+    /// Guess the PCI vendor id of the implementation, 0 if unknown.
+    pub fn vendor_id(&self) -> usize {
+        if self.contains_any(&["nvidia", "nouveau", "geforce"]) {
+            0x10DE
+        } else if self.contains_any(&["amd", "ati technologies", "radeon"]) {
+            0x1002
+        } else if self.contains_any(&["intel"]) {
+            0x8086
+        } else if self.contains_any(&["arm", "mali"]) {
+            0x13B5
+        } else if self.contains_any(&["qualcomm", "adreno"]) {
+            0x5143
+        } else if self.contains_any(&["imagination", "powervr"]) {
+            0x1010
+        } else {
+            0
+        }
+    }
+
+    /// Guess the type of the device from the vendor and renderer strings.
+    pub fn device_type(&self) -> DeviceType {
+        if self.contains_any(&["llvmpipe", "softpipe", "swiftshader", "software", "gdi generic"]) {
+            DeviceType::Cpu
+        } else if self.contains_any(&["virgl", "svga3d", "parallels", "virtualbox"]) {
+            DeviceType::VirtualGpu
+        } else if self.contains_any(&["tegra", "intel", "mali", "adreno", "powervr"]) {
+            DeviceType::IntegratedGpu
+        } else if self.contains_any(&["nvidia", "nouveau", "geforce", "radeon"]) {
+            DeviceType::DiscreteGpu
+        } else {
+            DeviceType::Other
+        }
+    }
 }
 
 /// Private capabilities that don't need to be exposed.
@@ -305,6 +364,7 @@ pub fn query_all(gl: &gl::Gl) -> (Info, Features, LegacyFeatures, Limits, Privat
         min_texel_buffer_offset_alignment: 1, // TODO
         min_uniform_buffer_offset_alignment: 1, // TODO
         min_storage_buffer_offset_alignment: 1, // TODO
+        buffer_image_granularity: 1,
         .. Limits::default()
     };
 
@@ -460,7 +520,8 @@ pub fn query_all(gl: &gl::Gl) -> (Info, Features, LegacyFeatures, Limits, Privat
 
 #[cfg(test)]
 mod tests {
-    use super::Version;
+    use hal::DeviceType;
+    use super::{PlatformName, Version};
 
     #[test]
     fn test_version_parse() {
@@ -479,4 +540,23 @@ mod tests {
         assert_eq!(Version::parse("OpenGL ES 2.0 Google Nexus"), Ok(Version::new_embedded(2, 0, "Google Nexus")));
         assert_eq!(Version::parse("GLSL ES 1.1"), Ok(Version::new_embedded(1, 1, "")));
     }
+
+    #[test]
+    fn test_driver_version() {
+        assert_eq!(Version::parse("4.6.0 NVIDIA 390.77").unwrap().driver_version(), (390 << 22) | (77 << 12));
+        assert_eq!(Version::parse("3.0 Mesa 18.0.5").unwrap().driver_version(), (18 << 22) | 5);
+        assert_eq!(Version::parse("4.5 (Core Profile)").unwrap().driver_version(), 0);
+    }
+
+    #[test]
+    fn test_platform_name() {
+        let llvmpipe = PlatformName { vendor: "VMware, Inc.", renderer: "llvmpipe (LLVM 6.0, 256 bits)" };
+        assert_eq!(llvmpipe.device_type(), DeviceType::Cpu);
+        let intel = PlatformName { vendor: "Intel Open Source Technology Center", renderer: "Mesa DRI Intel(R) HD Graphics 620 (Kaby Lake GT2)" };
+        assert_eq!(intel.vendor_id(), 0x8086);
+        assert_eq!(intel.device_type(), DeviceType::IntegratedGpu);
+        let nvidia = PlatformName { vendor: "NVIDIA Corporation", renderer: "GeForce GTX 1060 6GB/PCIe/SSE2" };
+        assert_eq!(nvidia.vendor_id(), 0x10DE);
+        assert_eq!(nvidia.device_type(), DeviceType::DiscreteGpu);
+    }
 }
@@ -26,6 +26,9 @@ use hal::queue::{Queues, QueueFamilyId};
 pub use self::device::Device;
 pub use self::info::{Info, PlatformName, Version};
 
+#[path = "../../auxil/heap_usage.rs"]
+mod heap_usage;
+
 mod command;
 mod conv;
 mod device;
@@ -194,6 +197,9 @@ impl PhysicalDevice {
             debug!("- {}", *extension);
         }
         let name = info.platform_name.renderer.into();
+        let vendor = info.platform_name.vendor_id();
+        let device_type = info.platform_name.device_type();
+        let driver_version = info.version.driver_version();
 
         // create the shared context
         let share = Share {
@@ -212,9 +218,11 @@ impl PhysicalDevice {
         hal::Adapter {
             info: hal::AdapterInfo {
                 name,
-                vendor: 0, // TODO
+                vendor,
                 device: 0, // TODO
-                software_rendering: false, // not always true ..
+                device_type,
+                driver_version,
+                software_rendering: device_type == hal::DeviceType::Cpu,
             },
             physical_device: PhysicalDevice(Starc::new(share)),
             queue_families: vec![QueueFamily],
@@ -267,7 +275,7 @@ impl hal::PhysicalDevice<Backend> for PhysicalDevice {
         }
 
         Ok(hal::Gpu {
-            device: Device::new(self.0.clone()),
+            device: Device::new(self.0.clone(), hal::PhysicalDevice::memory_properties(self)),
             queues: Queues::new(families
                 .into_iter()
                 .map(|&(proto_family, priorities)| {
@@ -281,8 +289,32 @@ impl hal::PhysicalDevice<Backend> for PhysicalDevice {
         })
     }
 
-    fn format_properties(&self, _: Option<hal::format::Format>) -> hal::format::Properties {
-        unimplemented!()
+    fn format_properties(&self, format: Option<hal::format::Format>) -> hal::format::Properties {
+        use hal::format::{BufferFeature, Format, ImageFeature};
+
+        let format = match format {
+            Some(format) => format,
+            None => return hal::format::Properties::default(),
+        };
+        // Only the formats `create_image` knows about can be used for images.
+        let optimal_tiling = match format {
+            Format::Rgba8Unorm | Format::Rgba8Srgb => {
+                ImageFeature::SAMPLED | ImageFeature::SAMPLED_LINEAR |
+                ImageFeature::COLOR_ATTACHMENT | ImageFeature::COLOR_ATTACHMENT_BLEND
+            }
+            _ => ImageFeature::empty(),
+        };
+        let buffer_features = if conv::format_to_gl_format(format).is_some() {
+            BufferFeature::VERTEX
+        } else {
+            BufferFeature::empty()
+        };
+
+        hal::format::Properties {
+            linear_tiling: ImageFeature::empty(),
+            optimal_tiling,
+            buffer_features,
+        }
     }
 
     fn image_format_properties(
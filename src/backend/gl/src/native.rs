@@ -1,6 +1,8 @@
 use std::cell::Cell;
+use std::collections::HashMap;
+use std::sync::Mutex;
 
-use hal::{format, image as i, pass, pso};
+use hal::{format, image as i, pass, pso, MemoryTypeId};
 use hal::memory::Properties;
 
 use gl;
@@ -108,16 +110,35 @@ impl pso::DescriptorPool<Backend> for DescriptorPool {
     }
 }
 
-#[derive(Clone, Debug, Hash)]
+#[derive(Debug)]
 pub enum ShaderModule {
     Raw(Shader),
-    Spirv(Vec<u8>),
+    Spirv(SpirvModule),
+}
+
+/// SPIR-V shader module, translated to GLSL on pipeline creation.
+#[derive(Debug)]
+pub struct SpirvModule {
+    pub(crate) spirv: Vec<u8>,
+    /// Compiled shaders, keyed by stage and the raw values of the specialization constants.
+    pub(crate) compiled: Mutex<HashMap<(pso::Stage, Vec<(u32, u64)>), Shader>>,
+}
+
+impl SpirvModule {
+    pub(crate) fn new(spirv: Vec<u8>) -> Self {
+        SpirvModule {
+            spirv,
+            compiled: Mutex::new(HashMap::new()),
+        }
+    }
 }
 
 #[derive(Debug)]
 pub struct Memory {
     pub(crate) properties: Properties,
     pub(crate) first_bound_buffer: Cell<RawBuffer>,
+    /// Memory type of the allocation
+    pub(crate) ty: MemoryTypeId,
     /// Allocation size
     pub(crate) size: u64,
 }
@@ -551,6 +551,16 @@ impl CommandQueue {
             com::Command::CopyImageToSurface(..) => {
                 unimplemented!() //TODO: use FBO
             }
+            com::Command::BlitFramebuffer(ref r, filter) => unsafe {
+                let gl = &self.share.context;
+                gl.BlitFramebuffer(
+                    r.src_bounds.start.x, r.src_bounds.start.y,
+                    r.src_bounds.end.x, r.src_bounds.end.y,
+                    r.dst_bounds.start.x, r.dst_bounds.start.y,
+                    r.dst_bounds.end.x, r.dst_bounds.end.y,
+                    gl::COLOR_BUFFER_BIT, filter,
+                );
+            }
             /*
             com::Command::BindConstantBuffer(pso::ConstantBufferParam(buffer, _, slot)) => unsafe {
                 self.share.context.BindBufferBase(gl::UNIFORM_BUFFER, slot as gl::types::GLuint, buffer);
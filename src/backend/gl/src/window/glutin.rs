@@ -124,7 +124,9 @@ impl hal::Surface<B> for Surface {
         hal::image::Kind::D2(ex.width, ex.height, 1, samples as _)
     }
 
-    fn capabilities_and_formats(&self, _: &PhysicalDevice) -> (hal::SurfaceCapabilities, Option<Vec<f::Format>>) {
+    fn capabilities_and_formats(
+        &self, _: &PhysicalDevice,
+    ) -> (hal::SurfaceCapabilities, Option<Vec<(f::Format, hal::window::ColorSpace)>>) {
         let ex = get_window_extent(&self.window);
         let extent = hal::window::Extent2D::from(ex);
 
@@ -136,7 +138,10 @@ impl hal::Surface<B> for Surface {
                 height: ex.height + 1,
             },
             max_image_layers: 1,
-        }, Some(self.swapchain_formats()))
+        }, Some(self.swapchain_formats()
+            .into_iter()
+            .map(|format| (format, hal::window::ColorSpace::SrgbNonlinear))
+            .collect()))
     }
 
     fn supports_queue_family(&self, _: &QueueFamily) -> bool { true }
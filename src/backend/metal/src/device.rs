@@ -28,6 +28,7 @@ use metal::{self,
 };
 use spirv_cross::{msl, spirv, ErrorCode as SpirvErrorCode};
 
+use heap_usage::HeapUsage;
 use range_alloc::RangeAllocator;
 
 
@@ -200,6 +201,7 @@ pub struct Device {
     pub(crate) shared: Arc<Shared>,
     private_caps: PrivateCapabilities,
     memory_types: [hal::MemoryType; 4],
+    heap_usage: HeapUsage,
 }
 unsafe impl Send for Device {}
 unsafe impl Sync for Device {}
@@ -328,6 +330,7 @@ impl hal::PhysicalDevice<Backend> for PhysicalDevice {
             shared: self.shared.clone(),
             private_caps: self.private_caps.clone(),
             memory_types: self.memory_types,
+            heap_usage: HeapUsage::new(&hal::PhysicalDevice::memory_properties(self)),
         };
 
         let mut queues = HashMap::new();
@@ -439,6 +442,7 @@ impl hal::PhysicalDevice<Backend> for PhysicalDevice {
             // Note: we issue Metal buffer-to-buffer copies on memory flush/invalidate,
             // and those need to operate on sizes being multiples of 4.
             non_coherent_atom_size: 4,
+            buffer_image_granularity: 1,
         }
     }
 }
@@ -1250,6 +1254,16 @@ impl hal::Device<Backend> for Device {
         debug!("unmap_memory of size {}", memory.size);
     }
 
+    fn memory_coherency(&self, memory: &n::Memory) -> mapping::Coherency {
+        match MemoryTypes::describe(memory.ty.0).0 {
+            MTLStorageMode::Managed => mapping::Coherency::NonCoherent {
+                atom_size: 4,
+                memory_size: memory.size,
+            },
+            _ => mapping::Coherency::Coherent,
+        }
+    }
+
     fn flush_mapped_memory_ranges<'a, I, R>(&self, iter: I)
     where
         I: IntoIterator,
@@ -1542,10 +1556,16 @@ impl hal::Device<Backend> for Device {
             n::MemoryHeap::Public(memory_type, cpu_buffer)
         };
 
-        Ok(n::Memory::new(heap, size))
+        self.heap_usage.allocate(memory_type, size);
+        Ok(n::Memory::new(heap, memory_type, size))
+    }
+
+    fn free_memory(&self, memory: n::Memory) {
+        self.heap_usage.free(memory.ty, memory.size);
     }
 
-    fn free_memory(&self, _memory: n::Memory) {
+    fn memory_budget(&self) -> Vec<memory::HeapBudget> {
+        self.heap_usage.budget()
     }
 
     fn create_buffer(
@@ -1593,6 +1613,8 @@ impl hal::Device<Backend> for Device {
             } else {
                 (MemoryTypes::all() ^ MemoryTypes::SHARED).bits()
             },
+            prefers_dedicated: false,
+            requires_dedicated: false,
         }
     }
 
@@ -1804,6 +1826,8 @@ impl hal::Device<Backend> for Device {
                 size: max_size,
                 alignment: max_alignment,
                 type_mask: types.bits(),
+                prefers_dedicated: false,
+                requires_dedicated: false,
             }
         } else if image.host_visible {
             assert_eq!(image.mip_sizes.len(), 1);
@@ -1816,12 +1840,16 @@ impl hal::Device<Backend> for Device {
                 } else {
                     (MemoryTypes::all() ^ MemoryTypes::SHARED).bits()
                 },
+                prefers_dedicated: false,
+                requires_dedicated: false,
             }
         } else {
             memory::Requirements {
                 size: image.mip_sizes.iter().sum(),
                 alignment: 4,
                 type_mask: MemoryTypes::PRIVATE.bits(),
+                prefers_dedicated: false,
+                requires_dedicated: false,
             }
         }
     }
@@ -18,6 +18,8 @@ extern crate metal_rs as metal;
 #[cfg(feature = "winit")]
 extern crate winit;
 
+#[path = "../../auxil/heap_usage.rs"]
+mod heap_usage;
 #[path = "../../auxil/range_alloc.rs"]
 mod range_alloc;
 mod device;
@@ -102,6 +104,8 @@ impl hal::Instance for Instance {
                     name,
                     vendor: 0,
                     device: 0,
+                    device_type: hal::DeviceType::Other, // TODO
+                    driver_version: 0,
                     software_rendering: false,
                 },
                 physical_device: device::PhysicalDevice::new(self.shared.clone()),
@@ -394,13 +394,15 @@ pub enum DescriptorSetBinding {
 #[derive(Debug)]
 pub struct Memory {
     pub(crate) heap: MemoryHeap,
+    pub(crate) ty: hal::MemoryTypeId,
     pub(crate) size: u64,
 }
 
 impl Memory {
-    pub(crate) fn new(heap: MemoryHeap, size: u64) -> Self {
+    pub(crate) fn new(heap: MemoryHeap, ty: hal::MemoryTypeId, size: u64) -> Self {
         Memory {
             heap,
+            ty,
             size,
         }
     }
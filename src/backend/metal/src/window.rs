@@ -8,7 +8,7 @@ use std::sync::{Arc, Mutex};
 
 use hal::{self, format, image};
 use hal::{Backbuffer, SwapchainConfig};
-use hal::window::Extent2D;
+use hal::window::{ColorSpace, Extent2D};
 
 use metal::{self, MTLPixelFormat, MTLTextureUsage};
 use objc::runtime::{Object};
@@ -72,14 +72,14 @@ impl hal::Surface<Backend> for Surface {
 
     fn capabilities_and_formats(
         &self, _: &PhysicalDevice,
-    ) -> (hal::SurfaceCapabilities, Option<Vec<format::Format>>) {
+    ) -> (hal::SurfaceCapabilities, Option<Vec<(format::Format, ColorSpace)>>) {
         let caps = hal::SurfaceCapabilities {
             image_count: 1..8,
             current_extent: None,
             extents: Extent2D { width: 4, height: 4} .. Extent2D { width: 4096, height: 4096 },
             max_image_layers: 1,
         };
-        let formats = Some(vec![format::Format::Rgba8Srgb]);
+        let formats = Some(vec![(format::Format::Rgba8Srgb, ColorSpace::SrgbNonlinear)]);
         (caps, formats)
     }
 
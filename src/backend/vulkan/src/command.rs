@@ -6,7 +6,7 @@ use smallvec::SmallVec;
 use ash::vk;
 use ash::version::DeviceV1_0;
 
-use hal::{buffer, command as com, memory, pso, query};
+use hal::{buffer, command as com, image, memory, pso, query};
 use hal::{DrawCount, IndexCount, InstanceCount, VertexCount, VertexOffset, WorkGroupCount};
 use hal::format::Aspects;
 use hal::image::{Filter, Layout, SubresourceRange};
@@ -211,20 +211,32 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
                         dst_access_mask: conv::map_image_access(access.end),
                     });
                 }
-                memory::Barrier::Buffer { ref states, target} => {
+                memory::Barrier::Aliasing { .. } => {
+                    // Vulkan has no dedicated aliasing barrier, the new image is
+                    // transitioned from an undefined layout separately.
+                    global_bars.push(vk::MemoryBarrier {
+                        s_type: vk::StructureType::MemoryBarrier,
+                        p_next: ptr::null(),
+                        src_access_mask: conv::map_image_access(image::Access::MEMORY_WRITE),
+                        dst_access_mask: conv::map_image_access(image::Access::MEMORY_READ | image::Access::MEMORY_WRITE),
+                    });
+                }
+                memory::Barrier::Buffer { ref states, target, ref families } => {
+                    let (src_family, dst_family) = conv::map_queue_families(families);
                     buffer_bars.push(vk::BufferMemoryBarrier {
                         s_type: vk::StructureType::BufferMemoryBarrier,
                         p_next: ptr::null(),
                         src_access_mask: conv::map_buffer_access(states.start),
                         dst_access_mask: conv::map_buffer_access(states.end),
-                        src_queue_family_index: vk::VK_QUEUE_FAMILY_IGNORED, // TODO
-                        dst_queue_family_index: vk::VK_QUEUE_FAMILY_IGNORED, // TODO
+                        src_queue_family_index: src_family,
+                        dst_queue_family_index: dst_family,
                         buffer: target.raw,
                         offset: 0,
                         size: vk::VK_WHOLE_SIZE,
                     });
                 }
-                memory::Barrier::Image { ref states, target, ref range } => {
+                memory::Barrier::Image { ref states, target, ref range, ref families } => {
+                    let (src_family, dst_family) = conv::map_queue_families(families);
                     let subresource_range = conv::map_subresource_range(range);
                     image_bars.push(vk::ImageMemoryBarrier {
                         s_type: vk::StructureType::ImageMemoryBarrier,
@@ -233,8 +245,8 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
                         dst_access_mask: conv::map_image_access(states.end.0),
                         old_layout: conv::map_image_layout(states.start.1),
                         new_layout: conv::map_image_layout(states.end.1),
-                        src_queue_family_index: vk::VK_QUEUE_FAMILY_IGNORED, // TODO
-                        dst_queue_family_index: vk::VK_QUEUE_FAMILY_IGNORED, // TODO
+                        src_queue_family_index: src_family,
+                        dst_queue_family_index: dst_family,
                         image: target.raw,
                         subresource_range,
                     });
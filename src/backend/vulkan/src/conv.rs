@@ -2,7 +2,7 @@ use ash::vk;
 use byteorder::{NativeEndian, WriteBytesExt};
 use smallvec::SmallVec;
 
-use hal::{buffer, command, format, image, pass, pso, query};
+use hal::{buffer, command, format, image, pass, pso, query, queue, window};
 use hal::{IndexType, Primitive};
 use hal::range::RangeArg;
 
@@ -11,16 +11,42 @@ use native as n;
 use std::{io, mem};
 use std::borrow::Borrow;
 use std::ptr;
+use std::ops::Range;
 
 
+/// Map to the raw `VkFormat` value.
+///
+/// Multi-planar formats are appended after the core formats in HAL,
+/// but use extension values in Vulkan, which `ash` doesn't list yet.
+/// These have to be passed through raw values only.
+pub fn map_format_raw(format: format::Format) -> i32 {
+    use hal::format::Format::*;
+    match format {
+        Nv12Unorm => 1000156003,
+        Nv16Unorm => 1000156005,
+        P010Unorm => 1000156013,
+        Yuv420Unorm => 1000156002,
+        Yuv422Unorm => 1000156004,
+        Yuv444Unorm => 1000156006,
+        // HAL format values are equal to Vulkan format values for core formats
+        _ => format as i32,
+    }
+}
+
+/// Map a core format, panics on multi-planar formats.
 pub fn map_format(format: format::Format) -> vk::Format {
+    let raw = map_format_raw(format);
+    assert!(
+        raw <= format::Format::Astc12x12Srgb as i32,
+        "{:?} is not supported here", format
+    );
     // Safe due to equivalence of HAL format values and Vulkan format values
-    unsafe { mem::transmute(format) }
+    unsafe { mem::transmute(raw) }
 }
 
 pub fn map_vk_format(format: vk::Format) -> Option<format::Format> {
-    if (format as usize) < format::NUM_FORMATS &&
-        format != vk::Format::Undefined
+    if format != vk::Format::Undefined &&
+        format as i32 <= format::Format::Astc12x12Srgb as i32
     {
         // Safe due to equivalence of HAL format values and Vulkan format values
         Some(unsafe { mem::transmute(format) })
@@ -29,6 +55,93 @@ pub fn map_vk_format(format: vk::Format) -> Option<format::Format> {
     }
 }
 
+pub fn map_ycbcr_model(model: image::YcbcrModel) -> i32 {
+    use hal::image::YcbcrModel::*;
+    match model {
+        RgbIdentity => 0,
+        YcbcrIdentity => 1,
+        Bt709 => 2,
+        Bt601 => 3,
+        Bt2020 => 4,
+    }
+}
+
+pub fn map_ycbcr_range(range: image::YcbcrRange) -> i32 {
+    match range {
+        image::YcbcrRange::Full => 0,
+        image::YcbcrRange::Narrow => 1,
+    }
+}
+
+pub fn map_chroma_location(location: image::ChromaLocation) -> i32 {
+    match location {
+        image::ChromaLocation::CositedEven => 0,
+        image::ChromaLocation::Midpoint => 1,
+    }
+}
+
+/// Map a raw format value reported by the driver, which might not be known to `ash`.
+pub fn map_vk_format_raw(raw: i32) -> Option<format::Format> {
+    use hal::format::Format::*;
+    match raw {
+        1000156002 => Some(Yuv420Unorm),
+        1000156003 => Some(Nv12Unorm),
+        1000156004 => Some(Yuv422Unorm),
+        1000156005 => Some(Nv16Unorm),
+        1000156006 => Some(Yuv444Unorm),
+        1000156013 => Some(P010Unorm),
+        // Safe as core format values are contiguous
+        _ if raw > 0 && raw <= Astc12x12Srgb as i32 => {
+            map_vk_format(unsafe { mem::transmute(raw) })
+        }
+        _ => None,
+    }
+}
+
+/// Map to the raw `VkColorSpaceKHR` value, `ash` only knows about sRGB.
+pub fn map_color_space(color_space: window::ColorSpace) -> i32 {
+    use hal::window::ColorSpace::*;
+    match color_space {
+        SrgbNonlinear => 0,
+        DisplayP3Nonlinear => 1000104001,
+        ExtendedSrgbLinear => 1000104002,
+        DisplayP3Linear => 1000104003,
+        DciP3Nonlinear => 1000104004,
+        Bt709Linear => 1000104005,
+        Bt709Nonlinear => 1000104006,
+        Bt2020Linear => 1000104007,
+        Hdr10St2084 => 1000104008,
+        DolbyVision => 1000104009,
+        Hdr10Hlg => 1000104010,
+        AdobeRgbLinear => 1000104011,
+        AdobeRgbNonlinear => 1000104012,
+        PassThrough => 1000104013,
+        ExtendedSrgbNonlinear => 1000104014,
+    }
+}
+
+pub fn map_vk_color_space(raw: i32) -> Option<window::ColorSpace> {
+    use hal::window::ColorSpace::*;
+    Some(match raw {
+        0 => SrgbNonlinear,
+        1000104001 => DisplayP3Nonlinear,
+        1000104002 => ExtendedSrgbLinear,
+        1000104003 => DisplayP3Linear,
+        1000104004 => DciP3Nonlinear,
+        1000104005 => Bt709Linear,
+        1000104006 => Bt709Nonlinear,
+        1000104007 => Bt2020Linear,
+        1000104008 => Hdr10St2084,
+        1000104009 => DolbyVision,
+        1000104010 => Hdr10Hlg,
+        1000104011 => AdobeRgbLinear,
+        1000104012 => AdobeRgbNonlinear,
+        1000104013 => PassThrough,
+        1000104014 => ExtendedSrgbNonlinear,
+        _ => return None,
+    })
+}
+
 pub fn map_tiling(tiling: image::Tiling) -> vk::ImageTiling {
     unsafe { mem::transmute(tiling) }
 }
@@ -171,6 +284,28 @@ pub fn map_pipeline_stage(stage: pso::PipelineStage) -> vk::PipelineStageFlags {
     unsafe { mem::transmute(stage) }
 }
 
+pub fn map_sharing_mode(families: &[queue::QueueFamilyId]) -> (vk::SharingMode, SmallVec<[u32; 4]>) {
+    let mut indices = families
+        .iter()
+        .map(|family| family.0 as u32)
+        .collect::<SmallVec<[u32; 4]>>();
+    indices.sort();
+    indices.dedup();
+    // Concurrent sharing requires at least two distinct families.
+    if indices.len() > 1 {
+        (vk::SharingMode::Concurrent, indices)
+    } else {
+        (vk::SharingMode::Exclusive, SmallVec::new())
+    }
+}
+
+pub fn map_queue_families(families: &Option<Range<queue::QueueFamilyId>>) -> (u32, u32) {
+    match *families {
+        Some(ref families) => (families.start.0 as u32, families.end.0 as u32),
+        None => (vk::VK_QUEUE_FAMILY_IGNORED, vk::VK_QUEUE_FAMILY_IGNORED),
+    }
+}
+
 pub fn map_buffer_usage(usage: buffer::Usage) -> vk::BufferUsageFlags {
     // Safe due to equivalence of HAL values and Vulkan values
     unsafe { mem::transmute(usage) }
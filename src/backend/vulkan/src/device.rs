@@ -3,10 +3,10 @@ use ash::extensions as ext;
 use ash::version::DeviceV1_0;
 use smallvec::SmallVec;
 
-use hal::{buffer, device as d, format, image, mapping, pass, pso, query, queue, window};
+use hal::{buffer, device as d, format, image, mapping, memory, pass, pso, query, queue, window};
 use hal::{Backbuffer, Features, MemoryTypeId, SwapchainConfig};
 use hal::error::HostExecutionError;
-use hal::memory::Requirements;
+use hal::memory::{HeapBudget, Properties, Requirements};
 use hal::pool::CommandPoolCreateFlags;
 use hal::range::RangeArg;
 
@@ -14,10 +14,12 @@ use std::{mem, ptr};
 use std::borrow::Borrow;
 use std::collections::VecDeque;
 use std::ffi::CString;
+use std::os::raw::c_void;
 use std::ops::Range;
 use std::sync::Arc;
 
-use {Backend as B, Device};
+use {Backend as B, Device, MemoryDedicatedAllocateInfo, STRUCTURE_TYPE_MEMORY_DEDICATED_ALLOCATE_INFO};
+use SamplerYcbcrConversionInfo;
 use {conv, native as n, result, window as w};
 use pool::RawCommandPool;
 
@@ -28,7 +30,82 @@ pub struct UnboundBuffer(n::Buffer);
 #[derive(Debug)]
 pub struct UnboundImage(n::Image);
 
+/// `VkImageCreateInfo` with a raw format, as `ash` doesn't know about multi-planar formats.
+#[repr(C)]
+struct RawImageCreateInfo {
+    s_type: vk::StructureType,
+    p_next: *const c_void,
+    flags: vk::ImageCreateFlags,
+    image_type: vk::ImageType,
+    format: i32,
+    extent: vk::Extent3D,
+    mip_levels: u32,
+    array_layers: u32,
+    samples: vk::SampleCountFlags,
+    tiling: vk::ImageTiling,
+    usage: vk::ImageUsageFlags,
+    sharing_mode: vk::SharingMode,
+    queue_family_index_count: u32,
+    p_queue_family_indices: *const u32,
+    initial_layout: vk::ImageLayout,
+}
+
+/// `VkImageViewCreateInfo` with a raw format, as `ash` doesn't know about multi-planar formats.
+#[repr(C)]
+struct RawImageViewCreateInfo {
+    s_type: vk::StructureType,
+    p_next: *const c_void,
+    flags: vk::ImageViewCreateFlags,
+    image: vk::Image,
+    view_type: vk::ImageViewType,
+    format: i32,
+    components: vk::ComponentMapping,
+    subresource_range: vk::ImageSubresourceRange,
+}
+
+/// `VkSwapchainCreateInfoKHR` with a raw color space, as `ash` doesn't know about
+/// the color spaces of `VK_EXT_swapchain_colorspace`.
+#[repr(C)]
+struct RawSwapchainCreateInfo {
+    s_type: vk::StructureType,
+    p_next: *const c_void,
+    flags: vk::SwapchainCreateFlagsKHR,
+    surface: vk::SurfaceKHR,
+    min_image_count: u32,
+    image_format: vk::Format,
+    image_color_space: i32,
+    image_extent: vk::Extent2D,
+    image_array_layers: u32,
+    image_usage: vk::ImageUsageFlags,
+    image_sharing_mode: vk::SharingMode,
+    queue_family_index_count: u32,
+    p_queue_family_indices: *const u32,
+    pre_transform: vk::SurfaceTransformFlagsKHR,
+    composite_alpha: vk::CompositeAlphaFlagsKHR,
+    present_mode: vk::PresentModeKHR,
+    clipped: vk::Bool32,
+    old_swapchain: vk::SwapchainKHR,
+}
+
 impl Device {
+    fn allocate_memory_impl(
+        &self, mem_type: MemoryTypeId, size: u64, p_next: *const c_void,
+    ) -> Result<n::Memory, d::OutOfMemory> {
+        let info = vk::MemoryAllocateInfo {
+            s_type: vk::StructureType::MemoryAllocateInfo,
+            p_next,
+            allocation_size: size,
+            memory_type_index: mem_type.0 as _,
+        };
+
+        let memory = unsafe {
+            self.raw.0.allocate_memory(&info, None)
+        }.expect("Error on memory allocation"); // TODO: error handling
+
+        self.heap_usage.allocate(mem_type, size);
+        Ok(n::Memory { raw: memory, ty: mem_type, size })
+    }
+
     #[cfg(feature = "glsl-to-spirv")]
     pub fn create_shader_module_from_glsl(
         &self,
@@ -57,22 +134,92 @@ impl Device {
             Err(string) => Err(d::ShaderError::CompilationFailed(string)),
         }
     }
+
+    /// Returns the conversion info to chain into sampler and image view
+    /// create infos, if the conversion is requested and supported.
+    fn ycbcr_conversion_info(
+        &self, conversion: Option<&image::YcbcrConversion>,
+    ) -> Option<SamplerYcbcrConversionInfo> {
+        let conversion = conversion?;
+        match self.ycbcr_conversion {
+            Some(ref conversions) => Some(conversions.info(self.raw.0.handle(), conversion)),
+            None => {
+                warn!("Y'CbCr conversion was requested on a device without support");
+                None
+            }
+        }
+    }
+
+    fn create_image_view_impl(
+        &self,
+        image: &n::Image,
+        kind: image::ViewKind,
+        format: format::Format,
+        swizzle: format::Swizzle,
+        range: image::SubresourceRange,
+        conversion: Option<&image::YcbcrConversion>,
+    ) -> Result<n::ImageView, image::ViewError> {
+        let is_cube = image.flags.intersects(vk::IMAGE_CREATE_CUBE_COMPATIBLE_BIT);
+        let conversion_info = self.ycbcr_conversion_info(conversion);
+        let info = RawImageViewCreateInfo {
+            s_type: vk::StructureType::ImageViewCreateInfo,
+            p_next: conversion_info
+                .as_ref()
+                .map_or(ptr::null(), |info| info as *const _ as *const c_void),
+            flags: vk::ImageViewCreateFlags::empty(),
+            image: image.raw,
+            view_type: match conv::map_view_kind(kind, image.ty, is_cube) {
+                Some(ty) => ty,
+                None => return Err(image::ViewError::BadKind),
+            },
+            format: conv::map_format_raw(format),
+            components: conv::map_swizzle(swizzle),
+            subresource_range: conv::map_subresource_range(&range),
+        };
+
+        let mut view = vk::ImageView::null();
+        let result = unsafe {
+            self.raw.0.fp_v1_0().create_image_view(
+                self.raw.0.handle(),
+                &info as *const _ as *const vk::ImageViewCreateInfo,
+                ptr::null(),
+                &mut view,
+            )
+        };
+        if result != vk::Result::Success {
+            panic!("Error on image view creation: {:?}", result); // TODO
+        }
+
+        Ok(n::ImageView {
+            image: image.raw,
+            view,
+            range,
+        })
+    }
 }
 
 impl d::Device<B> for Device {
     fn allocate_memory(&self, mem_type: MemoryTypeId, size: u64) -> Result<n::Memory, d::OutOfMemory> {
-        let info = vk::MemoryAllocateInfo {
-            s_type: vk::StructureType::MemoryAllocateInfo,
+        self.allocate_memory_impl(mem_type, size, ptr::null())
+    }
+
+    fn allocate_dedicated_memory(
+        &self, mem_type: MemoryTypeId, size: u64, dedicated: memory::Dedicated<B>,
+    ) -> Result<n::Memory, d::OutOfMemory> {
+        if self.dedicated_allocation.is_none() {
+            return self.allocate_memory(mem_type, size);
+        }
+        let (image, buffer) = match dedicated {
+            memory::Dedicated::Buffer(buffer) => (vk::Image::null(), (buffer.0).raw),
+            memory::Dedicated::Image(image) => (image.0.raw, vk::Buffer::null()),
+        };
+        let info = MemoryDedicatedAllocateInfo {
+            s_type: STRUCTURE_TYPE_MEMORY_DEDICATED_ALLOCATE_INFO,
             p_next: ptr::null(),
-            allocation_size: size,
-            memory_type_index: mem_type.0 as _,
+            image,
+            buffer,
         };
-
-        let memory = unsafe {
-            self.raw.0.allocate_memory(&info, None)
-        }.expect("Error on memory allocation"); // TODO: error handling
-
-        Ok(n::Memory { raw: memory })
+        self.allocate_memory_impl(mem_type, size, &info as *const _ as *const c_void)
     }
 
     fn create_command_pool(
@@ -850,9 +997,12 @@ impl d::Device<B> for Device {
                 }
             },
         };
+        let conversion_info = self.ycbcr_conversion_info(sampler_info.ycbcr_conversion.as_ref());
         let info = vk::SamplerCreateInfo {
             s_type: vk::StructureType::SamplerCreateInfo,
-            p_next: ptr::null(),
+            p_next: conversion_info
+                .as_ref()
+                .map_or(ptr::null(), |info| info as *const _ as *const c_void),
             flags: vk::SamplerCreateFlags::empty(),
             mag_filter: conv::map_filter(sampler_info.mag_filter),
             min_filter: conv::map_filter(sampler_info.min_filter),
@@ -887,15 +1037,22 @@ impl d::Device<B> for Device {
 
     ///
     fn create_buffer(&self, size: u64, usage: buffer::Usage) -> Result<UnboundBuffer, buffer::CreationError> {
+        self.create_buffer_concurrent(size, usage, &[])
+    }
+
+    fn create_buffer_concurrent(
+        &self, size: u64, usage: buffer::Usage, families: &[queue::QueueFamilyId],
+    ) -> Result<UnboundBuffer, buffer::CreationError> {
+        let (sharing_mode, family_indices) = conv::map_sharing_mode(families);
         let info = vk::BufferCreateInfo {
             s_type: vk::StructureType::BufferCreateInfo,
             p_next: ptr::null(),
             flags: vk::BufferCreateFlags::empty(), // TODO:
             size,
             usage: conv::map_buffer_usage(usage),
-            sharing_mode: vk::SharingMode::Exclusive, // TODO:
-            queue_family_index_count: 0,
-            p_queue_family_indices: ptr::null(),
+            sharing_mode,
+            queue_family_index_count: family_indices.len() as u32,
+            p_queue_family_indices: family_indices.as_ptr(),
         };
 
         let buffer = unsafe {
@@ -907,12 +1064,17 @@ impl d::Device<B> for Device {
     }
 
     fn get_buffer_requirements(&self, buffer: &UnboundBuffer) -> Requirements {
+        if let Some(ref dedicated) = self.dedicated_allocation {
+            return dedicated.buffer_requirements(self.raw.0.handle(), (buffer.0).raw);
+        }
         let req = self.raw.0.get_buffer_memory_requirements((buffer.0).raw);
 
         Requirements {
             size: req.size,
             alignment: req.alignment,
             type_mask: req.memory_type_bits as _,
+            prefers_dedicated: false,
+            requires_dedicated: false,
         }
     }
 
@@ -958,6 +1120,20 @@ impl d::Device<B> for Device {
         usage: image::Usage,
         storage_flags: image::StorageFlags,
     ) -> Result<UnboundImage, image::CreationError> {
+        self.create_image_concurrent(kind, mip_levels, format, tiling, usage, storage_flags, &[])
+    }
+
+    fn create_image_concurrent(
+        &self,
+        kind: image::Kind,
+        mip_levels: image::Level,
+        format: format::Format,
+        tiling: image::Tiling,
+        usage: image::Usage,
+        storage_flags: image::StorageFlags,
+        families: &[queue::QueueFamilyId],
+    ) -> Result<UnboundImage, image::CreationError> {
+        let (sharing_mode, family_indices) = conv::map_sharing_mode(families);
         let flags = conv::map_image_flags(storage_flags);
         let extent = conv::map_extent(kind.extent());
         let array_layers = kind.num_layers();
@@ -968,38 +1144,52 @@ impl d::Device<B> for Device {
             image::Kind::D3(..) => vk::ImageType::Type3d,
         };
 
-        let info = vk::ImageCreateInfo {
+        let info = RawImageCreateInfo {
             s_type: vk::StructureType::ImageCreateInfo,
             p_next: ptr::null(),
             flags,
             image_type,
-            format: conv::map_format(format),
+            format: conv::map_format_raw(format),
             extent: extent.clone(),
             mip_levels: mip_levels as u32,
             array_layers: array_layers as u32,
             samples: vk::SampleCountFlags::from_flags_truncate(samples),
             tiling: conv::map_tiling(tiling),
             usage: conv::map_image_usage(usage),
-            sharing_mode: vk::SharingMode::Exclusive, // TODO:
-            queue_family_index_count: 0,
-            p_queue_family_indices: ptr::null(),
+            sharing_mode,
+            queue_family_index_count: family_indices.len() as u32,
+            p_queue_family_indices: family_indices.as_ptr(),
             initial_layout: vk::ImageLayout::Undefined,
         };
 
-        let raw = unsafe {
-            self.raw.0.create_image(&info, None)
-        }.expect("Error on image creation"); // TODO: error handling
+        let mut raw = vk::Image::null();
+        let result = unsafe {
+            self.raw.0.fp_v1_0().create_image(
+                self.raw.0.handle(),
+                &info as *const _ as *const vk::ImageCreateInfo,
+                ptr::null(),
+                &mut raw,
+            )
+        };
+        if result != vk::Result::Success {
+            panic!("Error on image creation: {:?}", result); // TODO: error handling
+        }
 
         Ok(UnboundImage(n::Image{ raw, ty: image_type, flags, extent }))
     }
 
     fn get_image_requirements(&self, image: &UnboundImage) -> Requirements {
+        if let Some(ref dedicated) = self.dedicated_allocation {
+            return dedicated.image_requirements(self.raw.0.handle(), image.0.raw);
+        }
         let req = self.raw.0.get_image_memory_requirements(image.0.raw);
 
         Requirements {
             size: req.size,
             alignment: req.alignment,
             type_mask: req.memory_type_bits as _,
+            prefers_dedicated: false,
+            requires_dedicated: false,
         }
     }
 
@@ -1035,30 +1225,19 @@ impl d::Device<B> for Device {
         swizzle: format::Swizzle,
         range: image::SubresourceRange,
     ) -> Result<n::ImageView, image::ViewError> {
-        let is_cube = image.flags.intersects(vk::IMAGE_CREATE_CUBE_COMPATIBLE_BIT);
-        let info = vk::ImageViewCreateInfo {
-            s_type: vk::StructureType::ImageViewCreateInfo,
-            p_next: ptr::null(),
-            flags: vk::ImageViewCreateFlags::empty(),
-            image: image.raw,
-            view_type: match conv::map_view_kind(kind, image.ty, is_cube) {
-                Some(ty) => ty,
-                None => return Err(image::ViewError::BadKind),
-            },
-            format: conv::map_format(format),
-            components: conv::map_swizzle(swizzle),
-            subresource_range: conv::map_subresource_range(&range),
-        };
-
-        let view = unsafe {
-            self.raw.0.create_image_view(&info, None)
-        }.expect("Error on image view creation"); // TODO
+        self.create_image_view_impl(image, kind, format, swizzle, range, None)
+    }
 
-        Ok(n::ImageView {
-            image: image.raw,
-            view,
-            range,
-        })
+    fn create_image_view_with_conversion(
+        &self,
+        image: &n::Image,
+        kind: image::ViewKind,
+        format: format::Format,
+        swizzle: format::Swizzle,
+        range: image::SubresourceRange,
+        conversion: &image::YcbcrConversion,
+    ) -> Result<n::ImageView, image::ViewError> {
+        self.create_image_view_impl(image, kind, format, swizzle, range, Some(conversion))
     }
 
     fn create_descriptor_pool<T>(&self, max_sets: usize, descriptor_pools: T) -> n::DescriptorPool
@@ -1313,6 +1492,17 @@ impl d::Device<B> for Device {
         unsafe { self.raw.0.unmap_memory(memory.raw) }
     }
 
+    fn memory_coherency(&self, memory: &n::Memory) -> mapping::Coherency {
+        if self.memory_types[memory.ty.0].properties.contains(Properties::COHERENT) {
+            mapping::Coherency::Coherent
+        } else {
+            mapping::Coherency::NonCoherent {
+                atom_size: self.non_coherent_atom_size,
+                memory_size: memory.size,
+            }
+        }
+    }
+
     fn flush_mapped_memory_ranges<'a, I, R>(&self, ranges: I)
     where
         I: IntoIterator,
@@ -1418,9 +1608,17 @@ impl d::Device<B> for Device {
     }
 
     fn free_memory(&self, memory: n::Memory) {
+        self.heap_usage.free(memory.ty, memory.size);
         unsafe { self.raw.0.free_memory(memory.raw, None); }
     }
 
+    fn memory_budget(&self) -> Vec<HeapBudget> {
+        match self.memory_budget {
+            Some(ref query) => query.query(),
+            None => self.heap_usage.budget(),
+        }
+    }
+
     fn create_query_pool(&self, ty: query::QueryType, query_count: u32) -> n::QueryPool {
         let (query_type, pipeline_statistics) = match ty {
             query::QueryType::Occlusion =>
@@ -1472,14 +1670,29 @@ impl d::Device<B> for Device {
         surface.width = extent.width;
         surface.height = extent.height;
 
-        let info = vk::SwapchainCreateInfoKHR {
+        let color_space = conv::map_color_space(config.color_space);
+        if config.color_space != window::ColorSpace::SrgbNonlinear {
+            let color_format = conv::map_format_raw(format);
+            let supported = surface.raw
+                .surface_formats(self.physical_device)
+                .iter()
+                .any(|sf| sf.format == color_format && sf.color_space == color_space);
+            if !supported {
+                panic!(
+                    "Swapchain color space {:?} can't be presented with {:?}",
+                    config.color_space, config.color_format,
+                );
+            }
+        }
+
+        let info = RawSwapchainCreateInfo {
             s_type: vk::StructureType::SwapchainCreateInfoKhr,
             p_next: ptr::null(),
             flags: vk::SwapchainCreateFlagsKHR::empty(),
             surface: surface.raw.handle,
             min_image_count: config.image_count,
             image_format: conv::map_format(format),
-            image_color_space: vk::ColorSpaceKHR::SrgbNonlinear,
+            image_color_space: color_space,
             image_extent: vk::Extent2D {
                 width: surface.width,
                 height: surface.height,
@@ -1496,8 +1709,18 @@ impl d::Device<B> for Device {
             old_swapchain,
         };
 
-        let swapchain_raw = unsafe { functor.create_swapchain_khr(&info, None) }
-            .expect("Unable to create a swapchain");
+        let mut swapchain_raw = vk::SwapchainKHR::null();
+        let result = unsafe {
+            self.swapchain_fn.create_swapchain_khr(
+                self.raw.0.handle(),
+                &info as *const _ as *const vk::SwapchainCreateInfoKHR,
+                ptr::null(),
+                &mut swapchain_raw,
+            )
+        };
+        if result != vk::Result::Success {
+            panic!("Unable to create a swapchain: {:?}", result);
+        }
 
         let backbuffer_images = functor.get_swapchain_images_khr(swapchain_raw)
             .expect("Unable to get swapchain images");
@@ -32,7 +32,12 @@ use hal::error::{DeviceCreationError, HostExecutionError};
 use std::{fmt, mem, ptr};
 use std::borrow::{Borrow, BorrowMut};
 use std::ffi::{CStr, CString};
-use std::sync::Arc;
+use std::os::raw::c_void;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+
+#[path = "../../auxil/heap_usage.rs"]
+mod heap_usage;
 
 mod command;
 mod conv;
@@ -47,9 +52,27 @@ const LAYERS: &'static [&'static str] = &[
     #[cfg(debug_assertions)]
     "VK_LAYER_LUNARG_standard_validation",
 ];
+const KHR_GET_PHYSICAL_DEVICE_PROPERTIES2: &'static str = "VK_KHR_get_physical_device_properties2";
+const EXT_MEMORY_BUDGET: &'static str = "VK_EXT_memory_budget";
+const KHR_GET_MEMORY_REQUIREMENTS2: &'static str = "VK_KHR_get_memory_requirements2";
+const KHR_DEDICATED_ALLOCATION: &'static str = "VK_KHR_dedicated_allocation";
+const EXT_SWAPCHAIN_COLORSPACE: &'static str = "VK_EXT_swapchain_colorspace";
+const KHR_MAINTENANCE1: &'static str = "VK_KHR_maintenance1";
+const KHR_BIND_MEMORY2: &'static str = "VK_KHR_bind_memory2";
+const KHR_SAMPLER_YCBCR_CONVERSION: &'static str = "VK_KHR_sampler_ycbcr_conversion";
+/// Extensions required for sampler Y'CbCr conversion.
+const YCBCR_CONVERSION_EXTENSIONS: &'static [&'static str] = &[
+    KHR_MAINTENANCE1,
+    KHR_BIND_MEMORY2,
+    KHR_GET_MEMORY_REQUIREMENTS2,
+    KHR_SAMPLER_YCBCR_CONVERSION,
+];
+
 const EXTENSIONS: &'static [&'static str] = &[
     #[cfg(debug_assertions)]
     "VK_EXT_debug_report",
+    KHR_GET_PHYSICAL_DEVICE_PROPERTIES2,
+    EXT_SWAPCHAIN_COLORSPACE,
 ];
 const DEVICE_EXTENSIONS: &'static [&'static str] = &[
     vk::VK_KHR_SWAPCHAIN_EXTENSION_NAME,
@@ -250,11 +273,24 @@ impl hal::Instance for Instance {
     type Backend = Backend;
 
     fn enumerate_adapters(&self) -> Vec<hal::Adapter<Backend>> {
+        let format_queries = FormatQueries::load(&self.raw);
         self.raw.0.enumerate_physical_devices()
             .expect("Unable to enumerate adapter")
             .into_iter()
             .map(|device| {
                 let properties = self.raw.0.get_physical_device_properties(device);
+                let memory_properties2 = if self.extensions.contains(&KHR_GET_PHYSICAL_DEVICE_PROPERTIES2) {
+                    let entry = VK_ENTRY.as_ref().expect("Unable to load Vulkan entry points");
+                    let name = CString::new("vkGetPhysicalDeviceMemoryProperties2KHR").unwrap();
+                    let function = entry.get_instance_proc_addr(self.raw.0.handle(), name.as_ptr());
+                    if function.is_null() {
+                        None
+                    } else {
+                        Some(unsafe { mem::transmute::<_, GetPhysicalDeviceMemoryProperties2>(function) })
+                    }
+                } else {
+                    None
+                };
                 let info = hal::AdapterInfo {
                     name: unsafe {
                         CStr::from_ptr(properties.device_name.as_ptr())
@@ -264,12 +300,22 @@ impl hal::Instance for Instance {
                     },
                     vendor: properties.vendor_id as usize,
                     device: properties.device_id as usize,
+                    device_type: match properties.device_type {
+                        vk::PhysicalDeviceType::IntegratedGpu => hal::DeviceType::IntegratedGpu,
+                        vk::PhysicalDeviceType::DiscreteGpu => hal::DeviceType::DiscreteGpu,
+                        vk::PhysicalDeviceType::VirtualGpu => hal::DeviceType::VirtualGpu,
+                        vk::PhysicalDeviceType::Cpu => hal::DeviceType::Cpu,
+                        _ => hal::DeviceType::Other,
+                    },
+                    driver_version: properties.driver_version,
                     software_rendering: properties.device_type == vk::PhysicalDeviceType::Cpu,
                 };
                 let physical_device = PhysicalDevice {
                     instance: self.raw.clone(),
                     handle: device,
                     properties,
+                    memory_properties2,
+                    format_queries,
                 };
                 let queue_families = self.raw.0
                     .get_physical_device_queue_family_properties(device)
@@ -316,6 +362,54 @@ pub struct PhysicalDevice {
     instance: Arc<RawInstance>,
     handle: vk::PhysicalDevice,
     properties: vk::PhysicalDeviceProperties,
+    /// Available if `VK_KHR_get_physical_device_properties2` is enabled.
+    memory_properties2: Option<GetPhysicalDeviceMemoryProperties2>,
+    format_queries: FormatQueries,
+}
+
+/// Format queries taking raw format values, as `ash` doesn't know about
+/// multi-planar formats.
+#[derive(Clone, Copy)]
+struct FormatQueries {
+    properties: GetPhysicalDeviceFormatProperties,
+    image_properties: GetPhysicalDeviceImageFormatProperties,
+}
+
+type GetPhysicalDeviceFormatProperties = unsafe extern "system" fn(
+    vk::PhysicalDevice, i32, *mut vk::FormatProperties,
+);
+type GetPhysicalDeviceImageFormatProperties = unsafe extern "system" fn(
+    vk::PhysicalDevice, i32, vk::ImageType, vk::ImageTiling, vk::ImageUsageFlags,
+    vk::ImageCreateFlags, *mut vk::ImageFormatProperties,
+) -> vk::Result;
+
+impl FormatQueries {
+    fn load(instance: &RawInstance) -> Self {
+        let entry = VK_ENTRY.as_ref().expect("Unable to load Vulkan entry points");
+        let load = |name: &str| {
+            let name = CString::new(name).unwrap();
+            let function = entry.get_instance_proc_addr(instance.0.handle(), name.as_ptr());
+            assert!(!function.is_null(), "Unable to load {:?}", name);
+            function
+        };
+        let properties = load("vkGetPhysicalDeviceFormatProperties");
+        let image_properties = load("vkGetPhysicalDeviceImageFormatProperties");
+        FormatQueries {
+            properties: unsafe { mem::transmute(properties) },
+            image_properties: unsafe { mem::transmute(image_properties) },
+        }
+    }
+}
+
+impl PhysicalDevice {
+    fn supports_extensions(&self, names: &[&str]) -> bool {
+        let device_extensions = self.instance.0
+            .enumerate_device_extension_properties(self.handle)
+            .unwrap_or_default();
+        names.iter().all(|name| device_extensions.iter().any(|ext| unsafe {
+            CStr::from_ptr(ext.extension_name.as_ptr()).to_bytes() == name.as_bytes()
+        }))
+    }
 }
 
 impl hal::PhysicalDevice<Backend> for PhysicalDevice {
@@ -337,11 +431,38 @@ impl hal::PhysicalDevice<Backend> for PhysicalDevice {
         // enabled features mask
         let features = Features::empty();
 
+        let device_extensions = self.instance.0
+            .enumerate_device_extension_properties(self.handle)
+            .unwrap_or_default();
+        let supports_extension = |name: &str| device_extensions.iter().any(|ext| unsafe {
+            CStr::from_ptr(ext.extension_name.as_ptr()).to_bytes() == name.as_bytes()
+        });
+        let supports_memory_budget = self.memory_properties2.is_some() &&
+            supports_extension(EXT_MEMORY_BUDGET);
+        let supports_dedicated_allocation = supports_extension(KHR_GET_MEMORY_REQUIREMENTS2) &&
+            supports_extension(KHR_DEDICATED_ALLOCATION);
+        let supports_ycbcr_conversion = YCBCR_CONVERSION_EXTENSIONS
+            .iter()
+            .all(|name| supports_extension(name));
+
         // Create device
         let device_raw = {
             let cstrings = DEVICE_EXTENSIONS
                 .iter()
+                .chain(if supports_memory_budget { Some(&EXT_MEMORY_BUDGET) } else { None })
+                .chain(if supports_dedicated_allocation {
+                    &[KHR_GET_MEMORY_REQUIREMENTS2, KHR_DEDICATED_ALLOCATION][..]
+                } else {
+                    &[][..]
+                })
+                .chain(if supports_ycbcr_conversion {
+                    YCBCR_CONVERSION_EXTENSIONS
+                } else {
+                    &[][..]
+                })
                 .map(|&s| CString::new(s).unwrap())
+                .collect::<HashSet<_>>()
+                .into_iter()
                 .collect::<Vec<_>>();
 
             let str_pointers = cstrings
@@ -351,9 +472,18 @@ impl hal::PhysicalDevice<Backend> for PhysicalDevice {
 
             // TODO: derive from `features`
             let enabled_features = unsafe { mem::zeroed() };
+            let ycbcr_features = PhysicalDeviceSamplerYcbcrConversionFeatures {
+                s_type: STRUCTURE_TYPE_PHYSICAL_DEVICE_SAMPLER_YCBCR_CONVERSION_FEATURES,
+                p_next: ptr::null_mut(),
+                sampler_ycbcr_conversion: vk::VK_TRUE,
+            };
             let info = vk::DeviceCreateInfo {
                 s_type: vk::StructureType::DeviceCreateInfo,
-                p_next: ptr::null(),
+                p_next: if supports_ycbcr_conversion {
+                    &ycbcr_features as *const _ as *const c_void
+                } else {
+                    ptr::null()
+                },
                 flags: vk::DeviceCreateFlags::empty(),
                 queue_create_info_count: family_infos.len() as u32,
                 p_queue_create_infos: family_infos.as_ptr(),
@@ -388,8 +518,63 @@ impl hal::PhysicalDevice<Backend> for PhysicalDevice {
             )
         }).unwrap();
 
+        let dedicated_allocation = if supports_dedicated_allocation {
+            let load = |name: &str| unsafe {
+                let name = CString::new(name).unwrap();
+                self.instance.0.get_device_proc_addr(device_raw.handle(), name.as_ptr())
+            };
+            let buffer = load("vkGetBufferMemoryRequirements2KHR");
+            let image = load("vkGetImageMemoryRequirements2KHR");
+            if buffer.is_null() || image.is_null() {
+                None
+            } else {
+                Some(DedicatedAllocation {
+                    get_buffer_requirements: unsafe { mem::transmute(buffer) },
+                    get_image_requirements: unsafe { mem::transmute(image) },
+                })
+            }
+        } else {
+            None
+        };
+
+        let ycbcr_conversion = if supports_ycbcr_conversion {
+            let load = |name: &str| unsafe {
+                let name = CString::new(name).unwrap();
+                self.instance.0.get_device_proc_addr(device_raw.handle(), name.as_ptr())
+            };
+            let create = load("vkCreateSamplerYcbcrConversionKHR");
+            let destroy = load("vkDestroySamplerYcbcrConversionKHR");
+            if create.is_null() || destroy.is_null() {
+                None
+            } else {
+                Some(YcbcrConversions {
+                    create: unsafe { mem::transmute(create) },
+                    destroy: unsafe { mem::transmute(destroy) },
+                    cache: Mutex::new(HashMap::new()),
+                })
+            }
+        } else {
+            None
+        };
+
+        let memory_properties = hal::PhysicalDevice::memory_properties(self);
         let device = Device {
             raw: Arc::new(RawDevice(device_raw, features)),
+            physical_device: self.handle,
+            swapchain_fn: swapchain_fn.clone(),
+            heap_usage: heap_usage::HeapUsage::new(&memory_properties),
+            memory_types: memory_properties.memory_types,
+            non_coherent_atom_size: hal::PhysicalDevice::limits(self).non_coherent_atom_size as u64,
+            memory_budget: match self.memory_properties2 {
+                Some(function) if supports_memory_budget => Some(MemoryBudgetQuery {
+                    _instance: self.instance.clone(),
+                    physical_device: self.handle,
+                    function,
+                }),
+                _ => None,
+            },
+            dedicated_allocation,
+            ycbcr_conversion,
         };
 
         let device_arc = device.raw.clone();
@@ -419,11 +604,14 @@ impl hal::PhysicalDevice<Backend> for PhysicalDevice {
     }
 
     fn format_properties(&self, format: Option<format::Format>) -> format::Properties {
-        let properties = self.instance.0
-            .get_physical_device_format_properties(
+        let mut properties: vk::FormatProperties = unsafe { mem::zeroed() };
+        unsafe {
+            (self.format_queries.properties)(
                 self.handle,
-                format.map_or(vk::Format::Undefined, conv::map_format),
+                format.map_or(0, conv::map_format_raw),
+                &mut properties,
             );
+        }
 
         format::Properties {
             linear_tiling: conv::map_image_features(properties.linear_tiling_features),
@@ -436,10 +624,11 @@ impl hal::PhysicalDevice<Backend> for PhysicalDevice {
         &self, format: format::Format, dimensions: u8, tiling: image::Tiling,
         usage: image::Usage, storage_flags: image::StorageFlags,
     ) -> Option<image::FormatProperties> {
-        match self.instance.0
-            .get_physical_device_image_format_properties(
+        let mut props: vk::ImageFormatProperties = unsafe { mem::zeroed() };
+        let result = unsafe {
+            (self.format_queries.image_properties)(
                 self.handle,
-                conv::map_format(format),
+                conv::map_format_raw(format),
                 match dimensions {
                     1 => vk::ImageType::Type1d,
                     2 => vk::ImageType::Type2d,
@@ -449,9 +638,11 @@ impl hal::PhysicalDevice<Backend> for PhysicalDevice {
                 conv::map_tiling(tiling),
                 conv::map_image_usage(usage),
                 conv::map_image_flags(storage_flags),
+                &mut props,
             )
-        {
-            Ok(props) => Some(image::FormatProperties {
+        };
+        match result {
+            vk::Result::Success => Some(image::FormatProperties {
                 max_extent: image::Extent {
                     width: props.max_extent.width,
                     height: props.max_extent.height,
@@ -462,8 +653,8 @@ impl hal::PhysicalDevice<Backend> for PhysicalDevice {
                 sample_count_mask: props.sample_counts.flags() as _,
                 max_resource_size: props.max_resource_size as _,
             }),
-            Err(vk::Result::ErrorFormatNotSupported) => None,
-            Err(other) => {
+            vk::Result::ErrorFormatNotSupported => None,
+            other => {
                 error!("Unexpected error in `image_format_properties`: {:?}", other);
                 None
             }
@@ -598,6 +789,10 @@ impl hal::PhysicalDevice<Backend> for PhysicalDevice {
         if features.fragment_stores_and_atomics != 0 {
             bits |= Features::FRAGMENT_STORES_AND_ATOMICS;
         }
+        if self.supports_extensions(YCBCR_CONVERSION_EXTENSIONS) {
+            // The feature is mandatory for devices exposing the extension.
+            bits |= Features::SAMPLER_YCBCR_CONVERSION;
+        }
         //TODO: cover more features
 
         bits
@@ -628,6 +823,7 @@ impl hal::PhysicalDevice<Backend> for PhysicalDevice {
             framebuffer_depth_samples_count: limits.framebuffer_depth_sample_counts.flags() as _,
             framebuffer_stencil_samples_count: limits.framebuffer_stencil_sample_counts.flags() as _,
             non_coherent_atom_size: limits.non_coherent_atom_size as _,
+            buffer_image_granularity: limits.buffer_image_granularity as _,
         }
     }
 }
@@ -645,6 +841,252 @@ impl Drop for RawDevice {
     }
 }
 
+const MAX_MEMORY_HEAPS: usize = 16;
+const STRUCTURE_TYPE_PHYSICAL_DEVICE_MEMORY_PROPERTIES_2: i32 = 1000059006;
+const STRUCTURE_TYPE_PHYSICAL_DEVICE_MEMORY_BUDGET_PROPERTIES: i32 = 1000237000;
+
+/// `VkPhysicalDeviceMemoryProperties2`, not exposed by `ash` yet.
+#[repr(C)]
+struct PhysicalDeviceMemoryProperties2 {
+    s_type: i32,
+    p_next: *mut c_void,
+    memory_properties: vk::PhysicalDeviceMemoryProperties,
+}
+
+/// `VkPhysicalDeviceMemoryBudgetPropertiesEXT`, not exposed by `ash` yet.
+#[repr(C)]
+struct PhysicalDeviceMemoryBudgetProperties {
+    s_type: i32,
+    p_next: *mut c_void,
+    heap_budget: [vk::DeviceSize; MAX_MEMORY_HEAPS],
+    heap_usage: [vk::DeviceSize; MAX_MEMORY_HEAPS],
+}
+
+type GetPhysicalDeviceMemoryProperties2 = unsafe extern "system" fn(
+    vk::PhysicalDevice, *mut PhysicalDeviceMemoryProperties2,
+);
+
+/// Queries the heap budgets via `VK_EXT_memory_budget`.
+struct MemoryBudgetQuery {
+    // Keeps the instance alive for the physical device handle.
+    _instance: Arc<RawInstance>,
+    physical_device: vk::PhysicalDevice,
+    function: GetPhysicalDeviceMemoryProperties2,
+}
+
+impl MemoryBudgetQuery {
+    fn query(&self) -> Vec<memory::HeapBudget> {
+        let mut budget: PhysicalDeviceMemoryBudgetProperties = unsafe { mem::zeroed() };
+        budget.s_type = STRUCTURE_TYPE_PHYSICAL_DEVICE_MEMORY_BUDGET_PROPERTIES;
+        let mut properties: PhysicalDeviceMemoryProperties2 = unsafe { mem::zeroed() };
+        properties.s_type = STRUCTURE_TYPE_PHYSICAL_DEVICE_MEMORY_PROPERTIES_2;
+        properties.p_next = &mut budget as *mut _ as *mut c_void;
+
+        unsafe {
+            (self.function)(self.physical_device, &mut properties);
+        }
+
+        (0 .. properties.memory_properties.memory_heap_count as usize)
+            .map(|i| memory::HeapBudget {
+                budget: budget.heap_budget[i],
+                usage: budget.heap_usage[i],
+            })
+            .collect()
+    }
+}
+
+const STRUCTURE_TYPE_MEMORY_DEDICATED_REQUIREMENTS: i32 = 1000127000;
+const STRUCTURE_TYPE_MEMORY_DEDICATED_ALLOCATE_INFO: i32 = 1000127001;
+const STRUCTURE_TYPE_BUFFER_MEMORY_REQUIREMENTS_INFO_2: i32 = 1000146000;
+const STRUCTURE_TYPE_IMAGE_MEMORY_REQUIREMENTS_INFO_2: i32 = 1000146001;
+const STRUCTURE_TYPE_MEMORY_REQUIREMENTS_2: i32 = 1000146003;
+
+/// `VkBufferMemoryRequirementsInfo2`, not exposed by `ash` yet.
+#[repr(C)]
+struct BufferMemoryRequirementsInfo2 {
+    s_type: i32,
+    p_next: *const c_void,
+    buffer: vk::Buffer,
+}
+
+/// `VkImageMemoryRequirementsInfo2`, not exposed by `ash` yet.
+#[repr(C)]
+struct ImageMemoryRequirementsInfo2 {
+    s_type: i32,
+    p_next: *const c_void,
+    image: vk::Image,
+}
+
+/// `VkMemoryRequirements2`, not exposed by `ash` yet.
+#[repr(C)]
+struct MemoryRequirements2 {
+    s_type: i32,
+    p_next: *mut c_void,
+    memory_requirements: vk::MemoryRequirements,
+}
+
+/// `VkMemoryDedicatedRequirements`, not exposed by `ash` yet.
+#[repr(C)]
+struct MemoryDedicatedRequirements {
+    s_type: i32,
+    p_next: *mut c_void,
+    prefers_dedicated_allocation: vk::Bool32,
+    requires_dedicated_allocation: vk::Bool32,
+}
+
+/// `VkMemoryDedicatedAllocateInfo`, not exposed by `ash` yet.
+#[repr(C)]
+struct MemoryDedicatedAllocateInfo {
+    s_type: i32,
+    p_next: *const c_void,
+    image: vk::Image,
+    buffer: vk::Buffer,
+}
+
+type GetBufferMemoryRequirements2 = unsafe extern "system" fn(
+    vk::Device, *const BufferMemoryRequirementsInfo2, *mut MemoryRequirements2,
+);
+type GetImageMemoryRequirements2 = unsafe extern "system" fn(
+    vk::Device, *const ImageMemoryRequirementsInfo2, *mut MemoryRequirements2,
+);
+
+/// Entry points of `VK_KHR_get_memory_requirements2`, enabled together
+/// with `VK_KHR_dedicated_allocation`.
+struct DedicatedAllocation {
+    get_buffer_requirements: GetBufferMemoryRequirements2,
+    get_image_requirements: GetImageMemoryRequirements2,
+}
+
+impl DedicatedAllocation {
+    fn buffer_requirements(&self, device: vk::Device, buffer: vk::Buffer) -> memory::Requirements {
+        let info = BufferMemoryRequirementsInfo2 {
+            s_type: STRUCTURE_TYPE_BUFFER_MEMORY_REQUIREMENTS_INFO_2,
+            p_next: ptr::null(),
+            buffer,
+        };
+        Self::query(|requirements| unsafe {
+            (self.get_buffer_requirements)(device, &info, requirements)
+        })
+    }
+
+    fn image_requirements(&self, device: vk::Device, image: vk::Image) -> memory::Requirements {
+        let info = ImageMemoryRequirementsInfo2 {
+            s_type: STRUCTURE_TYPE_IMAGE_MEMORY_REQUIREMENTS_INFO_2,
+            p_next: ptr::null(),
+            image,
+        };
+        Self::query(|requirements| unsafe {
+            (self.get_image_requirements)(device, &info, requirements)
+        })
+    }
+
+    fn query<F: FnOnce(*mut MemoryRequirements2)>(fun: F) -> memory::Requirements {
+        let mut dedicated: MemoryDedicatedRequirements = unsafe { mem::zeroed() };
+        dedicated.s_type = STRUCTURE_TYPE_MEMORY_DEDICATED_REQUIREMENTS;
+        let mut requirements: MemoryRequirements2 = unsafe { mem::zeroed() };
+        requirements.s_type = STRUCTURE_TYPE_MEMORY_REQUIREMENTS_2;
+        requirements.p_next = &mut dedicated as *mut _ as *mut c_void;
+
+        fun(&mut requirements);
+
+        let req = requirements.memory_requirements;
+        memory::Requirements {
+            size: req.size,
+            alignment: req.alignment,
+            type_mask: req.memory_type_bits as _,
+            prefers_dedicated: dedicated.prefers_dedicated_allocation != vk::VK_FALSE,
+            requires_dedicated: dedicated.requires_dedicated_allocation != vk::VK_FALSE,
+        }
+    }
+}
+
+const STRUCTURE_TYPE_SAMPLER_YCBCR_CONVERSION_CREATE_INFO: i32 = 1000156000;
+const STRUCTURE_TYPE_SAMPLER_YCBCR_CONVERSION_INFO: i32 = 1000156001;
+const STRUCTURE_TYPE_PHYSICAL_DEVICE_SAMPLER_YCBCR_CONVERSION_FEATURES: i32 = 1000156004;
+
+/// `VkSamplerYcbcrConversion`, a non-dispatchable handle.
+type SamplerYcbcrConversion = u64;
+
+/// `VkPhysicalDeviceSamplerYcbcrConversionFeatures`, not exposed by `ash` yet.
+#[repr(C)]
+struct PhysicalDeviceSamplerYcbcrConversionFeatures {
+    s_type: i32,
+    p_next: *mut c_void,
+    sampler_ycbcr_conversion: vk::Bool32,
+}
+
+/// `VkSamplerYcbcrConversionCreateInfo`, not exposed by `ash` yet.
+#[repr(C)]
+struct SamplerYcbcrConversionCreateInfo {
+    s_type: i32,
+    p_next: *const c_void,
+    format: i32,
+    ycbcr_model: i32,
+    ycbcr_range: i32,
+    components: vk::ComponentMapping,
+    x_chroma_offset: i32,
+    y_chroma_offset: i32,
+    chroma_filter: vk::Filter,
+    force_explicit_reconstruction: vk::Bool32,
+}
+
+/// `VkSamplerYcbcrConversionInfo`, not exposed by `ash` yet.
+#[repr(C)]
+struct SamplerYcbcrConversionInfo {
+    s_type: i32,
+    p_next: *const c_void,
+    conversion: SamplerYcbcrConversion,
+}
+
+type CreateSamplerYcbcrConversion = unsafe extern "system" fn(
+    vk::Device, *const SamplerYcbcrConversionCreateInfo, *const c_void, *mut SamplerYcbcrConversion,
+) -> vk::Result;
+type DestroySamplerYcbcrConversion = unsafe extern "system" fn(
+    vk::Device, SamplerYcbcrConversion, *const c_void,
+);
+
+/// Entry points of `VK_KHR_sampler_ycbcr_conversion`.
+///
+/// Conversion objects are shared between all samplers and views using
+/// the same parameters and live as long as the device.
+struct YcbcrConversions {
+    create: CreateSamplerYcbcrConversion,
+    destroy: DestroySamplerYcbcrConversion,
+    cache: Mutex<HashMap<image::YcbcrConversion, SamplerYcbcrConversion>>,
+}
+
+impl YcbcrConversions {
+    /// Get the conversion info for `conversion`, creating the conversion object on first use.
+    fn info(
+        &self, device: vk::Device, conversion: &image::YcbcrConversion,
+    ) -> SamplerYcbcrConversionInfo {
+        let mut cache = self.cache.lock().unwrap();
+        let raw = *cache.entry(*conversion).or_insert_with(|| {
+            let info = SamplerYcbcrConversionCreateInfo {
+                s_type: STRUCTURE_TYPE_SAMPLER_YCBCR_CONVERSION_CREATE_INFO,
+                p_next: ptr::null(),
+                format: conv::map_format_raw(conversion.format),
+                ycbcr_model: conv::map_ycbcr_model(conversion.model),
+                ycbcr_range: conv::map_ycbcr_range(conversion.range),
+                components: conv::map_swizzle(conversion.swizzle),
+                x_chroma_offset: conv::map_chroma_location(conversion.chroma_location.0),
+                y_chroma_offset: conv::map_chroma_location(conversion.chroma_location.1),
+                chroma_filter: conv::map_filter(conversion.chroma_filter),
+                force_explicit_reconstruction: vk::VK_FALSE,
+            };
+            let mut raw = 0;
+            let result = unsafe { (self.create)(device, &info, ptr::null(), &mut raw) };
+            assert_eq!(result, vk::Result::Success, "error on Y'CbCr conversion creation");
+            raw
+        });
+        SamplerYcbcrConversionInfo {
+            s_type: STRUCTURE_TYPE_SAMPLER_YCBCR_CONVERSION_INFO,
+            p_next: ptr::null(),
+            conversion: raw,
+        }
+    }
+}
+
 // Need to explicitly synchronize on submission and present.
 pub type RawCommandQueue = Arc<vk::Queue>;
 
@@ -760,6 +1202,25 @@ impl hal::queue::RawCommandQueue<Backend> for CommandQueue {
 
 pub struct Device {
     raw: Arc<RawDevice>,
+    physical_device: vk::PhysicalDevice,
+    swapchain_fn: vk::SwapchainFn,
+    heap_usage: heap_usage::HeapUsage,
+    memory_types: Vec<hal::MemoryType>,
+    non_coherent_atom_size: u64,
+    memory_budget: Option<MemoryBudgetQuery>,
+    dedicated_allocation: Option<DedicatedAllocation>,
+    ycbcr_conversion: Option<YcbcrConversions>,
+}
+
+impl Drop for Device {
+    fn drop(&mut self) {
+        if let Some(ref conversions) = self.ycbcr_conversion {
+            let device = self.raw.0.handle();
+            for (_, raw) in conversions.cache.lock().unwrap().drain() {
+                unsafe { (conversions.destroy)(device, raw, ptr::null()); }
+            }
+        }
+    }
 }
 
 #[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
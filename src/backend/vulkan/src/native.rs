@@ -1,6 +1,6 @@
 use ash::vk;
 use ash::version::DeviceV1_0;
-use hal::pso;
+use hal::{pso, MemoryTypeId};
 use hal::image::SubresourceRange;
 use std::borrow::Borrow;
 use std::sync::Arc;
@@ -21,6 +21,8 @@ pub struct ComputePipeline(pub vk::Pipeline);
 #[derive(Debug, Hash)]
 pub struct Memory {
     pub(crate) raw: vk::DeviceMemory,
+    pub(crate) ty: MemoryTypeId,
+    pub(crate) size: u64,
 }
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
@@ -1,5 +1,6 @@
 use std::collections::VecDeque;
-use std::ptr;
+use std::ffi::CString;
+use std::{mem, ptr};
 use std::sync::Arc;
 use std::os::raw::c_void;
 
@@ -9,6 +10,7 @@ use ash::extensions as ext;
 use hal;
 use hal::image::{NumSamples, Size};
 use hal::format::Format;
+use hal::window::ColorSpace;
 
 #[cfg(feature = "winit")]
 use winit;
@@ -32,6 +34,40 @@ pub struct RawSurface {
     pub(crate) instance: Arc<RawInstance>,
 }
 
+/// `VkSurfaceFormatKHR` with raw values, as `ash` doesn't know about the
+/// color spaces of `VK_EXT_swapchain_colorspace`.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub(crate) struct RawSurfaceFormat {
+    pub(crate) format: i32,
+    pub(crate) color_space: i32,
+}
+
+type GetPhysicalDeviceSurfaceFormats = unsafe extern "system" fn(
+    vk::PhysicalDevice, vk::SurfaceKHR, *mut u32, *mut RawSurfaceFormat,
+) -> vk::Result;
+
+impl RawSurface {
+    pub(crate) fn surface_formats(&self, physical_device: vk::PhysicalDevice) -> Vec<RawSurfaceFormat> {
+        let entry = VK_ENTRY.as_ref().expect("Unable to load Vulkan entry points");
+        let name = CString::new("vkGetPhysicalDeviceSurfaceFormatsKHR").unwrap();
+        let function = entry.get_instance_proc_addr(self.instance.0.handle(), name.as_ptr());
+        assert!(!function.is_null(), "Unable to query surface formats");
+        let function: GetPhysicalDeviceSurfaceFormats = unsafe { mem::transmute(function) };
+
+        let mut count = 0;
+        let result = unsafe { function(physical_device, self.handle, &mut count, ptr::null_mut()) };
+        assert_eq!(result, vk::Result::Success, "Unable to query surface formats");
+        let mut formats = vec![RawSurfaceFormat { format: 0, color_space: 0 }; count as usize];
+        let result = unsafe {
+            function(physical_device, self.handle, &mut count, formats.as_mut_ptr())
+        };
+        assert_eq!(result, vk::Result::Success, "Unable to query surface formats");
+        formats.truncate(count as usize);
+        formats
+    }
+}
+
 impl Drop for RawSurface {
     fn drop(&mut self) {
         unsafe {
@@ -295,7 +331,9 @@ impl hal::Surface<Backend> for Surface {
         hal::image::Kind::D2(self.width, self.height, 1, self.samples)
     }
 
-    fn capabilities_and_formats(&self, physical_device: &PhysicalDevice) -> (hal::SurfaceCapabilities, Option<Vec<Format>>) {
+    fn capabilities_and_formats(
+        &self, physical_device: &PhysicalDevice,
+    ) -> (hal::SurfaceCapabilities, Option<Vec<(Format, ColorSpace)>>) {
         // Capabilities
         let caps =
             self.raw.functor.get_physical_device_surface_capabilities_khr(
@@ -336,21 +374,21 @@ impl hal::Surface<Backend> for Surface {
         };
 
         // Swapchain formats
-        let formats =
-            self.raw.functor.get_physical_device_surface_formats_khr(
-                physical_device.handle,
-                self.raw.handle,
-            ).expect("Unable to query surface formats");
+        let formats = self.raw.surface_formats(physical_device.handle);
 
         let formats = match formats[0].format {
             // If pSurfaceFormats includes just one entry, whose value for format is
             // VK_FORMAT_UNDEFINED, surface has no preferred format. In this case, the application
             // can use any valid VkFormat value.
-            vk::Format::Undefined => None,
+            0 => None,
             _ => {
                 Some(formats
                     .iter()
-                    .filter_map(|sf| conv::map_vk_format(sf.format))
+                    .filter_map(|sf| {
+                        let format = conv::map_vk_format_raw(sf.format)?;
+                        let color_space = conv::map_vk_color_space(sf.color_space)?;
+                        Some((format, color_space))
+                    })
                     .collect()
                 )
             }
@@ -7,6 +7,9 @@
 //! that has the properties specified.
 
 use std::any::Any;
+use std::error::Error;
+use std::fmt;
+use std::marker::PhantomData;
 
 use {format, image, memory, Backend, Gpu, Features, Limits};
 use error::DeviceCreationError;
@@ -96,16 +99,39 @@ pub trait PhysicalDevice<B: Backend>: Any + Send + Sync {
     fn limits(&self) -> Limits;
 }
 
+/// Type of a physical device.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum DeviceType {
+    /// Other or unknown device type.
+    Other,
+    /// Integrated GPU, sharing the memory with the host.
+    IntegratedGpu,
+    /// Discrete GPU, with its own dedicated memory.
+    DiscreteGpu,
+    /// Virtual GPU, exposed by a hypervisor.
+    VirtualGpu,
+    /// Software rasterizer running on the host CPU.
+    Cpu,
+}
+
 /// Metadata about a backend adapter.
 #[derive(Clone, Debug, Eq, PartialEq)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct AdapterInfo {
     /// Adapter name
     pub name: String,
-    /// Vendor PCI id of the adapter
+    /// Vendor PCI id of the adapter, 0 if unknown
     pub vendor: usize,
-    /// PCI id of the adapter
+    /// PCI id of the adapter, 0 if unknown
     pub device: usize,
+    /// Type of the adapter
+    pub device_type: DeviceType,
+    /// Version of the driver, 0 if unknown.
+    ///
+    /// The encoding is vendor specific on Vulkan. Other backends pack the
+    /// parsed version as `major << 22 | minor << 12 | patch`.
+    pub driver_version: u32,
     /// Whether or not the device is based on a software rasterizer
     pub software_rendering: bool,
 }
@@ -174,3 +200,374 @@ impl<B: Backend> Adapter<B> {
         Ok((device, queues.take(id).unwrap()))
     }
 }
+
+/// Reason why an adapter has been discarded by `select_adapter`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Rejection {
+    /// Some of the requested features are not supported.
+    /// Contains the missing features.
+    MissingFeatures(Features),
+    /// One of the device limits is lower than requested.
+    /// Contains the name of the limit.
+    InsufficientLimit(&'static str),
+    /// None of the queue families provides the requested capability.
+    NoSuitableQueueFamily,
+    /// None of the queue families supports presentation to the surface.
+    NoPresentationSupport,
+}
+
+/// Error returned by `select_adapter` if none of the adapters matched.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SelectionError {
+    /// Every adapter passed to the selection, paired with the reasons of its rejection.
+    pub rejected: Vec<(AdapterInfo, Vec<Rejection>)>,
+}
+
+impl fmt::Display for SelectionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:", self.description())?;
+        for &(ref info, ref rejections) in &self.rejected {
+            write!(f, " {} {:?};", info.name, rejections)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for SelectionError {
+    fn description(&self) -> &str {
+        "No suitable adapter found"
+    }
+}
+
+/// Requirements and preferences used for picking an adapter with `select_adapter`.
+///
+/// # Examples
+///
+/// This type implements the builder pattern, method calls can be
+/// easily chained.
+///
+/// ```no_run
+/// # extern crate gfx_backend_empty as empty;
+/// # extern crate gfx_hal;
+/// # fn main() {
+/// use gfx_hal::{Features, Graphics};
+/// use gfx_hal::adapter::{select_adapter, Criteria};
+///
+/// # let adapters: Vec<gfx_hal::Adapter<empty::Backend>> = return;
+/// # let surface: empty::Surface = return;
+/// let criteria = Criteria::<empty::Backend, Graphics>::new()
+///     .with_features(Features::SAMPLER_ANISOTROPY)
+///     .with_surface(&surface);
+/// let adapter = select_adapter(adapters, &criteria).unwrap();
+/// # }
+/// ```
+pub struct Criteria<'a, B: Backend, C> {
+    /// Features the adapter must support.
+    pub features: Features,
+    /// Minimal limits of the adapter.
+    ///
+    /// Only the maximum counts and sizes are compared, alignments are ignored.
+    pub limits: Limits,
+    /// Surface, which must be supported by at least one queue family providing `C`.
+    pub surface: Option<&'a B::Surface>,
+    _capability: PhantomData<C>,
+}
+
+impl<'a, B: Backend, C: Capability> Criteria<'a, B, C> {
+    /// Create new criteria, only requiring a queue family with capability `C`.
+    pub fn new() -> Self {
+        Criteria {
+            features: Features::empty(),
+            limits: Limits::default(),
+            surface: None,
+            _capability: PhantomData,
+        }
+    }
+
+    /// Specify the features the adapter must support.
+    pub fn with_features(mut self, features: Features) -> Self {
+        self.features = features;
+        self
+    }
+
+    /// Specify the minimal limits of the adapter.
+    pub fn with_limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Specify a surface the adapter must be able to present to.
+    pub fn with_surface(mut self, surface: &'a B::Surface) -> Self {
+        self.surface = Some(surface);
+        self
+    }
+
+    /// Check the adapter against the criteria, returning all reasons of rejection.
+    pub fn check(&self, adapter: &Adapter<B>) -> Vec<Rejection> {
+        use queue::QueueFamily;
+        use window::Surface;
+
+        let mut rejections = Vec::new();
+
+        let missing = self.features - adapter.physical_device.features();
+        if !missing.is_empty() {
+            rejections.push(Rejection::MissingFeatures(missing));
+        }
+        if let Some(name) = insufficient_limit(&adapter.physical_device.limits(), &self.limits) {
+            rejections.push(Rejection::InsufficientLimit(name));
+        }
+
+        let mut families = adapter.queue_families
+            .iter()
+            .filter(|family| C::supported_by(family.queue_type()))
+            .peekable();
+        if families.peek().is_none() {
+            rejections.push(Rejection::NoSuitableQueueFamily);
+        } else if let Some(surface) = self.surface {
+            if !families.any(|family| surface.supports_queue_family(family)) {
+                rejections.push(Rejection::NoPresentationSupport);
+            }
+        }
+
+        rejections
+    }
+}
+
+/// Returns the name of the first limit of `limits` below `required`.
+fn insufficient_limit(limits: &Limits, required: &Limits) -> Option<&'static str> {
+    macro_rules! check {
+        ($($field:ident),*) => {
+            $(
+                if limits.$field < required.$field {
+                    return Some(stringify!($field));
+                }
+            )*
+        };
+    }
+    check!(
+        max_texture_size,
+        max_patch_size,
+        max_viewports,
+        max_vertex_input_attributes,
+        max_vertex_input_bindings,
+        max_vertex_input_attribute_offset,
+        max_vertex_input_binding_stride,
+        max_vertex_output_components
+    );
+    for i in 0 .. 3 {
+        if limits.max_compute_group_count[i] < required.max_compute_group_count[i] {
+            return Some("max_compute_group_count");
+        }
+        if limits.max_compute_group_size[i] < required.max_compute_group_size[i] {
+            return Some("max_compute_group_size");
+        }
+    }
+    // Sample counts are bit masks of the supported counts.
+    if required.framebuffer_color_samples_count & !limits.framebuffer_color_samples_count != 0 {
+        return Some("framebuffer_color_samples_count");
+    }
+    if required.framebuffer_depth_samples_count & !limits.framebuffer_depth_samples_count != 0 {
+        return Some("framebuffer_depth_samples_count");
+    }
+    if required.framebuffer_stencil_samples_count & !limits.framebuffer_stencil_samples_count != 0 {
+        return Some("framebuffer_stencil_samples_count");
+    }
+    None
+}
+
+/// Rank of an adapter, higher is better.
+///
+/// Discrete GPUs are preferred over integrated and virtual ones,
+/// software rasterizers come last. Ties are broken by the amount of device local memory.
+fn rank<B: Backend>(adapter: &Adapter<B>) -> (u8, u64) {
+    let properties = adapter.physical_device.memory_properties();
+    let local_memory = properties.memory_heaps
+        .iter()
+        .enumerate()
+        .filter(|&(heap, _)| {
+            properties.memory_types.iter().any(|ty| {
+                ty.heap_index == heap && ty.properties.contains(memory::Properties::DEVICE_LOCAL)
+            })
+        })
+        .map(|(_, &size)| size)
+        .sum::<u64>();
+    let device_type = match adapter.info.device_type {
+        DeviceType::DiscreteGpu => 4,
+        DeviceType::IntegratedGpu => 3,
+        DeviceType::VirtualGpu => 2,
+        DeviceType::Other => 1,
+        DeviceType::Cpu => 0,
+    };
+    (device_type, local_memory)
+}
+
+/// Pick the best adapter matching the given criteria.
+///
+/// Adapters are ranked by their type and the size of the device local memory.
+/// On failure, the returned error lists the reasons why each adapter was rejected.
+pub fn select_adapter<B, C, I>(
+    adapters: I, criteria: &Criteria<B, C>
+) -> Result<Adapter<B>, SelectionError>
+where
+    B: Backend,
+    C: Capability,
+    I: IntoIterator<Item = Adapter<B>>,
+{
+    let mut best: Option<((u8, u64), Adapter<B>)> = None;
+    let mut rejected = Vec::new();
+
+    for adapter in adapters {
+        let rejections = criteria.check(&adapter);
+        if !rejections.is_empty() {
+            rejected.push((adapter.info.clone(), rejections));
+            continue;
+        }
+        let rank = rank(&adapter);
+        match best {
+            Some((ref best_rank, _)) if *best_rank >= rank => {}
+            _ => best = Some((rank, adapter)),
+        }
+    }
+
+    match best {
+        Some((_, adapter)) => Ok(adapter),
+        None => Err(SelectionError { rejected }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {memory, Compute, Features, Graphics, Limits, QueueType};
+    use mock::{Backend, PhysicalDevice, QueueFamily, Surface};
+    use super::*;
+
+    const GB: u64 = 1 << 30;
+
+    fn adapter(name: &str, device_type: DeviceType, local_memory: u64) -> Adapter<Backend> {
+        Adapter {
+            info: AdapterInfo {
+                name: name.to_string(),
+                vendor: 0,
+                device: 0,
+                device_type,
+                driver_version: 0,
+                software_rendering: device_type == DeviceType::Cpu,
+            },
+            physical_device: PhysicalDevice {
+                memory_properties: MemoryProperties {
+                    memory_types: vec![
+                        MemoryType { properties: memory::Properties::DEVICE_LOCAL, heap_index: 0 },
+                        MemoryType { properties: memory::Properties::CPU_VISIBLE, heap_index: 1 },
+                    ],
+                    memory_heaps: vec![local_memory, 16 * GB],
+                },
+                features: Features::SAMPLER_ANISOTROPY,
+                limits: Limits {
+                    max_texture_size: 4096,
+                    framebuffer_color_samples_count: 0b101,
+                    .. Limits::default()
+                },
+            },
+            queue_families: vec![QueueFamily(QueueType::Graphics, 0), QueueFamily(QueueType::Transfer, 1)],
+        }
+    }
+
+    fn select(adapters: Vec<Adapter<Backend>>) -> String {
+        select_adapter(adapters, &Criteria::<Backend, Graphics>::new()).unwrap().info.name
+    }
+
+    #[test]
+    fn prefers_device_types() {
+        let all = [
+            ("cpu", DeviceType::Cpu, 8 * GB),
+            ("integrated", DeviceType::IntegratedGpu, 4 * GB),
+            ("discrete", DeviceType::DiscreteGpu, GB),
+            ("virtual", DeviceType::VirtualGpu, 4 * GB),
+            ("other", DeviceType::Other, 4 * GB),
+        ];
+        let mut remaining: Vec<_> = all.iter().map(|&(name, _, _)| name).collect();
+        for &expected in &["discrete", "integrated", "virtual", "other", "cpu"] {
+            let adapters = all
+                .iter()
+                .filter(|&&(name, _, _)| remaining.contains(&name))
+                .map(|&(name, ty, memory)| adapter(name, ty, memory))
+                .collect();
+            assert_eq!(select(adapters), expected);
+            remaining.retain(|&name| name != expected);
+        }
+    }
+
+    #[test]
+    fn breaks_ties_by_local_memory() {
+        // the host visible heap doesn't count
+        assert_eq!(select(vec![
+            adapter("small", DeviceType::DiscreteGpu, 2 * GB),
+            adapter("large", DeviceType::DiscreteGpu, 4 * GB),
+        ]), "large");
+        assert_eq!(select(vec![
+            adapter("large", DeviceType::DiscreteGpu, 4 * GB),
+            adapter("small", DeviceType::DiscreteGpu, 2 * GB),
+        ]), "large");
+        // the first one wins on equal ranks
+        assert_eq!(select(vec![
+            adapter("first", DeviceType::IntegratedGpu, 2 * GB),
+            adapter("second", DeviceType::IntegratedGpu, 2 * GB),
+        ]), "first");
+    }
+
+    #[test]
+    fn rejects_missing_features() {
+        let criteria = Criteria::<Backend, Graphics>::new()
+            .with_features(Features::SAMPLER_ANISOTROPY | Features::GEOMETRY_SHADER);
+        assert_eq!(criteria.check(&adapter("gpu", DeviceType::DiscreteGpu, GB)),
+            vec![Rejection::MissingFeatures(Features::GEOMETRY_SHADER)]);
+    }
+
+    #[test]
+    fn rejects_insufficient_limits() {
+        let adapter = adapter("gpu", DeviceType::DiscreteGpu, GB);
+        let check = |limits| Criteria::<Backend, Graphics>::new().with_limits(limits).check(&adapter);
+        assert!(check(Limits { max_texture_size: 4096, .. Limits::default() }).is_empty());
+        assert_eq!(check(Limits { max_texture_size: 8192, .. Limits::default() }),
+            vec![Rejection::InsufficientLimit("max_texture_size")]);
+        // sample counts are compared as masks
+        assert!(check(Limits { framebuffer_color_samples_count: 0b100, .. Limits::default() }).is_empty());
+        assert_eq!(check(Limits { framebuffer_color_samples_count: 0b010, .. Limits::default() }),
+            vec![Rejection::InsufficientLimit("framebuffer_color_samples_count")]);
+    }
+
+    #[test]
+    fn rejects_missing_queue_families() {
+        let adapter = adapter("gpu", DeviceType::DiscreteGpu, GB);
+        assert!(Criteria::<Backend, Graphics>::new().check(&adapter).is_empty());
+        assert_eq!(Criteria::<Backend, Compute>::new().check(&adapter),
+            vec![Rejection::NoSuitableQueueFamily]);
+    }
+
+    #[test]
+    fn rejects_missing_presentation() {
+        let adapter = adapter("gpu", DeviceType::DiscreteGpu, GB);
+        // only the transfer family can present
+        let surface = Surface { families: vec![1] };
+        assert_eq!(Criteria::<Backend, Graphics>::new().with_surface(&surface).check(&adapter),
+            vec![Rejection::NoPresentationSupport]);
+        let surface = Surface { families: vec![0] };
+        assert!(Criteria::<Backend, Graphics>::new().with_surface(&surface).check(&adapter).is_empty());
+    }
+
+    #[test]
+    fn reports_all_rejections() {
+        let criteria = Criteria::<Backend, Compute>::new()
+            .with_features(Features::GEOMETRY_SHADER);
+        let error = select_adapter(vec![
+            adapter("first", DeviceType::DiscreteGpu, GB),
+            adapter("second", DeviceType::IntegratedGpu, GB),
+        ], &criteria).err().unwrap();
+        let rejections = vec![
+            Rejection::MissingFeatures(Features::GEOMETRY_SHADER),
+            Rejection::NoSuitableQueueFamily,
+        ];
+        assert_eq!(error.rejected.iter().map(|&(ref info, ref r)| (&info.name[..], r.clone())).collect::<Vec<_>>(),
+            vec![("first", rejections.clone()), ("second", rejections)]);
+    }
+}
@@ -132,6 +132,16 @@ bitflags!(
     }
 );
 
+impl Access {
+    /// Check if any of the accesses writes to the buffer.
+    pub fn is_write(&self) -> bool {
+        self.intersects(
+            Access::SHADER_WRITE | Access::TRANSFER_WRITE |
+            Access::HOST_WRITE | Access::MEMORY_WRITE
+        )
+    }
+}
+
 /// Buffer state
 pub type State = Access;
 
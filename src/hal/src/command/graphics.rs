@@ -299,6 +299,21 @@ impl<'a, B: Backend, C: Supports<Graphics>, S: Shot, L: Level> CommandBuffer<'a,
     {
         self.raw.blit_image(src, src_layout, dst, dst_layout, filter, regions)
     }
+
+    /// Identical to the `RawCommandBuffer` method of the same name.
+    pub fn blit_image_to_framebuffer<T>(
+        &mut self,
+        src: &B::Image,
+        src_layout: image::Layout,
+        dst: &B::Framebuffer,
+        filter: image::Filter,
+        regions: T,
+    ) where
+        T: IntoIterator,
+        T::Item: Borrow<ImageBlit>,
+    {
+        self.raw.blit_image_to_framebuffer(src, src_layout, dst, filter, regions)
+    }
 }
 
 impl<'a, B: Backend, C: Supports<Graphics>, S: Shot> CommandBuffer<'a, B, C, S, Primary> {
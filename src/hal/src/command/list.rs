@@ -0,0 +1,869 @@
+//! Owned command lists.
+//!
+//! A `CommandList` records commands into a plain `Vec`, independently of any backend
+//! command pool. The list can be built on any thread, inspected or modified before use
+//! and replayed into any `RawCommandBuffer` as often as needed.
+
+use std::borrow::Borrow;
+use std::ops::Range;
+
+use {buffer, pso};
+use {Backend, DrawCount, IndexCount, IndexType, InstanceCount, VertexCount, VertexOffset, WorkGroupCount};
+use image::{Filter, Layout, SubresourceRange};
+use memory::{Barrier, Dependencies};
+use query::{Query, QueryControl, QueryId};
+use range::RangeArg;
+use super::{
+    AttachmentClear, BufferCopy, BufferImageCopy, ClearColorRaw, ClearDepthStencilRaw,
+    ClearValueRaw, DescriptorSetOffset, ImageBlit, ImageCopy, ImageResolve,
+    RawCommandBuffer, SubpassContents,
+};
+
+/// A single recorded command.
+///
+/// Each variant corresponds to the `RawCommandBuffer` method of the same name.
+#[allow(missing_docs)]
+#[derive(Debug)]
+pub enum Command<'a, B: Backend> {
+    PipelineBarrier {
+        stages: Range<pso::PipelineStage>,
+        dependencies: Dependencies,
+        barriers: Vec<Barrier<'a, B>>,
+    },
+    FillBuffer {
+        buffer: &'a B::Buffer,
+        range: (Option<buffer::Offset>, Option<buffer::Offset>),
+        data: u32,
+    },
+    UpdateBuffer {
+        buffer: &'a B::Buffer,
+        offset: buffer::Offset,
+        data: Vec<u8>,
+    },
+    ClearImage {
+        image: &'a B::Image,
+        layout: Layout,
+        color: ClearColorRaw,
+        depth_stencil: ClearDepthStencilRaw,
+        subresource_ranges: Vec<SubresourceRange>,
+    },
+    ClearAttachments {
+        clears: Vec<AttachmentClear>,
+        rects: Vec<pso::ClearRect>,
+    },
+    ResolveImage {
+        src: &'a B::Image,
+        src_layout: Layout,
+        dst: &'a B::Image,
+        dst_layout: Layout,
+        regions: Vec<ImageResolve>,
+    },
+    BlitImage {
+        src: &'a B::Image,
+        src_layout: Layout,
+        dst: &'a B::Image,
+        dst_layout: Layout,
+        filter: Filter,
+        regions: Vec<ImageBlit>,
+    },
+    BindIndexBuffer {
+        buffer: &'a B::Buffer,
+        offset: buffer::Offset,
+        index_type: IndexType,
+    },
+    BindVertexBuffers {
+        first_binding: u32,
+        buffers: Vec<(&'a B::Buffer, buffer::Offset)>,
+    },
+    SetViewports {
+        first_viewport: u32,
+        viewports: Vec<pso::Viewport>,
+    },
+    SetScissors {
+        first_scissor: u32,
+        rects: Vec<pso::Rect>,
+    },
+    SetStencilReference {
+        faces: pso::Face,
+        value: pso::StencilValue,
+    },
+    SetStencilReadMask {
+        faces: pso::Face,
+        value: pso::StencilValue,
+    },
+    SetStencilWriteMask {
+        faces: pso::Face,
+        value: pso::StencilValue,
+    },
+    SetBlendConstants(pso::ColorValue),
+    SetDepthBounds(Range<f32>),
+    SetLineWidth(f32),
+    SetDepthBias(pso::DepthBias),
+    BeginRenderPass {
+        render_pass: &'a B::RenderPass,
+        framebuffer: &'a B::Framebuffer,
+        render_area: pso::Rect,
+        clear_values: Vec<ClearValueRaw>,
+        first_subpass: SubpassContents,
+    },
+    NextSubpass(SubpassContents),
+    EndRenderPass,
+    BindGraphicsPipeline(&'a B::GraphicsPipeline),
+    BindGraphicsDescriptorSets {
+        layout: &'a B::PipelineLayout,
+        first_set: usize,
+        sets: Vec<&'a B::DescriptorSet>,
+        offsets: Vec<DescriptorSetOffset>,
+    },
+    BindComputePipeline(&'a B::ComputePipeline),
+    BindComputeDescriptorSets {
+        layout: &'a B::PipelineLayout,
+        first_set: usize,
+        sets: Vec<&'a B::DescriptorSet>,
+        offsets: Vec<DescriptorSetOffset>,
+    },
+    Dispatch(WorkGroupCount),
+    DispatchIndirect {
+        buffer: &'a B::Buffer,
+        offset: buffer::Offset,
+    },
+    CopyBuffer {
+        src: &'a B::Buffer,
+        dst: &'a B::Buffer,
+        regions: Vec<BufferCopy>,
+    },
+    CopyImage {
+        src: &'a B::Image,
+        src_layout: Layout,
+        dst: &'a B::Image,
+        dst_layout: Layout,
+        regions: Vec<ImageCopy>,
+    },
+    CopyBufferToImage {
+        src: &'a B::Buffer,
+        dst: &'a B::Image,
+        dst_layout: Layout,
+        regions: Vec<BufferImageCopy>,
+    },
+    CopyImageToBuffer {
+        src: &'a B::Image,
+        src_layout: Layout,
+        dst: &'a B::Buffer,
+        regions: Vec<BufferImageCopy>,
+    },
+    Draw {
+        vertices: Range<VertexCount>,
+        instances: Range<InstanceCount>,
+    },
+    DrawIndexed {
+        indices: Range<IndexCount>,
+        base_vertex: VertexOffset,
+        instances: Range<InstanceCount>,
+    },
+    DrawIndirect {
+        buffer: &'a B::Buffer,
+        offset: buffer::Offset,
+        draw_count: DrawCount,
+        stride: u32,
+    },
+    DrawIndexedIndirect {
+        buffer: &'a B::Buffer,
+        offset: buffer::Offset,
+        draw_count: DrawCount,
+        stride: u32,
+    },
+    BeginQuery {
+        pool: &'a B::QueryPool,
+        id: QueryId,
+        flags: QueryControl,
+    },
+    EndQuery {
+        pool: &'a B::QueryPool,
+        id: QueryId,
+    },
+    ResetQueryPool {
+        pool: &'a B::QueryPool,
+        queries: Range<QueryId>,
+    },
+    WriteTimestamp {
+        stage: pso::PipelineStage,
+        pool: &'a B::QueryPool,
+        id: QueryId,
+    },
+    PushGraphicsConstants {
+        layout: &'a B::PipelineLayout,
+        stages: pso::ShaderStageFlags,
+        offset: u32,
+        constants: Vec<u32>,
+    },
+    PushComputeConstants {
+        layout: &'a B::PipelineLayout,
+        offset: u32,
+        constants: Vec<u32>,
+    },
+}
+
+impl<'a, B: Backend> Command<'a, B> {
+    /// Record the command into a command buffer.
+    pub fn replay<C: RawCommandBuffer<B>>(&self, cmd_buffer: &mut C) {
+        match *self {
+            Command::PipelineBarrier { ref stages, dependencies, ref barriers } => {
+                cmd_buffer.pipeline_barrier(stages.clone(), dependencies, barriers)
+            }
+            Command::FillBuffer { buffer, range, data } => {
+                cmd_buffer.fill_buffer(buffer, range, data)
+            }
+            Command::UpdateBuffer { buffer, offset, ref data } => {
+                cmd_buffer.update_buffer(buffer, offset, data)
+            }
+            Command::ClearImage { image, layout, color, depth_stencil, ref subresource_ranges } => {
+                cmd_buffer.clear_image(image, layout, color, depth_stencil, subresource_ranges)
+            }
+            Command::ClearAttachments { ref clears, ref rects } => {
+                cmd_buffer.clear_attachments(clears, rects)
+            }
+            Command::ResolveImage { src, src_layout, dst, dst_layout, ref regions } => {
+                cmd_buffer.resolve_image(src, src_layout, dst, dst_layout, regions)
+            }
+            Command::BlitImage { src, src_layout, dst, dst_layout, filter, ref regions } => {
+                cmd_buffer.blit_image(src, src_layout, dst, dst_layout, filter, regions)
+            }
+            Command::BindIndexBuffer { buffer, offset, index_type } => {
+                cmd_buffer.bind_index_buffer(buffer::IndexBufferView { buffer, offset, index_type })
+            }
+            Command::BindVertexBuffers { first_binding, ref buffers } => {
+                cmd_buffer.bind_vertex_buffers(first_binding, pso::VertexBufferSet(buffers.clone()))
+            }
+            Command::SetViewports { first_viewport, ref viewports } => {
+                cmd_buffer.set_viewports(first_viewport, viewports)
+            }
+            Command::SetScissors { first_scissor, ref rects } => {
+                cmd_buffer.set_scissors(first_scissor, rects)
+            }
+            Command::SetStencilReference { faces, value } => {
+                cmd_buffer.set_stencil_reference(faces, value)
+            }
+            Command::SetStencilReadMask { faces, value } => {
+                cmd_buffer.set_stencil_read_mask(faces, value)
+            }
+            Command::SetStencilWriteMask { faces, value } => {
+                cmd_buffer.set_stencil_write_mask(faces, value)
+            }
+            Command::SetBlendConstants(color) => cmd_buffer.set_blend_constants(color),
+            Command::SetDepthBounds(ref bounds) => cmd_buffer.set_depth_bounds(bounds.clone()),
+            Command::SetLineWidth(width) => cmd_buffer.set_line_width(width),
+            Command::SetDepthBias(depth_bias) => cmd_buffer.set_depth_bias(depth_bias),
+            Command::BeginRenderPass { render_pass, framebuffer, render_area, ref clear_values, first_subpass } => {
+                cmd_buffer.begin_render_pass(render_pass, framebuffer, render_area, clear_values, first_subpass)
+            }
+            Command::NextSubpass(contents) => cmd_buffer.next_subpass(contents),
+            Command::EndRenderPass => cmd_buffer.end_render_pass(),
+            Command::BindGraphicsPipeline(pipeline) => cmd_buffer.bind_graphics_pipeline(pipeline),
+            Command::BindGraphicsDescriptorSets { layout, first_set, ref sets, ref offsets } => {
+                cmd_buffer.bind_graphics_descriptor_sets(layout, first_set, sets.iter().cloned(), offsets)
+            }
+            Command::BindComputePipeline(pipeline) => cmd_buffer.bind_compute_pipeline(pipeline),
+            Command::BindComputeDescriptorSets { layout, first_set, ref sets, ref offsets } => {
+                cmd_buffer.bind_compute_descriptor_sets(layout, first_set, sets.iter().cloned(), offsets)
+            }
+            Command::Dispatch(count) => cmd_buffer.dispatch(count),
+            Command::DispatchIndirect { buffer, offset } => cmd_buffer.dispatch_indirect(buffer, offset),
+            Command::CopyBuffer { src, dst, ref regions } => cmd_buffer.copy_buffer(src, dst, regions),
+            Command::CopyImage { src, src_layout, dst, dst_layout, ref regions } => {
+                cmd_buffer.copy_image(src, src_layout, dst, dst_layout, regions)
+            }
+            Command::CopyBufferToImage { src, dst, dst_layout, ref regions } => {
+                cmd_buffer.copy_buffer_to_image(src, dst, dst_layout, regions)
+            }
+            Command::CopyImageToBuffer { src, src_layout, dst, ref regions } => {
+                cmd_buffer.copy_image_to_buffer(src, src_layout, dst, regions)
+            }
+            Command::Draw { ref vertices, ref instances } => {
+                cmd_buffer.draw(vertices.clone(), instances.clone())
+            }
+            Command::DrawIndexed { ref indices, base_vertex, ref instances } => {
+                cmd_buffer.draw_indexed(indices.clone(), base_vertex, instances.clone())
+            }
+            Command::DrawIndirect { buffer, offset, draw_count, stride } => {
+                cmd_buffer.draw_indirect(buffer, offset, draw_count, stride)
+            }
+            Command::DrawIndexedIndirect { buffer, offset, draw_count, stride } => {
+                cmd_buffer.draw_indexed_indirect(buffer, offset, draw_count, stride)
+            }
+            Command::BeginQuery { pool, id, flags } => cmd_buffer.begin_query(Query { pool, id }, flags),
+            Command::EndQuery { pool, id } => cmd_buffer.end_query(Query { pool, id }),
+            Command::ResetQueryPool { pool, ref queries } => {
+                cmd_buffer.reset_query_pool(pool, queries.clone())
+            }
+            Command::WriteTimestamp { stage, pool, id } => {
+                cmd_buffer.write_timestamp(stage, Query { pool, id })
+            }
+            Command::PushGraphicsConstants { layout, stages, offset, ref constants } => {
+                cmd_buffer.push_graphics_constants(layout, stages, offset, constants)
+            }
+            Command::PushComputeConstants { layout, offset, ref constants } => {
+                cmd_buffer.push_compute_constants(layout, offset, constants)
+            }
+        }
+    }
+}
+
+/// An owned, backend-agnostic list of commands.
+///
+/// Recording methods mirror the ones of `RawCommandBuffer`, but borrow the resources for
+/// the lifetime of the list instead of the duration of the call. Secondary command buffers
+/// can't be recorded, lists are composed by extending `commands` instead.
+///
+/// # Example
+///
+/// ```rust
+/// # extern crate gfx_backend_empty as empty;
+/// # extern crate gfx_hal as hal;
+/// # fn main() {
+/// use hal::command::{Command, CommandList, RawCommandBuffer};
+///
+/// let buffer = ();
+/// let mut list = CommandList::<empty::Backend>::new();
+/// list.fill_buffer(&buffer, 0 .. 256, 0);
+/// list.dispatch([64, 1, 1]);
+///
+/// match list.commands[1] {
+///     Command::Dispatch(count) => assert_eq!(count, [64, 1, 1]),
+///     _ => unreachable!(),
+/// }
+///
+/// // Later, on the thread owning the command buffer:
+/// # fn record<C: RawCommandBuffer<empty::Backend>>(list: &CommandList<empty::Backend>, cmd_buffer: &mut C) {
+/// list.replay(cmd_buffer);
+/// # }
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct CommandList<'a, B: Backend> {
+    /// Recorded commands, in submission order.
+    pub commands: Vec<Command<'a, B>>,
+}
+
+impl<'a, B: Backend> CommandList<'a, B> {
+    /// Create an empty command list.
+    pub fn new() -> Self {
+        CommandList {
+            commands: Vec::new(),
+        }
+    }
+
+    /// Record all commands of the list into a command buffer, in order.
+    ///
+    /// The command buffer needs to be in the recording state.
+    pub fn replay<C: RawCommandBuffer<B>>(&self, cmd_buffer: &mut C) {
+        for command in &self.commands {
+            command.replay(cmd_buffer);
+        }
+    }
+
+    ///
+    pub fn pipeline_barrier<T>(
+        &mut self,
+        stages: Range<pso::PipelineStage>,
+        dependencies: Dependencies,
+        barriers: T,
+    ) where
+        T: IntoIterator,
+        T::Item: Borrow<Barrier<'a, B>>,
+    {
+        self.commands.push(Command::PipelineBarrier {
+            stages,
+            dependencies,
+            barriers: barriers.into_iter().map(|b| b.borrow().clone()).collect(),
+        });
+    }
+
+    ///
+    pub fn fill_buffer<R>(&mut self, buffer: &'a B::Buffer, range: R, data: u32)
+    where
+        R: RangeArg<buffer::Offset>,
+    {
+        self.commands.push(Command::FillBuffer {
+            buffer,
+            range: (range.start().cloned(), range.end().cloned()),
+            data,
+        });
+    }
+
+    ///
+    pub fn update_buffer(&mut self, buffer: &'a B::Buffer, offset: buffer::Offset, data: &[u8]) {
+        self.commands.push(Command::UpdateBuffer {
+            buffer,
+            offset,
+            data: data.to_vec(),
+        });
+    }
+
+    ///
+    pub fn clear_image<T>(
+        &mut self,
+        image: &'a B::Image,
+        layout: Layout,
+        color: ClearColorRaw,
+        depth_stencil: ClearDepthStencilRaw,
+        subresource_ranges: T,
+    ) where
+        T: IntoIterator,
+        T::Item: Borrow<SubresourceRange>,
+    {
+        self.commands.push(Command::ClearImage {
+            image,
+            layout,
+            color,
+            depth_stencil,
+            subresource_ranges: collect(subresource_ranges),
+        });
+    }
+
+    ///
+    pub fn clear_attachments<T, U>(&mut self, clears: T, rects: U)
+    where
+        T: IntoIterator,
+        T::Item: Borrow<AttachmentClear>,
+        U: IntoIterator,
+        U::Item: Borrow<pso::ClearRect>,
+    {
+        self.commands.push(Command::ClearAttachments {
+            clears: collect(clears),
+            rects: collect(rects),
+        });
+    }
+
+    ///
+    pub fn resolve_image<T>(
+        &mut self,
+        src: &'a B::Image,
+        src_layout: Layout,
+        dst: &'a B::Image,
+        dst_layout: Layout,
+        regions: T,
+    ) where
+        T: IntoIterator,
+        T::Item: Borrow<ImageResolve>,
+    {
+        self.commands.push(Command::ResolveImage {
+            src,
+            src_layout,
+            dst,
+            dst_layout,
+            regions: collect(regions),
+        });
+    }
+
+    ///
+    pub fn blit_image<T>(
+        &mut self,
+        src: &'a B::Image,
+        src_layout: Layout,
+        dst: &'a B::Image,
+        dst_layout: Layout,
+        filter: Filter,
+        regions: T,
+    ) where
+        T: IntoIterator,
+        T::Item: Borrow<ImageBlit>,
+    {
+        self.commands.push(Command::BlitImage {
+            src,
+            src_layout,
+            dst,
+            dst_layout,
+            filter,
+            regions: collect(regions),
+        });
+    }
+
+    ///
+    pub fn bind_index_buffer(&mut self, view: buffer::IndexBufferView<'a, B>) {
+        self.commands.push(Command::BindIndexBuffer {
+            buffer: view.buffer,
+            offset: view.offset,
+            index_type: view.index_type,
+        });
+    }
+
+    ///
+    pub fn bind_vertex_buffers(&mut self, first_binding: u32, buffers: pso::VertexBufferSet<'a, B>) {
+        self.commands.push(Command::BindVertexBuffers {
+            first_binding,
+            buffers: buffers.0,
+        });
+    }
+
+    ///
+    pub fn set_viewports<T>(&mut self, first_viewport: u32, viewports: T)
+    where
+        T: IntoIterator,
+        T::Item: Borrow<pso::Viewport>,
+    {
+        self.commands.push(Command::SetViewports {
+            first_viewport,
+            viewports: collect(viewports),
+        });
+    }
+
+    ///
+    pub fn set_scissors<T>(&mut self, first_scissor: u32, rects: T)
+    where
+        T: IntoIterator,
+        T::Item: Borrow<pso::Rect>,
+    {
+        self.commands.push(Command::SetScissors {
+            first_scissor,
+            rects: collect(rects),
+        });
+    }
+
+    ///
+    pub fn set_stencil_reference(&mut self, faces: pso::Face, value: pso::StencilValue) {
+        self.commands.push(Command::SetStencilReference { faces, value });
+    }
+
+    ///
+    pub fn set_stencil_read_mask(&mut self, faces: pso::Face, value: pso::StencilValue) {
+        self.commands.push(Command::SetStencilReadMask { faces, value });
+    }
+
+    ///
+    pub fn set_stencil_write_mask(&mut self, faces: pso::Face, value: pso::StencilValue) {
+        self.commands.push(Command::SetStencilWriteMask { faces, value });
+    }
+
+    ///
+    pub fn set_blend_constants(&mut self, color: pso::ColorValue) {
+        self.commands.push(Command::SetBlendConstants(color));
+    }
+
+    ///
+    pub fn set_depth_bounds(&mut self, bounds: Range<f32>) {
+        self.commands.push(Command::SetDepthBounds(bounds));
+    }
+
+    ///
+    pub fn set_line_width(&mut self, width: f32) {
+        self.commands.push(Command::SetLineWidth(width));
+    }
+
+    ///
+    pub fn set_depth_bias(&mut self, depth_bias: pso::DepthBias) {
+        self.commands.push(Command::SetDepthBias(depth_bias));
+    }
+
+    ///
+    pub fn begin_render_pass<T>(
+        &mut self,
+        render_pass: &'a B::RenderPass,
+        framebuffer: &'a B::Framebuffer,
+        render_area: pso::Rect,
+        clear_values: T,
+        first_subpass: SubpassContents,
+    ) where
+        T: IntoIterator,
+        T::Item: Borrow<ClearValueRaw>,
+    {
+        self.commands.push(Command::BeginRenderPass {
+            render_pass,
+            framebuffer,
+            render_area,
+            clear_values: collect(clear_values),
+            first_subpass,
+        });
+    }
+
+    ///
+    pub fn next_subpass(&mut self, contents: SubpassContents) {
+        self.commands.push(Command::NextSubpass(contents));
+    }
+
+    ///
+    pub fn end_render_pass(&mut self) {
+        self.commands.push(Command::EndRenderPass);
+    }
+
+    ///
+    pub fn bind_graphics_pipeline(&mut self, pipeline: &'a B::GraphicsPipeline) {
+        self.commands.push(Command::BindGraphicsPipeline(pipeline));
+    }
+
+    ///
+    pub fn bind_graphics_descriptor_sets<I, J>(
+        &mut self,
+        layout: &'a B::PipelineLayout,
+        first_set: usize,
+        sets: I,
+        offsets: J,
+    ) where
+        I: IntoIterator<Item = &'a B::DescriptorSet>,
+        J: IntoIterator,
+        J::Item: Borrow<DescriptorSetOffset>,
+    {
+        self.commands.push(Command::BindGraphicsDescriptorSets {
+            layout,
+            first_set,
+            sets: sets.into_iter().collect(),
+            offsets: collect(offsets),
+        });
+    }
+
+    ///
+    pub fn bind_compute_pipeline(&mut self, pipeline: &'a B::ComputePipeline) {
+        self.commands.push(Command::BindComputePipeline(pipeline));
+    }
+
+    ///
+    pub fn bind_compute_descriptor_sets<I, J>(
+        &mut self,
+        layout: &'a B::PipelineLayout,
+        first_set: usize,
+        sets: I,
+        offsets: J,
+    ) where
+        I: IntoIterator<Item = &'a B::DescriptorSet>,
+        J: IntoIterator,
+        J::Item: Borrow<DescriptorSetOffset>,
+    {
+        self.commands.push(Command::BindComputeDescriptorSets {
+            layout,
+            first_set,
+            sets: sets.into_iter().collect(),
+            offsets: collect(offsets),
+        });
+    }
+
+    ///
+    pub fn dispatch(&mut self, count: WorkGroupCount) {
+        self.commands.push(Command::Dispatch(count));
+    }
+
+    ///
+    pub fn dispatch_indirect(&mut self, buffer: &'a B::Buffer, offset: buffer::Offset) {
+        self.commands.push(Command::DispatchIndirect { buffer, offset });
+    }
+
+    ///
+    pub fn copy_buffer<T>(&mut self, src: &'a B::Buffer, dst: &'a B::Buffer, regions: T)
+    where
+        T: IntoIterator,
+        T::Item: Borrow<BufferCopy>,
+    {
+        self.commands.push(Command::CopyBuffer {
+            src,
+            dst,
+            regions: collect(regions),
+        });
+    }
+
+    ///
+    pub fn copy_image<T>(
+        &mut self,
+        src: &'a B::Image,
+        src_layout: Layout,
+        dst: &'a B::Image,
+        dst_layout: Layout,
+        regions: T,
+    ) where
+        T: IntoIterator,
+        T::Item: Borrow<ImageCopy>,
+    {
+        self.commands.push(Command::CopyImage {
+            src,
+            src_layout,
+            dst,
+            dst_layout,
+            regions: collect(regions),
+        });
+    }
+
+    ///
+    pub fn copy_buffer_to_image<T>(
+        &mut self,
+        src: &'a B::Buffer,
+        dst: &'a B::Image,
+        dst_layout: Layout,
+        regions: T,
+    ) where
+        T: IntoIterator,
+        T::Item: Borrow<BufferImageCopy>,
+    {
+        self.commands.push(Command::CopyBufferToImage {
+            src,
+            dst,
+            dst_layout,
+            regions: collect(regions),
+        });
+    }
+
+    ///
+    pub fn copy_image_to_buffer<T>(
+        &mut self,
+        src: &'a B::Image,
+        src_layout: Layout,
+        dst: &'a B::Buffer,
+        regions: T,
+    ) where
+        T: IntoIterator,
+        T::Item: Borrow<BufferImageCopy>,
+    {
+        self.commands.push(Command::CopyImageToBuffer {
+            src,
+            src_layout,
+            dst,
+            regions: collect(regions),
+        });
+    }
+
+    ///
+    pub fn draw(&mut self, vertices: Range<VertexCount>, instances: Range<InstanceCount>) {
+        self.commands.push(Command::Draw { vertices, instances });
+    }
+
+    ///
+    pub fn draw_indexed(
+        &mut self,
+        indices: Range<IndexCount>,
+        base_vertex: VertexOffset,
+        instances: Range<InstanceCount>,
+    ) {
+        self.commands.push(Command::DrawIndexed { indices, base_vertex, instances });
+    }
+
+    ///
+    pub fn draw_indirect(
+        &mut self,
+        buffer: &'a B::Buffer,
+        offset: buffer::Offset,
+        draw_count: DrawCount,
+        stride: u32,
+    ) {
+        self.commands.push(Command::DrawIndirect { buffer, offset, draw_count, stride });
+    }
+
+    ///
+    pub fn draw_indexed_indirect(
+        &mut self,
+        buffer: &'a B::Buffer,
+        offset: buffer::Offset,
+        draw_count: DrawCount,
+        stride: u32,
+    ) {
+        self.commands.push(Command::DrawIndexedIndirect { buffer, offset, draw_count, stride });
+    }
+
+    ///
+    pub fn begin_query(&mut self, query: Query<'a, B>, flags: QueryControl) {
+        self.commands.push(Command::BeginQuery { pool: query.pool, id: query.id, flags });
+    }
+
+    ///
+    pub fn end_query(&mut self, query: Query<'a, B>) {
+        self.commands.push(Command::EndQuery { pool: query.pool, id: query.id });
+    }
+
+    ///
+    pub fn reset_query_pool(&mut self, pool: &'a B::QueryPool, queries: Range<QueryId>) {
+        self.commands.push(Command::ResetQueryPool { pool, queries });
+    }
+
+    ///
+    pub fn write_timestamp(&mut self, stage: pso::PipelineStage, query: Query<'a, B>) {
+        self.commands.push(Command::WriteTimestamp { stage, pool: query.pool, id: query.id });
+    }
+
+    ///
+    pub fn push_graphics_constants(
+        &mut self,
+        layout: &'a B::PipelineLayout,
+        stages: pso::ShaderStageFlags,
+        offset: u32,
+        constants: &[u32],
+    ) {
+        self.commands.push(Command::PushGraphicsConstants {
+            layout,
+            stages,
+            offset,
+            constants: constants.to_vec(),
+        });
+    }
+
+    ///
+    pub fn push_compute_constants(
+        &mut self,
+        layout: &'a B::PipelineLayout,
+        offset: u32,
+        constants: &[u32],
+    ) {
+        self.commands.push(Command::PushComputeConstants {
+            layout,
+            offset,
+            constants: constants.to_vec(),
+        });
+    }
+}
+
+fn collect<T: Clone, I>(items: I) -> Vec<T>
+where
+    I: IntoIterator,
+    I::Item: Borrow<T>,
+{
+    items.into_iter().map(|item| item.borrow().clone()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use buffer::{self, IndexBufferView};
+    use memory::{Barrier, Dependencies};
+    use mock::{Backend, RawCommandBuffer, Resource};
+    use pso::{self, PipelineStage, VertexBufferSet};
+    use {IndexType, command};
+    use super::*;
+
+    #[test]
+    fn replay_in_order() {
+        let (buffer, pipeline, render_pass, framebuffer) =
+            (Resource(0), Resource(1), Resource(2), Resource(3));
+        let mut list = CommandList::<Backend>::new();
+        list.update_buffer(&buffer, 0, &[0; 16]);
+        list.pipeline_barrier(
+            PipelineStage::TRANSFER .. PipelineStage::VERTEX_INPUT,
+            Dependencies::empty(),
+            &[Barrier::Buffer {
+                states: buffer::Access::TRANSFER_WRITE .. buffer::Access::INDEX_BUFFER_READ,
+                target: &buffer,
+                families: None,
+            }],
+        );
+        let area = pso::Rect { x: 0, y: 0, w: 64, h: 64 };
+        list.begin_render_pass(&render_pass, &framebuffer, area, &[], command::SubpassContents::Inline);
+        list.bind_graphics_pipeline(&pipeline);
+        list.bind_vertex_buffers(0, VertexBufferSet(vec![(&buffer, 16)]));
+        list.bind_index_buffer(IndexBufferView { buffer: &buffer, offset: 0, index_type: IndexType::U16 });
+        list.draw_indexed(0 .. 6, 0, 0 .. 1);
+        list.draw(0 .. 3, 0 .. 1);
+        list.end_render_pass();
+
+        let mut cmd_buffer = RawCommandBuffer::default();
+        list.replay(&mut cmd_buffer);
+        let expected = [
+            "update_buffer",
+            "pipeline_barrier",
+            "begin_render_pass",
+            "bind_graphics_pipeline",
+            "bind_vertex_buffers",
+            "bind_index_buffer",
+            "draw_indexed",
+            "draw",
+            "end_render_pass",
+        ];
+        assert_eq!(cmd_buffer.commands, expected);
+
+        // the list is left untouched and can be replayed again
+        list.replay(&mut cmd_buffer);
+        assert_eq!(cmd_buffer.commands[.. expected.len()], expected);
+        assert_eq!(cmd_buffer.commands[expected.len() ..], expected);
+    }
+}
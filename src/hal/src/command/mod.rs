@@ -19,11 +19,13 @@ use std::marker::PhantomData;
 
 mod compute;
 mod graphics;
+mod list;
 mod raw;
 mod render_pass;
 mod transfer;
 
 pub use self::graphics::*;
+pub use self::list::{Command, CommandList};
 pub use self::raw::{
     ClearValueRaw, ClearColorRaw, ClearDepthStencilRaw, DescriptorSetOffset,
     RawCommandBuffer, CommandBufferFlags, Level as RawLevel, CommandBufferInheritanceInfo,
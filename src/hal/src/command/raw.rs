@@ -1,5 +1,6 @@
 use std::any::Any;
 use std::borrow::Borrow;
+use std::fmt;
 use std::ops::Range;
 
 use {buffer, pass, pso};
@@ -26,6 +27,12 @@ pub union ClearColorRaw {
     _align: [u32; 4],
 }
 
+impl fmt::Debug for ClearColorRaw {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "ClearColorRaw({:?})", unsafe { self.uint32 })
+    }
+}
+
 /// A variant of `ClearDepthStencil` that has a `#[repr(C)]` layout
 /// and so is used when a known layout is needed.
 #[repr(C)]
@@ -48,6 +55,12 @@ pub union ClearValueRaw {
     _align: [u32; 4],
 }
 
+impl fmt::Debug for ClearValueRaw {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "ClearValueRaw({:?})", unsafe { self._align })
+    }
+}
+
 /// Offset for dynamic descriptors.
 pub type DescriptorSetOffset = u32;
 
@@ -197,6 +210,26 @@ pub trait RawCommandBuffer<B: Backend>: Clone + Any + Send + Sync {
         T: IntoIterator,
         T::Item: Borrow<ImageBlit>;
 
+    /// Copies regions from the source image to the color attachment of an opaque
+    /// framebuffer, like the one of a `Backbuffer::Framebuffer`, applying scaling
+    /// and filtering. The destination subresource of the regions is ignored.
+    ///
+    /// Only backends returning opaque framebuffers from `create_swapchain` support it.
+    fn blit_image_to_framebuffer<T>(
+        &mut self,
+        _src: &B::Image,
+        _src_layout: Layout,
+        _dst: &B::Framebuffer,
+        _filter: Filter,
+        _regions: T,
+    ) where
+        T: IntoIterator,
+        T::Item: Borrow<ImageBlit>,
+    {
+        panic!("blitting to opaque framebuffers is not supported by this backend, \
+            its swapchains only provide images")
+    }
+
     /// Bind the index buffer view, making it the "current" one that draw commands
     /// will operate on.
     fn bind_index_buffer(&mut self, buffer::IndexBufferView<B>);
@@ -11,6 +11,7 @@ use super::{
 };
 
 /// Specifies how commands for the following renderpasses will be recorded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum SubpassContents {
     /// Contents of the subpass will be inline in the command buffer,
     /// NOT in secondary command buffers.
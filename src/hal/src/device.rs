@@ -11,6 +11,7 @@
 
 use std::{fmt, mem, slice};
 use std::any::Any;
+use std::marker::PhantomData;
 use std::borrow::Borrow;
 use std::error::Error;
 use std::ops::Range;
@@ -19,7 +20,7 @@ use {buffer, format, image, mapping, pass, pso, query, window};
 use {Backend, MemoryTypeId};
 
 use error::HostExecutionError;
-use memory::Requirements;
+use memory::{Dedicated, HeapBudget, Requirements};
 use pool::{CommandPool, CommandPoolCreateFlags};
 use queue::{QueueFamilyId, QueueGroup};
 use range::RangeArg;
@@ -139,9 +140,27 @@ pub trait Device<B: Backend>: Any + Send + Sync {
     /// * `size` - Size of the allocation.
     fn allocate_memory(&self, memory_type: MemoryTypeId, size: u64) -> Result<B::Memory, OutOfMemory>;
 
+    /// Allocates memory dedicated to a single buffer or image.
+    ///
+    /// Should be used for resources with `Requirements::prefers_dedicated` or
+    /// `Requirements::requires_dedicated` set, binding the resource at offset 0.
+    /// Backends without dedicated allocations fall back to `allocate_memory`.
+    fn allocate_dedicated_memory(
+        &self, memory_type: MemoryTypeId, size: u64, _dedicated: Dedicated<B>,
+    ) -> Result<B::Memory, OutOfMemory> {
+        self.allocate_memory(memory_type, size)
+    }
+
     ///
     fn free_memory(&self, memory: B::Memory);
 
+    /// Query the budget and current usage of each memory heap.
+    ///
+    /// The returned list is indexed the same way as `MemoryProperties::memory_heaps`.
+    /// Backends without a native query report the heap size as budget and
+    /// the sum of the allocations made through this device as usage.
+    fn memory_budget(&self) -> Vec<HeapBudget>;
+
     /// Creates a new command pool for a given queue family.
     ///
     /// *Note*: the family has to be associated by one as the `Gpu::queue_groups`.
@@ -285,6 +304,18 @@ pub trait Device<B: Backend>: Any + Send + Sync {
         &self, size: u64, usage: buffer::Usage,
     ) -> Result<B::UnboundBuffer, buffer::CreationError>;
 
+    /// Create a new buffer (unbound), which may be accessed concurrently by
+    /// queues of the given families without explicit ownership transfers.
+    ///
+    /// Buffers created with `create_buffer` are exclusive to one queue family at
+    /// a time and require a `memory::Barrier` with `families` set to be handed over.
+    /// Backends without the concept of queue family ownership fall back to `create_buffer`.
+    fn create_buffer_concurrent(
+        &self, size: u64, usage: buffer::Usage, _families: &[QueueFamilyId],
+    ) -> Result<B::UnboundBuffer, buffer::CreationError> {
+        self.create_buffer(size, usage)
+    }
+
     ///
     fn get_buffer_requirements(&self, buf: &B::UnboundBuffer) -> Requirements;
 
@@ -317,6 +348,16 @@ pub trait Device<B: Backend>: Any + Send + Sync {
         tiling: image::Tiling, usage: image::Usage, storage_flags: image::StorageFlags,
     ) -> Result<B::UnboundImage, image::CreationError>;
 
+    /// Create a new image (unbound), which may be accessed concurrently by
+    /// queues of the given families, see `create_buffer_concurrent`.
+    fn create_image_concurrent(
+        &self, kind: image::Kind, mip_levels: image::Level, format: format::Format,
+        tiling: image::Tiling, usage: image::Usage, storage_flags: image::StorageFlags,
+        _families: &[QueueFamilyId],
+    ) -> Result<B::UnboundImage, image::CreationError> {
+        self.create_image(kind, mip_levels, format, tiling, usage, storage_flags)
+    }
+
     ///
     fn get_image_requirements(&self, image: &B::UnboundImage) -> Requirements;
 
@@ -325,7 +366,12 @@ pub trait Device<B: Backend>: Any + Send + Sync {
         &self, image: &B::Image, subresource: image::Subresource
     ) -> image::SubresourceFootprint;
 
+    /// Bind memory to an image.
     ///
+    /// Multiple images may be bound to overlapping ranges of the same memory,
+    /// as long as only one of them is used at a time and switching between them
+    /// is done with a `Barrier::Aliasing`, see `memory::AliasingTracker`.
+    /// This is not validated by the backends.
     fn bind_image_memory(
         &self, &B::Memory, offset: u64, B::UnboundImage
     ) -> Result<B::Image, BindError>;
@@ -346,6 +392,23 @@ pub trait Device<B: Backend>: Any + Send + Sync {
         range: image::SubresourceRange,
     ) -> Result<B::ImageView, image::ViewError>;
 
+    /// Create an image view which applies the Y'CbCr `conversion` when sampled.
+    ///
+    /// The conversion has to match the one of the sampler the view is used with.
+    /// Backends without `Features::SAMPLER_YCBCR_CONVERSION` ignore the conversion.
+    fn create_image_view_with_conversion(
+        &self,
+        image: &B::Image,
+        view_kind: image::ViewKind,
+        format: format::Format,
+        swizzle: format::Swizzle,
+        range: image::SubresourceRange,
+        conversion: &image::YcbcrConversion,
+    ) -> Result<B::ImageView, image::ViewError> {
+        let _ = conversion;
+        self.create_image_view(image, view_kind, format, swizzle, range)
+    }
+
     ///
     fn destroy_image_view(&self, view: B::ImageView);
 
@@ -415,21 +478,29 @@ pub trait Device<B: Backend>: Any + Send + Sync {
     ///
     fn unmap_memory(&self, memory: &B::Memory);
 
+    /// Returns the coherency of host-visible `memory`, used for flushing and
+    /// invalidating mappings.
+    fn memory_coherency(&self, memory: &B::Memory) -> mapping::Coherency;
+
     /// Acquire a mapping Reader.
     ///
     /// The accessible slice will correspond to the specified range (in bytes).
+    /// Non-coherent memory is invalidated before reading.
     fn acquire_mapping_reader<'a, T>(&self, memory: &'a B::Memory, range: Range<u64>)
         -> Result<mapping::Reader<'a, B, T>, mapping::Error>
     where
         T: Copy,
     {
-        let len = range.end - range.start;
-        let count = len as usize / mem::size_of::<T>();
-        self.map_memory(memory, range.clone())
+        let coherency = self.memory_coherency(memory);
+        let mapped = coherency.mapped_range(range.clone());
+        let count = (range.end - range.start) as usize / mem::size_of::<T>();
+        self.map_memory(memory, mapped.clone())
             .map(|ptr| unsafe {
-                let start_ptr = ptr as *const _;
-                self.invalidate_mapped_memory_ranges(Some((memory, range.clone())));
+                if coherency != mapping::Coherency::Coherent {
+                    self.invalidate_mapped_memory_ranges(Some((memory, mapped.clone())));
+                }
 
+                let start_ptr = ptr.offset((range.start - mapped.start) as isize) as *const _;
                 mapping::Reader {
                     slice: slice::from_raw_parts(start_ptr, count),
                     memory,
@@ -447,19 +518,22 @@ pub trait Device<B: Backend>: Any + Send + Sync {
     /// Acquire a mapping Writer.
     ///
     /// The accessible slice will correspond to the specified range (in bytes).
+    /// Non-coherent memory is flushed when the writer is released.
     fn acquire_mapping_writer<'a, T>(&self, memory: &'a B::Memory, range: Range<u64>)
         -> Result<mapping::Writer<'a, B, T>, mapping::Error>
     where
         T: Copy,
     {
+        let coherency = self.memory_coherency(memory);
+        let mapped = coherency.mapped_range(range.clone());
         let count = (range.end - range.start) as usize / mem::size_of::<T>();
-        self.map_memory(memory, range.clone())
+        self.map_memory(memory, mapped.clone())
             .map(|ptr| unsafe {
-                let start_ptr = ptr as *mut _;
+                let start_ptr = ptr.offset((range.start - mapped.start) as isize) as *mut _;
                 mapping::Writer {
                     slice: slice::from_raw_parts_mut(start_ptr, count),
                     memory,
-                    range,
+                    flush: coherency.align(range),
                     released: false,
                 }
             })
@@ -468,10 +542,127 @@ pub trait Device<B: Backend>: Any + Send + Sync {
     /// Release a mapping Writer.
     fn release_mapping_writer<'a, T>(&self, mut writer: mapping::Writer<'a, B, T>) {
         writer.released = true;
-        self.flush_mapped_memory_ranges(Some((writer.memory, writer.range.clone())));
+        if let Some(range) = writer.flush.take() {
+            self.flush_mapped_memory_ranges(Some((writer.memory, range)));
+        }
         self.unmap_memory(writer.memory);
     }
 
+    /// Acquire a persistent mapping of `range`, see `mapping::Persistent`.
+    fn acquire_persistent_mapping(&self, memory: &B::Memory, range: Range<u64>)
+        -> Result<mapping::Persistent<B>, mapping::Error>
+    {
+        let coherency = self.memory_coherency(memory);
+        let mapped = coherency.mapped_range(range.clone());
+        self.map_memory(memory, mapped.clone())
+            .map(|ptr| mapping::Persistent {
+                ptr,
+                mapped,
+                range,
+                coherency,
+                released: false,
+                phantom: PhantomData,
+            })
+    }
+
+    /// Make host writes to `range` of a persistent mapping visible to the device.
+    ///
+    /// `memory` must be the memory the mapping was acquired from.
+    fn flush_persistent_mapping(
+        &self, memory: &B::Memory, mapping: &mapping::Persistent<B>, range: Range<u64>
+    ) {
+        if let Some(range) = mapping.sync_range(range) {
+            self.flush_mapped_memory_ranges(Some((memory, range)));
+        }
+    }
+
+    /// Make device writes to `range` of a persistent mapping visible to the host.
+    ///
+    /// `memory` must be the memory the mapping was acquired from.
+    fn invalidate_persistent_mapping(
+        &self, memory: &B::Memory, mapping: &mapping::Persistent<B>, range: Range<u64>
+    ) {
+        if let Some(range) = mapping.sync_range(range) {
+            self.invalidate_mapped_memory_ranges(Some((memory, range)));
+        }
+    }
+
+    /// Release a persistent mapping.
+    ///
+    /// `memory` must be the memory the mapping was acquired from.
+    fn release_persistent_mapping(&self, memory: &B::Memory, mut mapping: mapping::Persistent<B>) {
+        mapping.released = true;
+        self.unmap_memory(memory);
+    }
+
+    /// Write a subresource of a linear image from the host, without recording commands.
+    ///
+    /// The image has to be created with `Tiling::Linear` and bound at `offset`
+    /// of `CPU_VISIBLE` memory. `extent` and `format` describe the subresource,
+    /// for planes of multi-planar formats these are the plane format and subsampled extent.
+    /// `data` holds the texel blocks tightly packed, row by row and slice by slice,
+    /// the row, array and depth pitches of the image are taken from
+    /// `get_image_subresource_footprint`.
+    ///
+    /// The image must not be in use by the device. `memory` is mapped for the
+    /// duration of the call, so it must not be mapped already, e.g. by a
+    /// `Persistent` mapping: write through that mapping instead.
+    fn write_linear_image(
+        &self,
+        image: &B::Image,
+        memory: &B::Memory,
+        offset: u64,
+        subresource: image::Subresource,
+        extent: image::Extent,
+        format: format::Format,
+        data: &[u8],
+    ) -> Result<(), mapping::Error> {
+        let footprint = self.get_image_subresource_footprint(image, subresource);
+        let (row_size, rows) = packed_image_layout(format, extent);
+        assert!(data.len() >= row_size * (rows * extent.depth) as usize,
+            "Not enough data for the image subresource");
+        assert!(pitched_size(row_size, rows, extent.depth, &footprint) <= footprint.slice.end - footprint.slice.start,
+            "Image subresource footprint doesn't cover the extent");
+
+        let range = offset + footprint.slice.start .. offset + footprint.slice.end;
+        let mut writer = self.acquire_mapping_writer::<u8>(memory, range)?;
+        pitch_rows(&mut writer, data, row_size, rows, extent.depth, &footprint);
+        self.release_mapping_writer(writer);
+        Ok(())
+    }
+
+    /// Read a subresource of a linear image to the host, without recording commands.
+    ///
+    /// Counterpart of `write_linear_image`, with the same requirements on
+    /// `image`, `memory`, `extent` and `format`. The texel blocks are written
+    /// tightly packed into `data`.
+    ///
+    /// Device writes to the image must have completed and been made visible to the host.
+    /// As for `write_linear_image`, `memory` must not be mapped already.
+    fn read_linear_image(
+        &self,
+        image: &B::Image,
+        memory: &B::Memory,
+        offset: u64,
+        subresource: image::Subresource,
+        extent: image::Extent,
+        format: format::Format,
+        data: &mut [u8],
+    ) -> Result<(), mapping::Error> {
+        let footprint = self.get_image_subresource_footprint(image, subresource);
+        let (row_size, rows) = packed_image_layout(format, extent);
+        assert!(data.len() >= row_size * (rows * extent.depth) as usize,
+            "Not enough space for the image subresource");
+        assert!(pitched_size(row_size, rows, extent.depth, &footprint) <= footprint.slice.end - footprint.slice.start,
+            "Image subresource footprint doesn't cover the extent");
+
+        let range = offset + footprint.slice.start .. offset + footprint.slice.end;
+        let reader = self.acquire_mapping_reader::<u8>(memory, range)?;
+        pack_rows(data, &reader, row_size, rows, extent.depth, &footprint);
+        self.release_mapping_reader(reader);
+        Ok(())
+    }
+
     ///
     fn create_semaphore(&self) -> B::Semaphore;
 
@@ -606,3 +797,103 @@ pub trait Device<B: Backend>: Any + Send + Sync {
     /// Host access to all queues needs to be **externally** sycnhronized!
     fn wait_idle(&self) -> Result<(), HostExecutionError>;
 }
+
+/// Returns the size in bytes of a tightly packed row of texel blocks and
+/// the number of rows per depth slice.
+fn packed_image_layout(format: format::Format, extent: image::Extent) -> (usize, u32) {
+    let desc = format.surface_desc();
+    let (block_width, block_height) = (desc.dim.0 as u32, desc.dim.1 as u32);
+    let blocks_per_row = (extent.width + block_width - 1) / block_width;
+    let rows = (extent.height + block_height - 1) / block_height;
+    (blocks_per_row as usize * desc.bits as usize / 8, rows)
+}
+
+/// Returns the number of bytes spanned by `depth` slices of `rows` rows of
+/// `row_size` bytes, laid out with the pitches of `footprint`.
+fn pitched_size(row_size: usize, rows: u32, depth: u32, footprint: &image::SubresourceFootprint) -> u64 {
+    if rows == 0 || depth == 0 {
+        return 0;
+    }
+    (depth - 1) as u64 * footprint.depth_pitch + (rows - 1) as u64 * footprint.row_pitch + row_size as u64
+}
+
+/// Copy tightly packed rows from `src` to the pitched rows of `dst`,
+/// which starts at the slice of `footprint`.
+fn pitch_rows(
+    dst: &mut [u8], src: &[u8], row_size: usize, rows: u32, depth: u32,
+    footprint: &image::SubresourceFootprint,
+) {
+    for z in 0 .. depth as u64 {
+        for y in 0 .. rows as u64 {
+            let packed = ((z * rows as u64 + y) as usize) * row_size;
+            let pitched = (z * footprint.depth_pitch + y * footprint.row_pitch) as usize;
+            dst[pitched .. pitched + row_size].copy_from_slice(&src[packed .. packed + row_size]);
+        }
+    }
+}
+
+/// Copy the pitched rows of `src`, which starts at the slice of `footprint`,
+/// tightly packed to `dst`.
+fn pack_rows(
+    dst: &mut [u8], src: &[u8], row_size: usize, rows: u32, depth: u32,
+    footprint: &image::SubresourceFootprint,
+) {
+    for z in 0 .. depth as u64 {
+        for y in 0 .. rows as u64 {
+            let packed = ((z * rows as u64 + y) as usize) * row_size;
+            let pitched = (z * footprint.depth_pitch + y * footprint.row_pitch) as usize;
+            dst[packed .. packed + row_size].copy_from_slice(&src[pitched .. pitched + row_size]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use format::Format;
+    use image::{Extent, SubresourceFootprint};
+    use super::*;
+
+    // 3 texels of 4 bytes per row, 2 rows per slice, 2 slices
+    fn padded() -> SubresourceFootprint {
+        SubresourceFootprint {
+            slice: 0 .. 68,
+            row_pitch: 16,
+            array_pitch: 64,
+            depth_pitch: 40,
+        }
+    }
+
+    #[test]
+    fn packed_layout() {
+        let extent = Extent { width: 3, height: 2, depth: 2 };
+        assert_eq!(packed_image_layout(Format::Rgba8Unorm, extent), (12, 2));
+        // 4x4 blocks of 8 bytes
+        let extent = Extent { width: 9, height: 5, depth: 1 };
+        assert_eq!(packed_image_layout(Format::Bc1RgbUnorm, extent), (24, 2));
+    }
+
+    #[test]
+    fn pitched_extent() {
+        let footprint = padded();
+        assert_eq!(pitched_size(12, 2, 2, &footprint), 40 + 16 + 12);
+        assert_eq!(pitched_size(12, 2, 1, &footprint), 16 + 12);
+        assert_eq!(pitched_size(12, 0, 2, &footprint), 0);
+    }
+
+    #[test]
+    fn copy_padded_rows() {
+        let footprint = padded();
+        let packed: Vec<u8> = (1 .. 49).collect();
+        let mut pitched = vec![0; 68];
+        pitch_rows(&mut pitched, &packed, 12, 2, 2, &footprint);
+        for &(start, row) in &[(0, 0), (16, 1), (40, 2), (56, 3)] {
+            assert_eq!(pitched[start .. start + 12], packed[row * 12 .. (row + 1) * 12]);
+        }
+        // the padding is left untouched
+        assert!(pitched[12 .. 16].iter().chain(&pitched[28 .. 40]).chain(&pitched[52 .. 56]).all(|&b| b == 0));
+
+        let mut unpacked = vec![0; 48];
+        pack_rows(&mut unpacked, &pitched, 12, 2, 2, &footprint);
+        assert_eq!(unpacked, packed);
+    }
+}
@@ -23,6 +23,12 @@ bitflags!(
         const DEPTH = 0x2;
         /// Stencil aspect.
         const STENCIL = 0x4;
+        /// First plane of a multi-planar format.
+        const PLANE_0 = 0x10;
+        /// Second plane of a multi-planar format.
+        const PLANE_1 = 0x20;
+        /// Third plane of a multi-planar format.
+        const PLANE_2 = 0x40;
     }
 );
 
@@ -37,6 +43,7 @@ pub struct FormatDesc {
     /// * The total number can be larger than the sum of individual format bits
     ///   (`color`, `alpha`, `depth` and `stencil`) for packed formats.
     /// * For compressed formats, this denotes the number of bits per block.
+    /// * For multi-planar formats, this denotes the average number of bits per texel.
     pub bits: u16,
     /// Dimensions (width, height) of the texel blocks.
     pub dim: (u8, u8),
@@ -317,6 +324,12 @@ surface_types! {
     ASTC_10x10          { 128, COLOR, (10, 10) },
     ASTC_12x10          { 128, COLOR, (12, 10) },
     ASTC_12x12          { 128, COLOR, (12, 12) },
+    G8_B8R8_2PLANE_420  {  12, COLOR | PLANE_0 | PLANE_1, (1, 1), color: 24 },
+    G8_B8R8_2PLANE_422  {  16, COLOR | PLANE_0 | PLANE_1, (1, 1), color: 24 },
+    G10X6_B10X6R10X6_2PLANE_420 { 24, COLOR | PLANE_0 | PLANE_1, (1, 1), color: 30 },
+    G8_B8_R8_3PLANE_420 {  12, COLOR | PLANE_0 | PLANE_1 | PLANE_2, (1, 1), color: 24 },
+    G8_B8_R8_3PLANE_422 {  16, COLOR | PLANE_0 | PLANE_1 | PLANE_2, (1, 1), color: 24 },
+    G8_B8_R8_3PLANE_444 {  24, COLOR | PLANE_0 | PLANE_1 | PLANE_2, (1, 1), color: 24 },
 }
 
 /// Generic run-time base format.
@@ -576,6 +589,30 @@ formats! {
     Astc12x10Srgb = (ASTC_12x10, Srgb),
     Astc12x12Unorm = (ASTC_12x12, Unorm),
     Astc12x12Srgb = (ASTC_12x12, Srgb),
+    // Multi-planar Y'CbCr formats, these don't follow the core Vulkan format order
+    // and have to be mapped explicitly.
+    // NV12: luma plane followed by an interleaved, 2x2 subsampled CbCr plane.
+    Nv12Unorm = (G8_B8R8_2PLANE_420, Unorm),
+    // NV16: luma plane followed by an interleaved, 2x1 subsampled CbCr plane.
+    Nv16Unorm = (G8_B8R8_2PLANE_422, Unorm),
+    // P010: 10-bit variant of NV12, stored in the high bits of 16-bit words.
+    P010Unorm = (G10X6_B10X6R10X6_2PLANE_420, Unorm),
+    // Three separate Y, Cb and Cr planes.
+    Yuv420Unorm = (G8_B8_R8_3PLANE_420, Unorm),
+    Yuv422Unorm = (G8_B8_R8_3PLANE_422, Unorm),
+    Yuv444Unorm = (G8_B8_R8_3PLANE_444, Unorm),
+}
+
+/// Description of a single plane of a multi-planar format.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct PlaneDesc {
+    /// Aspect selecting the plane in subresource ranges and copies.
+    pub aspect: Aspects,
+    /// Single-plane format compatible with the plane.
+    pub format: Format,
+    /// Horizontal and vertical subsampling factors of the plane.
+    pub subsampling: (u8, u8),
 }
 
 impl Format {
@@ -606,6 +643,52 @@ impl Format {
     pub fn is_stencil(self) -> bool {
         self.surface_desc().aspects.contains(Aspects::STENCIL)
     }
+
+    /// Returns if the format consists of multiple planes.
+    pub fn is_multi_planar(self) -> bool {
+        !self.planes().is_empty()
+    }
+
+    /// Returns the planes of a multi-planar format, or an empty slice otherwise.
+    pub fn planes(self) -> &'static [PlaneDesc] {
+        const NV12: &[PlaneDesc] = &[
+            PlaneDesc { aspect: Aspects::PLANE_0, format: Format::R8Unorm, subsampling: (1, 1) },
+            PlaneDesc { aspect: Aspects::PLANE_1, format: Format::Rg8Unorm, subsampling: (2, 2) },
+        ];
+        const NV16: &[PlaneDesc] = &[
+            PlaneDesc { aspect: Aspects::PLANE_0, format: Format::R8Unorm, subsampling: (1, 1) },
+            PlaneDesc { aspect: Aspects::PLANE_1, format: Format::Rg8Unorm, subsampling: (2, 1) },
+        ];
+        const P010: &[PlaneDesc] = &[
+            PlaneDesc { aspect: Aspects::PLANE_0, format: Format::R16Unorm, subsampling: (1, 1) },
+            PlaneDesc { aspect: Aspects::PLANE_1, format: Format::Rg16Unorm, subsampling: (2, 2) },
+        ];
+        const YUV420: &[PlaneDesc] = &[
+            PlaneDesc { aspect: Aspects::PLANE_0, format: Format::R8Unorm, subsampling: (1, 1) },
+            PlaneDesc { aspect: Aspects::PLANE_1, format: Format::R8Unorm, subsampling: (2, 2) },
+            PlaneDesc { aspect: Aspects::PLANE_2, format: Format::R8Unorm, subsampling: (2, 2) },
+        ];
+        const YUV422: &[PlaneDesc] = &[
+            PlaneDesc { aspect: Aspects::PLANE_0, format: Format::R8Unorm, subsampling: (1, 1) },
+            PlaneDesc { aspect: Aspects::PLANE_1, format: Format::R8Unorm, subsampling: (2, 1) },
+            PlaneDesc { aspect: Aspects::PLANE_2, format: Format::R8Unorm, subsampling: (2, 1) },
+        ];
+        const YUV444: &[PlaneDesc] = &[
+            PlaneDesc { aspect: Aspects::PLANE_0, format: Format::R8Unorm, subsampling: (1, 1) },
+            PlaneDesc { aspect: Aspects::PLANE_1, format: Format::R8Unorm, subsampling: (1, 1) },
+            PlaneDesc { aspect: Aspects::PLANE_2, format: Format::R8Unorm, subsampling: (1, 1) },
+        ];
+
+        match self {
+            Format::Nv12Unorm => NV12,
+            Format::Nv16Unorm => NV16,
+            Format::P010Unorm => P010,
+            Format::Yuv420Unorm => YUV420,
+            Format::Yuv422Unorm => YUV422,
+            Format::Yuv444Unorm => YUV444,
+            _ => &[],
+        }
+    }
 }
 
 // Common vertex attribute formats
@@ -481,6 +481,79 @@ impl Into<[f32; 4]> for PackedColor {
     }
 }
 
+/// Color model of a Y'CbCr conversion.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum YcbcrModel {
+    /// The input is already RGB, only the range expansion is applied.
+    RgbIdentity,
+    /// Range expansion only, the components stay in Y'CbCr.
+    YcbcrIdentity,
+    /// ITU-R BT.709 color conversion.
+    Bt709,
+    /// ITU-R BT.601 color conversion.
+    Bt601,
+    /// ITU-R BT.2020 color conversion.
+    Bt2020,
+}
+
+/// Numerical range of the encoded Y'CbCr values.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum YcbcrRange {
+    /// The full range of the encoding is used.
+    Full,
+    /// Headroom and footroom are reserved, e.g. [16, 235] for 8-bit luma.
+    Narrow,
+}
+
+/// Location of downsampled chroma samples relative to the luma samples.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ChromaLocation {
+    /// Chroma samples are aligned with the even luma samples.
+    CositedEven,
+    /// Chroma samples are located between the luma samples.
+    Midpoint,
+}
+
+/// Conversion of sampled Y'CbCr data into RGB, applied by the sampler.
+///
+/// Views of the color aspect of multi-planar formats need to be created with
+/// `Device::create_image_view_with_conversion` and an identical conversion.
+/// Requires `Features::SAMPLER_YCBCR_CONVERSION`.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct YcbcrConversion {
+    /// Format of the sampled image.
+    pub format: format::Format,
+    /// Color model to convert from.
+    pub model: YcbcrModel,
+    /// Range of the encoded values.
+    pub range: YcbcrRange,
+    /// Swizzle applied before the conversion.
+    pub swizzle: format::Swizzle,
+    /// Location of the chroma samples in x and y direction.
+    pub chroma_location: (ChromaLocation, ChromaLocation),
+    /// Filter used for reconstructing the chroma samples.
+    pub chroma_filter: Filter,
+}
+
+impl YcbcrConversion {
+    /// Create a conversion for `format` with the given color model,
+    /// using narrow range and midpoint chroma samples.
+    pub fn new(format: format::Format, model: YcbcrModel) -> Self {
+        YcbcrConversion {
+            format,
+            model,
+            range: YcbcrRange::Narrow,
+            swizzle: format::Swizzle::NO,
+            chroma_location: (ChromaLocation::Midpoint, ChromaLocation::Midpoint),
+            chroma_filter: Filter::Linear,
+        }
+    }
+}
+
 /// Specifies how to sample from an image.
 // TODO: document the details of sampling.
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
@@ -507,6 +580,8 @@ pub struct SamplerInfo {
     pub border: PackedColor,
     /// Anisotropic filtering.
     pub anisotropic: Anisotropic,
+    /// Y'CbCr conversion applied to the sampled values.
+    pub ycbcr_conversion: Option<YcbcrConversion>,
 }
 
 impl SamplerInfo {
@@ -523,6 +598,7 @@ impl SamplerInfo {
             comparison: None,
             border: PackedColor(0),
             anisotropic: Anisotropic::Off,
+            ycbcr_conversion: None,
         }
     }
 }
@@ -658,6 +734,17 @@ bitflags!(
     }
 );
 
+impl Access {
+    /// Check if any of the accesses writes to the image.
+    pub fn is_write(&self) -> bool {
+        self.intersects(
+            Access::SHADER_WRITE | Access::COLOR_ATTACHMENT_WRITE |
+            Access::DEPTH_STENCIL_ATTACHMENT_WRITE | Access::TRANSFER_WRITE |
+            Access::HOST_WRITE | Access::MEMORY_WRITE
+        )
+    }
+}
+
 /// Image state, combining access methods and the image's layout.
 pub type State = (Access, Layout);
 
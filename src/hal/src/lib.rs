@@ -24,7 +24,7 @@ use std::hash::Hash;
 //TODO: reconsider what is publicly exported
 
 pub use self::adapter::{
-    Adapter, AdapterInfo, MemoryProperties, MemoryType, MemoryTypeId,
+    Adapter, AdapterInfo, DeviceType, MemoryProperties, MemoryType, MemoryTypeId,
     PhysicalDevice, QueuePriority,
 };
 pub use self::device::Device;
@@ -57,6 +57,8 @@ pub mod window;
 
 #[doc(hidden)]
 pub mod backend;
+#[cfg(test)]
+mod mock;
 
 /// Draw vertex count.
 pub type VertexCount = u32;
@@ -218,6 +220,8 @@ bitflags! {
         const TRIANGLE_FAN = 0x1000_0000_0000_0000;
         /// Support separate stencil reference values for front and back sides.
         const SEPARATE_STENCIL_REF_VALUES = 0x2000_0000_0000_0000;
+        /// Support sampler Y'CbCr conversion of multi-planar formats.
+        const SAMPLER_YCBCR_CONVERSION = 0x4000_0000_0000_0000;
         /// Support manually specified vertex attribute rates (divisors).
         const INSTANCE_RATE = 0x8000_0000_0000_0000;
     }
@@ -268,6 +272,9 @@ pub struct Limits {
     pub framebuffer_stencil_samples_count: image::NumSamples,
     /// Size and alignment in bytes that bounds concurrent access to host-mapped device memory.
     pub non_coherent_atom_size: usize,
+    /// Granularity in bytes at which linear and optimal-tiling resources bound
+    /// to the same memory object must not alias.
+    pub buffer_image_granularity: buffer::Offset,
 }
 
 /// Describes the type of geometric primitives,
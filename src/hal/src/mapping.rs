@@ -2,7 +2,8 @@
 
 //! Memory mapping
 use std::error::Error as StdError;
-use std::fmt;
+use std::{fmt, mem, ptr, slice};
+use std::marker::PhantomData;
 use std::ops::{self, Range};
 use Backend;
 
@@ -35,6 +36,44 @@ impl StdError for Error {
     }
 }
 
+/// Coherency of mapped memory, as reported by `Device::memory_coherency`.
+///
+/// Host writes to non-coherent memory need to be flushed, and device writes
+/// invalidated, in ranges aligned to `Limits::non_coherent_atom_size`.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Coherency {
+    /// The memory is `Properties::COHERENT`, no flushes or invalidations needed.
+    Coherent,
+    /// The memory requires explicit flushes and invalidations.
+    NonCoherent {
+        /// Alignment of the flushed and invalidated ranges.
+        atom_size: u64,
+        /// Size of the memory object, which bounds the aligned ranges.
+        memory_size: u64,
+    },
+}
+
+impl Coherency {
+    /// Returns the range that needs to be mapped and flushed or invalidated
+    /// in order to access `range`, or `None` if the memory is coherent.
+    pub fn align(&self, range: Range<u64>) -> Option<Range<u64>> {
+        match *self {
+            Coherency::Coherent => None,
+            Coherency::NonCoherent { atom_size, memory_size } => {
+                let atom = atom_size.max(1);
+                let start = range.start / atom * atom;
+                let end = ((range.end + atom - 1) / atom * atom).min(memory_size);
+                Some(start .. end)
+            }
+        }
+    }
+
+    /// Returns the range that needs to be mapped in order to access `range`.
+    pub fn mapped_range(&self, range: Range<u64>) -> Range<u64> {
+        self.align(range.clone()).unwrap_or(range)
+    }
+}
+
 /// Mapping reader
 pub struct Reader<'a, B: Backend, T: 'a> {
     pub(crate) slice: &'a [T],
@@ -59,7 +98,8 @@ impl<'a, B: Backend, T: 'a> ops::Deref for Reader<'a, B, T> {
 pub struct Writer<'a, B: Backend, T: 'a> {
     pub(crate) slice: &'a mut [T],
     pub(crate) memory: &'a B::Memory,
-    pub(crate) range: Range<u64>,
+    /// Range to flush on release, `None` for coherent memory.
+    pub(crate) flush: Option<Range<u64>>,
     pub(crate) released: bool,
 }
 
@@ -77,3 +117,110 @@ impl<'a, B: Backend, T: 'a> ops::Deref for Writer<'a, B, T> {
 impl<'a, B: Backend, T: 'a> ops::DerefMut for Writer<'a, B, T> {
     fn deref_mut(&mut self) -> &mut [T] { self.slice }
 }
+
+/// Persistent mapping of a memory range, which stays valid across frames
+/// until released with `Device::release_persistent_mapping`.
+///
+/// Writes need to be made visible to the device with
+/// `Device::flush_persistent_mapping` and device writes to the host with
+/// `Device::invalidate_persistent_mapping`, which are no-ops on coherent memory.
+#[derive(Debug)]
+pub struct Persistent<B: Backend> {
+    pub(crate) ptr: *mut u8,
+    /// Range of the memory covered by `ptr`, possibly wider than requested.
+    pub(crate) mapped: Range<u64>,
+    pub(crate) range: Range<u64>,
+    pub(crate) coherency: Coherency,
+    pub(crate) released: bool,
+    pub(crate) phantom: PhantomData<B>,
+}
+
+unsafe impl<B: Backend> Send for Persistent<B> {}
+unsafe impl<B: Backend> Sync for Persistent<B> {}
+
+impl<B: Backend> Persistent<B> {
+    /// Range of the memory accessible through this mapping.
+    pub fn range(&self) -> Range<u64> {
+        self.range.clone()
+    }
+
+    /// Range of the memory which needs to be flushed or invalidated to make
+    /// the accessible `range` coherent, `None` for coherent memory.
+    pub(crate) fn sync_range(&self, range: Range<u64>) -> Option<Range<u64>> {
+        self.coherency.align(range).map(|r| {
+            r.start.max(self.mapped.start) .. r.end.min(self.mapped.end)
+        })
+    }
+
+    fn offset_of(&self, range: &Range<u64>) -> Result<usize, Error> {
+        if range.start < self.range.start || range.end > self.range.end || range.start > range.end {
+            Err(Error::OutOfBounds)
+        } else {
+            Ok((range.start - self.mapped.start) as usize)
+        }
+    }
+
+    /// Copy `data` into the memory at `offset`, returning the written range.
+    ///
+    /// The range needs to be flushed before the device reads it.
+    pub fn write<T: Copy>(&mut self, offset: u64, data: &[T]) -> Result<Range<u64>, Error> {
+        let range = offset .. offset + (data.len() * mem::size_of::<T>()) as u64;
+        let start = self.offset_of(&range)?;
+        unsafe {
+            ptr::copy_nonoverlapping(
+                data.as_ptr() as *const u8,
+                self.ptr.offset(start as isize),
+                (range.end - range.start) as usize,
+            );
+        }
+        Ok(range)
+    }
+
+    /// Copy the memory at `offset` into `data`.
+    ///
+    /// The range needs to be invalidated after the device writes to it.
+    pub fn read<T: Copy>(&self, offset: u64, data: &mut [T]) -> Result<(), Error> {
+        let range = offset .. offset + (data.len() * mem::size_of::<T>()) as u64;
+        let start = self.offset_of(&range)?;
+        unsafe {
+            ptr::copy_nonoverlapping(
+                self.ptr.offset(start as isize) as *const u8,
+                data.as_mut_ptr() as *mut u8,
+                (range.end - range.start) as usize,
+            );
+        }
+        Ok(())
+    }
+
+    /// Access the given memory range as a mutable slice.
+    ///
+    /// # Safety
+    ///
+    /// The range must be suitably aligned for `T` and must not be accessed
+    /// by the device or aliased on the host while the slice is alive.
+    pub unsafe fn slice_mut<T: Copy>(&mut self, range: Range<u64>) -> Result<&mut [T], Error> {
+        let start = self.offset_of(&range)?;
+        let count = (range.end - range.start) as usize / mem::size_of::<T>();
+        Ok(slice::from_raw_parts_mut(self.ptr.offset(start as isize) as *mut T, count))
+    }
+}
+
+impl<B: Backend> Drop for Persistent<B> {
+    fn drop(&mut self) {
+        assert!(self.released, "a persistent mapping was not released");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Coherency;
+
+    #[test]
+    fn align_non_coherent() {
+        let coherency = Coherency::NonCoherent { atom_size: 64, memory_size: 1000 };
+        assert_eq!(coherency.align(10 .. 20), Some(0 .. 64));
+        assert_eq!(coherency.align(64 .. 128), Some(64 .. 128));
+        assert_eq!(coherency.align(900 .. 990), Some(896 .. 1000));
+        assert_eq!(Coherency::Coherent.align(10 .. 20), None);
+    }
+}
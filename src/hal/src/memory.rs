@@ -1,8 +1,12 @@
 //! Types to describe the properties of memory allocated for gfx resources.
 
-use std::mem;
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::{mem, ptr};
 use std::ops::Range;
-use {buffer, image};
+use {buffer, format, image};
+use pso::PipelineStage;
+use queue::QueueFamilyId;
 use Backend;
 
 /// A trait for plain-old-data types.
@@ -79,7 +83,7 @@ bitflags!(
 /// A [memory barrier](https://www.khronos.org/registry/vulkan/specs/1.0/html/vkspec.html#synchronization-memory-barriers)
 /// type for either buffers or images.
 #[allow(missing_docs)] 
-#[derive(Clone, Debug)]
+#[derive(Debug)]
 pub enum Barrier<'a, B: Backend> {
     /// Applies the given access flags to all buffers in the range.
     AllBuffers(Range<buffer::Access>),
@@ -91,6 +95,12 @@ pub enum Barrier<'a, B: Backend> {
         states: Range<buffer::State>,
         /// The buffer the barrier controls.
         target: &'a B::Buffer,
+        /// Source and destination queue families of an ownership transfer.
+        ///
+        /// Resources created with exclusive sharing need to be released by a
+        /// barrier on the source queue and acquired by a matching barrier on
+        /// the destination queue before being used by the latter.
+        families: Option<Range<QueueFamilyId>>,
     },
     /// A memory barrier that defines access to (a subset of) an image.
     Image {
@@ -100,9 +110,445 @@ pub enum Barrier<'a, B: Backend> {
         target: &'a B::Image,
         /// A `SubresourceRange` that defines which section of an image the barrier applies to.
         range: image::SubresourceRange,
+        /// Source and destination queue families of an ownership transfer.
+        families: Option<Range<QueueFamilyId>>,
+    },
+    /// Switches between images bound to overlapping ranges of the same memory,
+    /// see `AliasingTracker`.
+    ///
+    /// The content of `after` is undefined following the barrier, so it has to
+    /// be transitioned from `Layout::Undefined` before use.
+    Aliasing {
+        /// The image previously using the memory, if known.
+        before: Option<&'a B::Image>,
+        /// The image using the memory after the barrier.
+        after: &'a B::Image,
+    },
+}
+
+impl<'a, B: Backend> Clone for Barrier<'a, B> {
+    fn clone(&self) -> Self {
+        match *self {
+            Barrier::AllBuffers(ref states) => Barrier::AllBuffers(states.clone()),
+            Barrier::AllImages(ref states) => Barrier::AllImages(states.clone()),
+            Barrier::Buffer { ref states, target, ref families } => Barrier::Buffer {
+                states: states.clone(),
+                target,
+                families: families.clone(),
+            },
+            Barrier::Image { ref states, target, ref range, ref families } => Barrier::Image {
+                states: states.clone(),
+                target,
+                range: range.clone(),
+                families: families.clone(),
+            },
+            Barrier::Aliasing { before, after } => Barrier::Aliasing { before, after },
+        }
+    }
+}
+
+/// Parameters of a single `pipeline_barrier` command.
+#[derive(Debug)]
+pub struct PipelineBarrier<'a, B: Backend> {
+    /// Source and destination pipeline stages.
+    pub stages: Range<PipelineStage>,
+    /// Memory barriers to apply.
+    pub barriers: Vec<Barrier<'a, B>>,
+}
+
+/// Access of a resource by a command.
+#[derive(Debug)]
+pub enum ResourceUsage<'a, B: Backend> {
+    /// A buffer accessed from the given pipeline stages.
+    Buffer {
+        /// The buffer being accessed.
+        target: &'a B::Buffer,
+        /// The access flags of the usage.
+        state: buffer::State,
+        /// Pipeline stages accessing the buffer.
+        stages: PipelineStage,
+    },
+    /// A subset of an image accessed from the given pipeline stages.
+    Image {
+        /// The image being accessed.
+        target: &'a B::Image,
+        /// The subresources being accessed.
+        range: image::SubresourceRange,
+        /// The access flags and layout of the usage.
+        state: image::State,
+        /// Pipeline stages accessing the image.
+        stages: PipelineStage,
     },
 }
 
+impl<'a, B: Backend> Clone for ResourceUsage<'a, B> {
+    fn clone(&self) -> Self {
+        match *self {
+            ResourceUsage::Buffer { target, state, stages } => ResourceUsage::Buffer {
+                target,
+                state,
+                stages,
+            },
+            ResourceUsage::Image { target, ref range, state, stages } => ResourceUsage::Image {
+                target,
+                range: range.clone(),
+                state,
+                stages,
+            },
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Tracked<S, A> {
+    /// State of the current usages, consecutive reads are merged.
+    state: S,
+    /// Pipeline stages of the current usages.
+    stages: PipelineStage,
+    /// State and stages of the last write.
+    write: Option<(S, PipelineStage)>,
+    /// Accesses and stages the last write has been made visible to.
+    visible: (A, PipelineStage),
+}
+
+impl<S: Copy, A: Copy> Tracked<S, A> {
+    fn new(state: S, stages: PipelineStage, write: bool, empty: A) -> Self {
+        Tracked {
+            state,
+            stages,
+            write: if write { Some((state, stages)) } else { None },
+            visible: (empty, PipelineStage::empty()),
+        }
+    }
+}
+
+/// Single aspects of `aspects`, as tracked separately.
+fn single_aspects(aspects: format::Aspects) -> Vec<format::Aspects> {
+    (0 .. 8)
+        .map(|bit| format::Aspects::from_bits_truncate(1 << bit))
+        .filter(|&aspect| !aspect.is_empty() && aspects.contains(aspect))
+        .collect()
+}
+
+/// Merge single subresource transitions into blocks of adjacent layers and levels.
+fn merge_subresources(
+    transitions: Vec<(image::Level, image::Layer, image::State)>,
+) -> Vec<(Range<image::Level>, Range<image::Layer>, image::State)> {
+    let mut runs: Vec<(image::Level, Range<image::Layer>, image::State)> = Vec::new();
+    for (level, layer, state) in transitions {
+        if let Some(run) = runs.last_mut() {
+            if run.0 == level && run.1.end == layer && run.2 == state {
+                run.1.end += 1;
+                continue;
+            }
+        }
+        runs.push((level, layer .. layer + 1, state));
+    }
+
+    let mut blocks: Vec<(Range<image::Level>, Range<image::Layer>, image::State)> = Vec::new();
+    for (level, layers, state) in runs {
+        if let Some(block) = blocks
+            .iter_mut()
+            .find(|block| block.0.end == level && block.1 == layers && block.2 == state)
+        {
+            block.0.end += 1;
+            continue;
+        }
+        blocks.push((level .. level + 1, layers, state));
+    }
+    blocks
+}
+
+/// Infers the barriers required between commands from the resources they access.
+///
+/// Usages are recorded per command in submission order and `require` returns the
+/// barrier to insert before the command, if any. A barrier is emitted when an image
+/// changes layout or when any of the two consecutive usages writes to the resource.
+/// Consecutive reads are merged, so a following write waits for all of them, and a
+/// read from stages or with accesses the last write hasn't been made visible to yet
+/// waits for that write. All barriers needed by a command are combined into a single
+/// `PipelineBarrier`, with image barriers merged over adjacent levels and layers.
+/// Image subresources are tracked per aspect.
+///
+/// Resources are identified by reference and each resource should only appear once
+/// per command. Resources which weren't given an initial state are assumed to
+/// already be in the state of their first usage.
+///
+/// # Example
+///
+/// ```rust
+/// # extern crate gfx_backend_empty as empty;
+/// # extern crate gfx_hal as hal;
+/// # fn main() {
+/// use hal::{buffer, format, image as i};
+/// use hal::memory::{BarrierTracker, ResourceUsage};
+/// use hal::pso::PipelineStage;
+///
+/// let (staging, texture) = ((), ());
+/// let range = i::SubresourceRange {
+///     aspects: format::Aspects::COLOR,
+///     levels: 0 .. 1,
+///     layers: 0 .. 1,
+/// };
+/// let sampled = ResourceUsage::Image {
+///     target: &texture,
+///     range: range.clone(),
+///     state: (i::Access::SHADER_READ, i::Layout::ShaderReadOnlyOptimal),
+///     stages: PipelineStage::FRAGMENT_SHADER,
+/// };
+///
+/// let mut tracker = BarrierTracker::<empty::Backend>::new();
+/// tracker.set_image_state(&texture, range.clone(), (i::Access::empty(), i::Layout::Undefined));
+///
+/// // Upload the texture contents, only the texture needs a layout transition.
+/// let upload = tracker.require(vec![
+///     ResourceUsage::Buffer {
+///         target: &staging,
+///         state: buffer::Access::TRANSFER_READ,
+///         stages: PipelineStage::TRANSFER,
+///     },
+///     ResourceUsage::Image {
+///         target: &texture,
+///         range: range.clone(),
+///         state: (i::Access::TRANSFER_WRITE, i::Layout::TransferDstOptimal),
+///         stages: PipelineStage::TRANSFER,
+///     },
+/// ]).unwrap();
+/// assert_eq!(upload.barriers.len(), 1);
+///
+/// // Sample from the texture, waiting for the transfer to finish.
+/// let sample = tracker.require(vec![sampled.clone()]).unwrap();
+/// assert_eq!(sample.stages, PipelineStage::TRANSFER .. PipelineStage::FRAGMENT_SHADER);
+///
+/// // Sampling again doesn't require any synchronization.
+/// assert!(tracker.require(vec![sampled]).is_none());
+///
+/// // Reading from the vertex shader still needs to wait for the transfer.
+/// let vertex = tracker.require(vec![ResourceUsage::Image {
+///     target: &texture,
+///     range: range.clone(),
+///     state: (i::Access::SHADER_READ, i::Layout::ShaderReadOnlyOptimal),
+///     stages: PipelineStage::VERTEX_SHADER,
+/// }]).unwrap();
+/// assert_eq!(vertex.stages, PipelineStage::TRANSFER .. PipelineStage::VERTEX_SHADER);
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct BarrierTracker<'a, B: Backend> {
+    buffers: Vec<(&'a B::Buffer, Tracked<buffer::State, buffer::Access>)>,
+    images: Vec<(&'a B::Image, ImageTracking)>,
+}
+
+/// Subresources are tracked per aspect, depth and stencil can be used separately.
+type ImageKey = (format::Aspects, image::Level, image::Layer);
+type ImageTracking = HashMap<ImageKey, Tracked<image::State, image::Access>>;
+
+impl<'a, B: Backend> BarrierTracker<'a, B> {
+    /// Create an empty tracker.
+    pub fn new() -> Self {
+        BarrierTracker {
+            buffers: Vec::new(),
+            images: Vec::new(),
+        }
+    }
+
+    /// Set the state of a buffer before the first tracked command.
+    pub fn set_buffer_state(&mut self, target: &'a B::Buffer, state: buffer::State) {
+        let tracked = Tracked::new(
+            state, PipelineStage::TOP_OF_PIPE, state.is_write(), buffer::Access::empty());
+        match self.buffers.iter().position(|&(buffer, _)| ptr::eq(buffer, target)) {
+            Some(index) => self.buffers[index].1 = tracked,
+            None => self.buffers.push((target, tracked)),
+        }
+    }
+
+    /// Set the state of image subresources before the first tracked command.
+    pub fn set_image_state(
+        &mut self, target: &'a B::Image, range: image::SubresourceRange, state: image::State
+    ) {
+        let tracked = Tracked::new(
+            state, PipelineStage::TOP_OF_PIPE, state.0.is_write(), image::Access::empty());
+        let subresources = self.image_subresources(target);
+        for aspect in single_aspects(range.aspects) {
+            for level in range.levels.clone() {
+                for layer in range.layers.clone() {
+                    subresources.insert((aspect, level, layer), tracked);
+                }
+            }
+        }
+    }
+
+    fn image_subresources(
+        &mut self, target: &'a B::Image
+    ) -> &mut ImageTracking {
+        let index = match self.images.iter().position(|&(image, _)| ptr::eq(image, target)) {
+            Some(index) => index,
+            None => {
+                self.images.push((target, HashMap::new()));
+                self.images.len() - 1
+            }
+        };
+        &mut self.images[index].1
+    }
+
+    /// Record the resource usages of the next command, returning the barrier
+    /// which needs to be recorded before it.
+    pub fn require<I>(&mut self, usages: I) -> Option<PipelineBarrier<'a, B>>
+    where
+        I: IntoIterator<Item = ResourceUsage<'a, B>>,
+    {
+        let mut stages = PipelineStage::empty() .. PipelineStage::empty();
+        let mut barriers = Vec::new();
+
+        for usage in usages {
+            match usage {
+                ResourceUsage::Buffer { target, state, stages: usage_stages } => {
+                    let index = self.buffers.iter().position(|&(buffer, _)| ptr::eq(buffer, target));
+                    let tracked = match index {
+                        Some(index) => &mut self.buffers[index].1,
+                        None => {
+                            let tracked = Tracked::new(
+                                state, usage_stages, state.is_write(), buffer::Access::empty());
+                            self.buffers.push((target, tracked));
+                            continue;
+                        }
+                    };
+                    if (tracked.state | state).is_write() {
+                        stages.start |= tracked.stages;
+                        stages.end |= usage_stages;
+                        barriers.push(Barrier::Buffer {
+                            states: tracked.state .. state,
+                            target,
+                            families: None,
+                        });
+                        *tracked = Tracked {
+                            state,
+                            stages: usage_stages,
+                            write: if state.is_write() {
+                                Some((state, usage_stages))
+                            } else {
+                                tracked.write
+                            },
+                            visible: if state.is_write() {
+                                (buffer::Access::empty(), PipelineStage::empty())
+                            } else {
+                                (state, usage_stages)
+                            },
+                        };
+                        continue;
+                    }
+                    // the last write needs to be made visible to new readers
+                    if let Some((write_state, write_stages)) = tracked.write {
+                        let (access, visible_stages) = tracked.visible;
+                        if !access.contains(state) || !visible_stages.contains(usage_stages) {
+                            stages.start |= write_stages;
+                            stages.end |= usage_stages;
+                            barriers.push(Barrier::Buffer {
+                                states: write_state .. state,
+                                target,
+                                families: None,
+                            });
+                            tracked.visible = (access | state, visible_stages | usage_stages);
+                        }
+                    }
+                    tracked.state |= state;
+                    tracked.stages |= usage_stages;
+                }
+                ResourceUsage::Image { target, range, state, stages: usage_stages } => {
+                    let mut transitions = Vec::new();
+                    {
+                        let subresources = self.image_subresources(target);
+                        for aspect in single_aspects(range.aspects) {
+                            for level in range.levels.clone() {
+                                for layer in range.layers.clone() {
+                                    let tracked = match subresources.entry((aspect, level, layer)) {
+                                        Entry::Occupied(entry) => entry.into_mut(),
+                                        Entry::Vacant(entry) => {
+                                            entry.insert(Tracked::new(
+                                                state, usage_stages, state.0.is_write(),
+                                                image::Access::empty(),
+                                            ));
+                                            continue;
+                                        }
+                                    };
+                                    if tracked.state.1 != state.1 || (tracked.state.0 | state.0).is_write() {
+                                        stages.start |= tracked.stages;
+                                        stages.end |= usage_stages;
+                                        transitions.push((aspect, level, layer, tracked.state));
+                                        *tracked = Tracked {
+                                            state,
+                                            stages: usage_stages,
+                                            write: if state.0.is_write() {
+                                                Some((state, usage_stages))
+                                            } else {
+                                                tracked.write
+                                            },
+                                            visible: if state.0.is_write() {
+                                                (image::Access::empty(), PipelineStage::empty())
+                                            } else {
+                                                (state.0, usage_stages)
+                                            },
+                                        };
+                                        continue;
+                                    }
+                                    // the last write needs to be made visible to new readers
+                                    if let Some((write_state, write_stages)) = tracked.write {
+                                        let (access, visible_stages) = tracked.visible;
+                                        if !access.contains(state.0) || !visible_stages.contains(usage_stages) {
+                                            stages.start |= write_stages;
+                                            stages.end |= usage_stages;
+                                            transitions.push((aspect, level, layer, (write_state.0, state.1)));
+                                            tracked.visible = (access | state.0, visible_stages | usage_stages);
+                                        }
+                                    }
+                                    tracked.state.0 |= state.0;
+                                    tracked.stages |= usage_stages;
+                                }
+                            }
+                        }
+                    }
+                    let mut blocks: Vec<(format::Aspects, Range<image::Level>, Range<image::Layer>, image::State)> =
+                        Vec::new();
+                    for aspect in single_aspects(range.aspects) {
+                        let aspect_transitions = transitions
+                            .iter()
+                            .filter(|&&(a, _, _, _)| a == aspect)
+                            .map(|&(_, level, layer, old_state)| (level, layer, old_state))
+                            .collect();
+                        for (levels, layers, old_state) in merge_subresources(aspect_transitions) {
+                            // combine the aspects transitioned alike
+                            match blocks.iter_mut().find(|block| {
+                                block.1 == levels && block.2 == layers && block.3 == old_state
+                            }) {
+                                Some(block) => block.0 |= aspect,
+                                None => blocks.push((aspect, levels, layers, old_state)),
+                            }
+                        }
+                    }
+                    for (aspects, levels, layers, old_state) in blocks {
+                        barriers.push(Barrier::Image {
+                            states: old_state .. state,
+                            target,
+                            range: image::SubresourceRange {
+                                aspects,
+                                levels,
+                                layers,
+                            },
+                            families: None,
+                        });
+                    }
+                }
+            }
+        }
+
+        if barriers.is_empty() {
+            None
+        } else {
+            Some(PipelineBarrier { stages, barriers })
+        }
+    }
+}
+
 /// Memory requirements for a certain resource (buffer/image).
 #[derive(Clone, Copy, Debug)]
 pub struct Requirements {
@@ -112,4 +558,379 @@ pub struct Requirements {
     pub alignment: u64,
     /// Supported memory types.
     pub type_mask: u64,
+    /// The resource would benefit from a dedicated allocation,
+    /// see `Device::allocate_dedicated_memory`.
+    pub prefers_dedicated: bool,
+    /// The resource has to be bound to a dedicated allocation.
+    pub requires_dedicated: bool,
+}
+
+/// Resource a memory allocation is dedicated to.
+#[derive(Debug)]
+pub enum Dedicated<'a, B: Backend> {
+    /// The allocation is dedicated to a buffer.
+    Buffer(&'a B::UnboundBuffer),
+    /// The allocation is dedicated to an image.
+    Image(&'a B::UnboundImage),
+}
+
+impl<'a, B: Backend> Clone for Dedicated<'a, B> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, B: Backend> Copy for Dedicated<'a, B> {}
+
+/// Tracks images bound to overlapping ranges of a single `Memory` object.
+///
+/// Aliasing images allows memory to be reused by resources with disjoint
+/// lifetimes within a frame, e.g. transient render targets. Only one of the
+/// overlapping images holds defined content at a time: switching to another
+/// one requires a `Barrier::Aliasing` and discards the previous content.
+///
+/// The tracker hands out the aliasing barriers when activating an image and
+/// rejects images bound to another memory or bound twice. `validate` checks,
+/// in debug builds, that the images used by a command are active.
+/// The backends don't validate aliasing on their own.
+#[derive(Debug)]
+pub struct AliasingTracker<'a, B: Backend> {
+    memory: &'a B::Memory,
+    images: Vec<(&'a B::Image, Range<u64>, bool)>,
+}
+
+impl<'a, B: Backend> AliasingTracker<'a, B> {
+    /// Create a tracker of the images bound to `memory`.
+    pub fn new(memory: &'a B::Memory) -> Self {
+        AliasingTracker {
+            memory,
+            images: Vec::new(),
+        }
+    }
+
+    /// Register `image`, bound to `range` of `memory`.
+    ///
+    /// Panics if `memory` isn't the tracked memory or if the image is already registered.
+    pub fn bind(&mut self, memory: &'a B::Memory, image: &'a B::Image, range: Range<u64>) {
+        assert!(ptr::eq(memory, self.memory), "Image is bound to another memory");
+        assert!(!self.images.iter().any(|&(other, _, _)| ptr::eq(image, other)),
+            "Image is already bound to the tracked memory");
+        self.images.push((image, range, false));
+    }
+
+    /// Check if `image` currently holds defined content and may be used
+    /// without an aliasing barrier.
+    pub fn is_active(&self, image: &'a B::Image) -> bool {
+        self.images
+            .iter()
+            .any(|&(other, _, active)| active && ptr::eq(image, other))
+    }
+
+    /// Check in debug builds that the tracked images among the `usages`
+    /// of a command are active.
+    pub fn validate<'b, I>(&self, usages: I)
+    where
+        I: IntoIterator<Item = &'b ResourceUsage<'a, B>>,
+        'a: 'b,
+    {
+        if cfg!(debug_assertions) {
+            for usage in usages {
+                if let ResourceUsage::Image { target, .. } = *usage {
+                    let tracked = self.images.iter().any(|&(image, _, _)| ptr::eq(image, target));
+                    assert!(!tracked || self.is_active(target),
+                        "Image is used without being activated");
+                }
+            }
+        }
+    }
+
+    /// Make `image` the user of its memory range, returning the aliasing
+    /// barriers for the overlapping images previously in use.
+    pub fn activate(&mut self, image: &'a B::Image) -> Vec<Barrier<'a, B>> {
+        let range = self.images
+            .iter()
+            .find(|&&(other, _, _)| ptr::eq(image, other))
+            .map(|&(_, ref range, _)| range.clone())
+            .expect("Image is not bound to the tracked memory");
+
+        let mut barriers = Vec::new();
+        for &mut (other, ref other_range, ref mut active) in &mut self.images {
+            if ptr::eq(image, other) {
+                *active = true;
+            } else if *active && other_range.start < range.end && range.start < other_range.end {
+                *active = false;
+                barriers.push(Barrier::Aliasing {
+                    before: Some(other),
+                    after: image,
+                });
+            }
+        }
+        barriers
+    }
+}
+
+/// Budget and current usage of a memory heap, in bytes.
+///
+/// Returned by `Device::memory_budget` for each heap in `MemoryProperties::memory_heaps`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct HeapBudget {
+    /// Estimated amount of memory the process can use from the heap
+    /// before allocations may fail or cause performance degradation.
+    pub budget: u64,
+    /// Estimated amount of memory currently used by the process.
+    pub usage: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ops::Range;
+    use {buffer, format, image};
+    use mock::{Backend, Resource};
+    use pso::PipelineStage;
+    use super::*;
+
+    fn buffer_usage(target: &Resource, state: buffer::Access, stages: PipelineStage) -> ResourceUsage<Backend> {
+        ResourceUsage::Buffer { target, state, stages }
+    }
+
+    fn image_usage(
+        target: &Resource,
+        aspects: format::Aspects,
+        levels: Range<image::Level>,
+        layers: Range<image::Layer>,
+        state: image::State,
+        stages: PipelineStage,
+    ) -> ResourceUsage<Backend> {
+        let range = image::SubresourceRange { aspects, levels, layers };
+        ResourceUsage::Image { target, range, state, stages }
+    }
+
+    fn image_ranges(barrier: &PipelineBarrier<Backend>) -> Vec<image::SubresourceRange> {
+        barrier.barriers
+            .iter()
+            .map(|barrier| match *barrier {
+                Barrier::Image { ref range, .. } => range.clone(),
+                _ => panic!("not an image barrier"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn write_then_read_from_other_stages() {
+        let buffer = Resource::default();
+        let mut tracker = BarrierTracker::<Backend>::new();
+        let write = buffer_usage(&buffer, buffer::Access::SHADER_WRITE, PipelineStage::COMPUTE_SHADER);
+        assert!(tracker.require(vec![write]).is_none());
+
+        let read = |stages| buffer_usage(&buffer, buffer::Access::SHADER_READ, stages);
+        let vertex = tracker.require(vec![read(PipelineStage::VERTEX_SHADER)]).unwrap();
+        assert_eq!(vertex.stages, PipelineStage::COMPUTE_SHADER .. PipelineStage::VERTEX_SHADER);
+        match vertex.barriers[..] {
+            [Barrier::Buffer { ref states, .. }] => {
+                assert_eq!(*states, buffer::Access::SHADER_WRITE .. buffer::Access::SHADER_READ);
+            }
+            _ => panic!("expected a single buffer barrier"),
+        }
+
+        // the write hasn't been made visible to the fragment shader yet
+        let fragment = tracker.require(vec![read(PipelineStage::FRAGMENT_SHADER)]).unwrap();
+        assert_eq!(fragment.stages, PipelineStage::COMPUTE_SHADER .. PipelineStage::FRAGMENT_SHADER);
+        assert!(tracker.require(vec![read(PipelineStage::VERTEX_SHADER)]).is_none());
+        assert!(tracker.require(vec![read(PipelineStage::FRAGMENT_SHADER)]).is_none());
+
+        // a following write waits for all the reads
+        let rewrite = buffer_usage(&buffer, buffer::Access::TRANSFER_WRITE, PipelineStage::TRANSFER);
+        let rewrite = tracker.require(vec![rewrite]).unwrap();
+        assert_eq!(
+            rewrite.stages,
+            PipelineStage::VERTEX_SHADER | PipelineStage::FRAGMENT_SHADER .. PipelineStage::TRANSFER
+        );
+    }
+
+    #[test]
+    fn reads_without_barriers() {
+        let (buffer, image) = (Resource::default(), Resource::default());
+        let mut tracker = BarrierTracker::<Backend>::new();
+        tracker.set_buffer_state(&buffer, buffer::Access::VERTEX_BUFFER_READ);
+        let sampled = (image::Access::SHADER_READ, image::Layout::ShaderReadOnlyOptimal);
+        let reads = |stages| vec![
+            buffer_usage(&buffer, buffer::Access::VERTEX_BUFFER_READ, PipelineStage::VERTEX_INPUT),
+            image_usage(&image, format::Aspects::COLOR, 0 .. 1, 0 .. 1, sampled, stages),
+        ];
+        assert!(tracker.require(reads(PipelineStage::FRAGMENT_SHADER)).is_none());
+        assert!(tracker.require(reads(PipelineStage::FRAGMENT_SHADER)).is_none());
+        // no write has to be made visible to other stages
+        assert!(tracker.require(reads(PipelineStage::VERTEX_SHADER)).is_none());
+    }
+
+    #[test]
+    fn depth_and_stencil_aspects() {
+        let image = Resource::default();
+        let depth_stencil = format::Aspects::DEPTH | format::Aspects::STENCIL;
+        let attachment = (
+            image::Access::DEPTH_STENCIL_ATTACHMENT_READ,
+            image::Layout::DepthStencilReadOnlyOptimal,
+        );
+        let sampled = (image::Access::SHADER_READ, image::Layout::ShaderReadOnlyOptimal);
+        let mut tracker = BarrierTracker::<Backend>::new();
+        tracker.set_image_state(&image, image::SubresourceRange {
+            aspects: depth_stencil,
+            levels: 0 .. 1,
+            layers: 0 .. 1,
+        }, attachment);
+
+        // sampling the depth only transitions the depth aspect
+        let depth = image_usage(
+            &image, format::Aspects::DEPTH, 0 .. 1, 0 .. 1, sampled, PipelineStage::FRAGMENT_SHADER);
+        let barrier = tracker.require(vec![depth]).unwrap();
+        assert_eq!(image_ranges(&barrier).iter().map(|range| range.aspects).collect::<Vec<_>>(),
+            vec![format::Aspects::DEPTH]);
+        let stencil_test = image_usage(
+            &image, format::Aspects::STENCIL, 0 .. 1, 0 .. 1, attachment, PipelineStage::EARLY_FRAGMENT_TESTS);
+        assert!(tracker.require(vec![stencil_test]).is_none());
+
+        // the aspects coming from different states need separate barriers
+        let both = image_usage(
+            &image, depth_stencil, 0 .. 1, 0 .. 1, attachment, PipelineStage::EARLY_FRAGMENT_TESTS);
+        let barrier = tracker.require(vec![both]).unwrap();
+        match barrier.barriers[..] {
+            [Barrier::Image { ref states, ref range, .. }] => {
+                assert_eq!(states.start, sampled);
+                assert_eq!(range.aspects, format::Aspects::DEPTH);
+            }
+            _ => panic!("expected a single image barrier"),
+        }
+
+        // and are combined when transitioned alike
+        let both = image_usage(
+            &image, depth_stencil, 0 .. 1, 0 .. 1, sampled, PipelineStage::FRAGMENT_SHADER);
+        let barrier = tracker.require(vec![both]).unwrap();
+        assert_eq!(image_ranges(&barrier).iter().map(|range| range.aspects).collect::<Vec<_>>(),
+            vec![depth_stencil]);
+    }
+
+    #[test]
+    fn merges_levels_and_layers() {
+        let image = Resource::default();
+        let color = format::Aspects::COLOR;
+        let undefined = (image::Access::empty(), image::Layout::Undefined);
+        let transfer = (image::Access::TRANSFER_WRITE, image::Layout::TransferDstOptimal);
+        let mut tracker = BarrierTracker::<Backend>::new();
+        tracker.set_image_state(&image, image::SubresourceRange {
+            aspects: color,
+            levels: 0 .. 3,
+            layers: 0 .. 4,
+        }, undefined);
+
+        let barrier = tracker.require(vec![
+            image_usage(&image, color, 0 .. 3, 0 .. 4, transfer, PipelineStage::TRANSFER),
+        ]).unwrap();
+        assert_eq!(image_ranges(&barrier), vec![
+            image::SubresourceRange { aspects: color, levels: 0 .. 3, layers: 0 .. 4 },
+        ]);
+
+        // a hole in the layers splits the levels into blocks
+        let sampled = (image::Access::SHADER_READ, image::Layout::ShaderReadOnlyOptimal);
+        let barrier = tracker.require(vec![
+            image_usage(&image, color, 0 .. 2, 0 .. 1, sampled, PipelineStage::FRAGMENT_SHADER),
+            image_usage(&image, color, 2 .. 3, 0 .. 2, sampled, PipelineStage::FRAGMENT_SHADER),
+        ]).unwrap();
+        assert_eq!(image_ranges(&barrier), vec![
+            image::SubresourceRange { aspects: color, levels: 0 .. 2, layers: 0 .. 1 },
+            image::SubresourceRange { aspects: color, levels: 2 .. 3, layers: 0 .. 2 },
+        ]);
+    }
+
+    #[test]
+    fn merge_subresource_blocks() {
+        let a = (image::Access::empty(), image::Layout::Undefined);
+        let b = (image::Access::TRANSFER_WRITE, image::Layout::TransferDstOptimal);
+        let transitions = vec![(0, 0, a), (0, 1, a), (0, 2, b), (1, 0, a), (1, 1, a), (1, 2, b), (2, 1, a)];
+        assert_eq!(merge_subresources(transitions), vec![
+            (0 .. 2, 0 .. 2, a),
+            (0 .. 2, 2 .. 3, b),
+            (2 .. 3, 1 .. 2, a),
+        ]);
+    }
+
+    fn aliased_images(barriers: &[Barrier<Backend>]) -> Vec<(usize, usize)> {
+        barriers
+            .iter()
+            .map(|barrier| match *barrier {
+                Barrier::Aliasing { before: Some(before), after } => (before.0, after.0),
+                _ => panic!("not an aliasing barrier"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn aliasing_overlaps() {
+        let memory = Resource(0);
+        let (a, b, c) = (Resource(1), Resource(2), Resource(3));
+        let mut tracker = AliasingTracker::<Backend>::new(&memory);
+        tracker.bind(&memory, &a, 0 .. 256);
+        tracker.bind(&memory, &b, 128 .. 384);
+        tracker.bind(&memory, &c, 256 .. 512);
+
+        assert!(tracker.activate(&a).is_empty());
+        // `c` starts where `a` ends
+        assert!(tracker.activate(&c).is_empty());
+        assert!(tracker.is_active(&a) && tracker.is_active(&c));
+
+        assert_eq!(aliased_images(&tracker.activate(&b)), vec![(1, 2), (3, 2)]);
+        assert!(!tracker.is_active(&a) && !tracker.is_active(&c));
+        assert!(tracker.is_active(&b));
+
+        assert_eq!(aliased_images(&tracker.activate(&a)), vec![(2, 1)]);
+        assert!(tracker.activate(&a).is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "already bound")]
+    fn aliasing_double_bind() {
+        let memory = Resource(0);
+        let image = Resource(1);
+        let mut tracker = AliasingTracker::<Backend>::new(&memory);
+        tracker.bind(&memory, &image, 0 .. 256);
+        tracker.bind(&memory, &image, 256 .. 512);
+    }
+
+    #[test]
+    #[should_panic(expected = "another memory")]
+    fn aliasing_other_memory() {
+        let (memory, other) = (Resource(0), Resource(1));
+        let image = Resource(2);
+        let mut tracker = AliasingTracker::<Backend>::new(&memory);
+        tracker.bind(&other, &image, 0 .. 256);
+    }
+
+    #[test]
+    #[should_panic(expected = "not bound")]
+    fn aliasing_activate_unbound() {
+        let memory = Resource(0);
+        let image = Resource(1);
+        let mut tracker = AliasingTracker::<Backend>::new(&memory);
+        tracker.activate(&image);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    fn aliasing_validation() {
+        use std::panic::{catch_unwind, AssertUnwindSafe};
+
+        let memory = Resource(0);
+        let (a, b, untracked) = (Resource(1), Resource(2), Resource(3));
+        let mut tracker = AliasingTracker::<Backend>::new(&memory);
+        tracker.bind(&memory, &a, 0 .. 256);
+        tracker.bind(&memory, &b, 0 .. 256);
+        tracker.activate(&a);
+
+        let sampled = (image::Access::SHADER_READ, image::Layout::ShaderReadOnlyOptimal);
+        let usage = |image| image_usage(
+            image, format::Aspects::COLOR, 0 .. 1, 0 .. 1, sampled, PipelineStage::FRAGMENT_SHADER);
+        tracker.validate(&[usage(&a), usage(&untracked)]);
+        let inactive = [usage(&b)];
+        assert!(catch_unwind(AssertUnwindSafe(|| tracker.validate(&inactive))).is_err());
+    }
 }
@@ -0,0 +1,821 @@
+#![allow(dead_code)]
+
+//! Backend for unit tests, based on the empty backend.
+//!
+//! Resources are distinct values, so that they can be
+//! told apart by address, and command buffers record the called commands.
+
+use std::borrow::{Borrow, BorrowMut};
+use std::ops::Range;
+use {buffer, command, device, error, format, image, mapping};
+use {memory, pass, pool, pso, query, queue, window};
+use range::RangeArg;
+
+/// Any resource.
+#[derive(Debug, Default)]
+pub struct Resource(pub usize);
+
+/// Test backend.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Backend { }
+impl ::Backend for Backend {
+    type PhysicalDevice = PhysicalDevice;
+    type Device = Device;
+
+    type Surface = Surface;
+    type Swapchain = Swapchain;
+
+    type QueueFamily = QueueFamily;
+    type CommandQueue = RawCommandQueue;
+    type CommandBuffer = RawCommandBuffer;
+
+    type Memory = Resource;
+    type CommandPool = RawCommandPool;
+
+    type ShaderModule = Resource;
+    type RenderPass = Resource;
+    type Framebuffer = Resource;
+
+    type UnboundBuffer = Resource;
+    type Buffer = Resource;
+    type BufferView = Resource;
+    type UnboundImage = Resource;
+    type Image = Resource;
+    type ImageView = Resource;
+    type Sampler = Resource;
+
+    type ComputePipeline = Resource;
+    type GraphicsPipeline = Resource;
+    type PipelineLayout = Resource;
+    type DescriptorSetLayout = Resource;
+    type DescriptorPool = DescriptorPool;
+    type DescriptorSet = Resource;
+
+    type Fence = Resource;
+    type Semaphore = Resource;
+    type QueryPool = Resource;
+}
+
+/// Physical device with the given properties.
+#[derive(Debug)]
+pub struct PhysicalDevice {
+    pub memory_properties: ::MemoryProperties,
+    pub features: ::Features,
+    pub limits: ::Limits,
+}
+impl ::PhysicalDevice<Backend> for PhysicalDevice {
+    fn open(
+        &self, _: &[(&QueueFamily, &[::QueuePriority])]
+    ) -> Result<::Gpu<Backend>, error::DeviceCreationError> {
+        unimplemented!()
+    }
+
+    fn format_properties(&self, _: Option<format::Format>) -> format::Properties {
+        unimplemented!()
+    }
+
+    fn image_format_properties(
+        &self, _: format::Format, _dim: u8, _: image:: Tiling,
+        _: image::Usage, _: image::StorageFlags,
+    ) -> Option<image::FormatProperties> {
+        unimplemented!()
+    }
+
+    fn memory_properties(&self) -> ::MemoryProperties {
+        self.memory_properties.clone()
+    }
+
+    fn features(&self) -> ::Features {
+        self.features
+    }
+
+    fn limits(&self) -> ::Limits {
+        self.limits
+    }
+}
+
+/// Dummy command queue doing nothing.
+pub struct RawCommandQueue;
+impl queue::RawCommandQueue<Backend> for RawCommandQueue {
+    unsafe fn submit_raw<IC>(&mut self, _: queue::RawSubmission<Backend, IC>, _: Option<&Resource>)
+    where
+        IC: IntoIterator,
+        IC::Item: Borrow<RawCommandBuffer>,
+    {
+        unimplemented!()
+    }
+
+    fn present<IS, IW>(&mut self, _: IS, _: IW) -> Result<(), ()>
+    where
+        IS: IntoIterator,
+        IS::Item: BorrowMut<Swapchain>,
+        IW: IntoIterator,
+        IW::Item: Borrow<Resource>,
+    {
+        unimplemented!()
+    }
+
+    fn wait_idle(&self) -> Result<(), error::HostExecutionError> {
+        unimplemented!()
+    }
+}
+
+/// Dummy device doing nothing.
+pub struct Device;
+impl ::Device<Backend> for Device {
+    fn create_command_pool(&self, _: queue::QueueFamilyId, _: pool::CommandPoolCreateFlags) -> RawCommandPool {
+        unimplemented!()
+    }
+
+    fn destroy_command_pool(&self, _: RawCommandPool) {
+        unimplemented!()
+    }
+
+    fn allocate_memory(&self, _: ::MemoryTypeId, _: u64) -> Result<Resource, device::OutOfMemory> {
+        unimplemented!()
+    }
+
+    fn create_render_pass<'a ,IA, IS, ID>(&self, _: IA, _: IS, _: ID) -> Resource
+    where
+        IA: IntoIterator,
+        IA::Item: Borrow<pass::Attachment>,
+        IS: IntoIterator,
+        IS::Item: Borrow<pass::SubpassDesc<'a>>,
+        ID: IntoIterator,
+        ID::Item: Borrow<pass::SubpassDependency>,
+    {
+        unimplemented!()
+    }
+
+    fn create_pipeline_layout<IS, IR>(&self, _: IS, _: IR) -> Resource
+    where
+        IS: IntoIterator,
+        IS::Item: Borrow<Resource>,
+        IR: IntoIterator,
+        IR::Item: Borrow<(pso::ShaderStageFlags, Range<u32>)>,
+    {
+        unimplemented!()
+    }
+
+    fn create_framebuffer<I>(
+        &self, _: &Resource, _: I, _: image::Extent
+    ) -> Result<Resource, device::FramebufferError>
+    where
+        I: IntoIterator,
+        I::Item: Borrow<Resource>,
+    {
+        unimplemented!()
+    }
+
+    fn create_shader_module(&self, _: &[u8]) -> Result<Resource, device::ShaderError> {
+        unimplemented!()
+    }
+
+    fn create_sampler(&self, _: image::SamplerInfo) -> Resource {
+        unimplemented!()
+    }
+    fn create_buffer(&self, _: u64, _: buffer::Usage) -> Result<Resource, buffer::CreationError> {
+        unimplemented!()
+    }
+
+    fn get_buffer_requirements(&self, _: &Resource) -> memory::Requirements {
+        unimplemented!()
+    }
+
+    fn bind_buffer_memory(&self, _: &Resource, _: u64, _: Resource) -> Result<Resource, device::BindError> {
+        unimplemented!()
+    }
+
+    fn create_buffer_view<R: RangeArg<u64>>(&self, _: &Resource, _: Option<format::Format>, _: R) -> Result<Resource, buffer::ViewError> {
+        unimplemented!()
+    }
+
+    fn create_image(
+        &self,
+        _: image::Kind,
+        _: image::Level,
+        _: format::Format,
+        _: image::Tiling,
+        _: image::Usage,
+        _: image::StorageFlags,
+    ) -> Result<Resource, image::CreationError> {
+        unimplemented!()
+    }
+
+    fn get_image_requirements(&self, _: &Resource) -> memory::Requirements {
+        unimplemented!()
+    }
+
+    fn get_image_subresource_footprint(&self, _: &Resource, _: image::Subresource) -> image::SubresourceFootprint {
+        unimplemented!()
+    }
+
+    fn bind_image_memory(&self, _: &Resource, _: u64, _: Resource) -> Result<Resource, device::BindError> {
+        unimplemented!()
+    }
+
+    fn create_image_view(
+        &self,
+        _: &Resource,
+        _: image::ViewKind,
+        _: format::Format,
+        _: format::Swizzle,
+        _: image::SubresourceRange,
+    ) -> Result<Resource, image::ViewError> {
+        unimplemented!()
+    }
+
+    fn create_descriptor_pool<I>(&self, _: usize, _: I) -> DescriptorPool
+    where
+        I: IntoIterator,
+        I::Item: Borrow<pso::DescriptorRangeDesc>,
+    {
+        unimplemented!()
+    }
+
+    fn create_descriptor_set_layout<I, J>(&self, _: I, _: J) -> Resource
+    where
+        I: IntoIterator,
+        I::Item: Borrow<pso::DescriptorSetLayoutBinding>,
+        J: IntoIterator,
+        J::Item: Borrow<Resource>
+    {
+        unimplemented!()
+    }
+
+    fn write_descriptor_sets<'a, I, J>(&self, _: I)
+    where
+        I: IntoIterator<Item = pso::DescriptorSetWrite<'a, Backend, J>>,
+        J: IntoIterator,
+        J::Item: Borrow<pso::Descriptor<'a, Backend>>,
+    {
+        unimplemented!()
+    }
+
+    fn copy_descriptor_sets<'a, I>(&self, _: I)
+    where
+        I: IntoIterator,
+        I::Item: Borrow<pso::DescriptorSetCopy<'a, Backend>>
+    {
+        unimplemented!()
+    }
+
+    fn create_semaphore(&self) -> Resource {
+        unimplemented!()
+    }
+
+    fn create_fence(&self, _: bool) -> Resource {
+        unimplemented!()
+    }
+
+    fn get_fence_status(&self, _: &Resource) -> bool {
+        unimplemented!()
+    }
+
+    fn create_query_pool(&self, _: query::QueryType, _: u32) -> Resource {
+        unimplemented!()
+    }
+
+    fn destroy_query_pool(&self, _: Resource) {
+        unimplemented!()
+    }
+
+    fn map_memory<R: RangeArg<u64>>(&self, _: &Resource, _: R) -> Result<*mut u8, mapping::Error> {
+        unimplemented!()
+    }
+
+    fn unmap_memory(&self, _: &Resource) {
+        unimplemented!()
+    }
+
+    fn memory_coherency(&self, _: &Resource) -> mapping::Coherency {
+        unimplemented!()
+    }
+
+    fn flush_mapped_memory_ranges<'a, I, R>(&self, _: I)
+    where
+        I: IntoIterator,
+        I::Item: Borrow<(&'a Resource, R)>,
+        R: RangeArg<u64>,
+    {
+        unimplemented!()
+    }
+
+    fn invalidate_mapped_memory_ranges<'a, I, R>(&self, _: I)
+    where
+        I: IntoIterator,
+        I::Item: Borrow<(&'a Resource, R)>,
+        R: RangeArg<u64>,
+    {
+        unimplemented!()
+    }
+
+    fn free_memory(&self, _: Resource) {
+        unimplemented!()
+    }
+
+    fn memory_budget(&self) -> Vec<memory::HeapBudget> {
+        unimplemented!()
+    }
+
+    fn destroy_shader_module(&self, _: Resource) {
+        unimplemented!()
+    }
+
+    fn destroy_render_pass(&self, _: Resource) {
+        unimplemented!()
+    }
+
+    fn destroy_pipeline_layout(&self, _: Resource) {
+        unimplemented!()
+    }
+    fn destroy_graphics_pipeline(&self, _: Resource) {
+        unimplemented!()
+    }
+    fn destroy_compute_pipeline(&self, _: Resource) {
+        unimplemented!()
+    }
+    fn destroy_framebuffer(&self, _: Resource) {
+        unimplemented!()
+    }
+
+    fn destroy_buffer(&self, _: Resource) {
+        unimplemented!()
+    }
+    fn destroy_buffer_view(&self, _: Resource) {
+        unimplemented!()
+    }
+    fn destroy_image(&self, _: Resource) {
+        unimplemented!()
+    }
+    fn destroy_image_view(&self, _: Resource) {
+        unimplemented!()
+    }
+    fn destroy_sampler(&self, _: Resource) {
+        unimplemented!()
+    }
+
+    fn destroy_descriptor_pool(&self, _: DescriptorPool) {
+        unimplemented!()
+    }
+
+    fn destroy_descriptor_set_layout(&self, _: Resource) {
+        unimplemented!()
+    }
+
+    fn destroy_fence(&self, _: Resource) {
+        unimplemented!()
+    }
+
+    fn destroy_semaphore(&self, _: Resource) {
+        unimplemented!()
+    }
+
+    fn create_swapchain(
+        &self,
+        _: &mut Surface,
+        _: ::SwapchainConfig,
+        _: Option<Swapchain>,
+        _: &window::Extent2D,
+    ) -> (Swapchain, ::Backbuffer<Backend>) {
+        unimplemented!()
+    }
+
+    fn destroy_swapchain(&self, _: Swapchain) {
+        unimplemented!()
+    }
+
+    fn wait_idle(&self) -> Result<(), error::HostExecutionError> {
+        unimplemented!()
+    }
+}
+
+#[derive(Debug)]
+pub struct QueueFamily(pub ::QueueType, pub usize);
+impl queue::QueueFamily for QueueFamily {
+    fn queue_type(&self) -> ::QueueType {
+        self.0
+    }
+    fn max_queues(&self) -> usize {
+        1
+    }
+    fn id(&self) -> queue::QueueFamilyId {
+        queue::QueueFamilyId(self.1)
+    }
+}
+
+/// Dummy raw command pool.
+pub struct RawCommandPool;
+impl pool::RawCommandPool<Backend> for RawCommandPool {
+    fn reset(&mut self) {
+        unimplemented!()
+    }
+
+    fn allocate(&mut self, _: usize, _: command::RawLevel) -> Vec<RawCommandBuffer> {
+        unimplemented!()
+    }
+
+    unsafe fn free(&mut self, _: Vec<RawCommandBuffer>) {
+        unimplemented!()
+    }
+}
+
+/// Command buffer recording the names of the called commands.
+#[derive(Clone, Debug, Default)]
+pub struct RawCommandBuffer {
+    pub commands: Vec<&'static str>,
+}
+impl command::RawCommandBuffer<Backend> for RawCommandBuffer {
+    fn begin(&mut self, _: command::CommandBufferFlags, _: command::CommandBufferInheritanceInfo<Backend>) {
+        self.commands.push("begin")
+    }
+
+    fn finish(&mut self) {
+        self.commands.push("finish")
+    }
+
+    fn reset(&mut self, _: bool) {
+        self.commands.push("reset")
+    }
+
+    fn pipeline_barrier<'a, T>(
+        &mut self,
+        _: Range<pso::PipelineStage>,
+        _: memory::Dependencies,
+        _: T,
+    ) where
+        T: IntoIterator,
+        T::Item: Borrow<memory::Barrier<'a, Backend>>,
+    {
+        self.commands.push("pipeline_barrier")
+    }
+
+    fn fill_buffer<R>(&mut self, _: &Resource, _: R, _: u32)
+    where
+        R: RangeArg<buffer::Offset>,
+    {
+        self.commands.push("fill_buffer")
+    }
+
+    fn update_buffer(&mut self, _: &Resource, _: buffer::Offset, _: &[u8]) {
+        self.commands.push("update_buffer")
+    }
+
+    fn clear_image<T>(
+        &mut self,
+        _: &Resource,
+        _: image::Layout,
+        _: command::ClearColorRaw,
+        _: command::ClearDepthStencilRaw,
+        _: T,
+    ) where
+        T: IntoIterator,
+        T::Item: Borrow<image::SubresourceRange>,
+    {
+        self.commands.push("clear_image")
+    }
+
+    fn clear_attachments<T, U>(&mut self, _: T, _: U)
+    where
+        T: IntoIterator,
+        T::Item: Borrow<command::AttachmentClear>,
+        U: IntoIterator,
+        U::Item: Borrow<pso::ClearRect>,
+    {
+        self.commands.push("clear_attachments")
+    }
+
+    fn resolve_image<T>(
+        &mut self,
+        _: &Resource,
+        _: image::Layout,
+        _: &Resource,
+        _: image::Layout,
+        _: T,
+    ) where
+        T: IntoIterator,
+        T::Item: Borrow<command::ImageResolve>,
+    {
+        self.commands.push("resolve_image")
+    }
+
+    fn blit_image<T>(
+        &mut self,
+        _: &Resource,
+        _: image::Layout,
+        _: &Resource,
+        _: image::Layout,
+        _: image::Filter,
+        _: T,
+    ) where
+        T: IntoIterator,
+        T::Item: Borrow<command::ImageBlit>,
+    {
+        self.commands.push("blit_image")
+    }
+
+    fn bind_index_buffer(&mut self, _: buffer::IndexBufferView<Backend>) {
+        self.commands.push("bind_index_buffer")
+    }
+
+    fn bind_vertex_buffers(&mut self, _: u32, _: pso::VertexBufferSet<Backend>) {
+        self.commands.push("bind_vertex_buffers")
+    }
+
+    fn set_viewports<T>(&mut self, _: u32, _: T)
+    where
+        T: IntoIterator,
+        T::Item: Borrow<pso::Viewport>,
+    {
+        self.commands.push("set_viewports")
+    }
+
+    fn set_scissors<T>(&mut self, _: u32, _: T)
+    where
+        T: IntoIterator,
+        T::Item: Borrow<pso::Rect>,
+    {
+        self.commands.push("set_scissors")
+    }
+
+    fn set_stencil_reference(&mut self, _: pso::Face, _: pso::StencilValue) {
+        self.commands.push("set_stencil_reference")
+    }
+
+    fn set_stencil_read_mask(&mut self, _: pso::Face, _: pso::StencilValue) {
+        self.commands.push("set_stencil_read_mask")
+    }
+
+    fn set_stencil_write_mask(&mut self, _: pso::Face, _: pso::StencilValue) {
+        self.commands.push("set_stencil_write_mask")
+    }
+
+    fn set_blend_constants(&mut self, _: pso::ColorValue) {
+        self.commands.push("set_blend_constants")
+    }
+
+    fn set_depth_bounds(&mut self, _: Range<f32>) {
+        self.commands.push("set_depth_bounds")
+    }
+
+    fn set_line_width(&mut self, _: f32) {
+        self.commands.push("set_line_width")
+    }
+
+    fn set_depth_bias(&mut self, _: pso::DepthBias) {
+        self.commands.push("set_depth_bias")
+    }
+
+    fn begin_render_pass<T>(
+        &mut self,
+        _: &Resource,
+        _: &Resource,
+        _: pso::Rect,
+        _: T,
+        _: command::SubpassContents,
+    ) where
+        T: IntoIterator,
+        T::Item: Borrow<command::ClearValueRaw>,
+    {
+        self.commands.push("begin_render_pass")
+    }
+
+    fn next_subpass(&mut self, _: command::SubpassContents) {
+        self.commands.push("next_subpass")
+    }
+
+    fn end_render_pass(&mut self) {
+        self.commands.push("end_render_pass")
+    }
+
+    fn bind_graphics_pipeline(&mut self, _: &Resource) {
+        self.commands.push("bind_graphics_pipeline")
+    }
+
+    fn bind_graphics_descriptor_sets<I, J>(&mut self, _: &Resource, _: usize, _: I, _: J)
+    where
+        I: IntoIterator,
+        I::Item: Borrow<Resource>,
+        J: IntoIterator,
+        J::Item: Borrow<command::DescriptorSetOffset>,
+    {
+        self.commands.push("bind_graphics_descriptor_sets")
+    }
+
+    fn bind_compute_pipeline(&mut self, _: &Resource) {
+        self.commands.push("bind_compute_pipeline")
+    }
+
+    fn bind_compute_descriptor_sets<I, J>(&mut self, _: &Resource, _: usize, _: I, _: J)
+    where
+        I: IntoIterator,
+        I::Item: Borrow<Resource>,
+        J: IntoIterator,
+        J::Item: Borrow<command::DescriptorSetOffset>,
+    {
+        self.commands.push("bind_compute_descriptor_sets")
+    }
+
+    fn dispatch(&mut self, _: ::WorkGroupCount) {
+        self.commands.push("dispatch")
+    }
+
+    fn dispatch_indirect(&mut self, _: &Resource, _: buffer::Offset) {
+        self.commands.push("dispatch_indirect")
+    }
+
+    fn copy_buffer<T>(&mut self, _: &Resource, _: &Resource, _: T)
+    where
+        T: IntoIterator,
+        T::Item: Borrow<command::BufferCopy>,
+    {
+        self.commands.push("copy_buffer")
+    }
+
+    fn copy_image<T>(
+        &mut self,
+        _: &Resource,
+        _: image::Layout,
+        _: &Resource,
+        _: image::Layout,
+        _: T,
+    ) where
+        T: IntoIterator,
+        T::Item: Borrow<command::ImageCopy>,
+    {
+        self.commands.push("copy_image")
+    }
+
+    fn copy_buffer_to_image<T>(
+        &mut self,
+        _: &Resource,
+        _: &Resource,
+        _: image::Layout,
+        _: T,
+    ) where
+        T: IntoIterator,
+        T::Item: Borrow<command::BufferImageCopy>,
+    {
+        self.commands.push("copy_buffer_to_image")
+    }
+
+    fn copy_image_to_buffer<T>(
+        &mut self,
+        _: &Resource,
+        _: image::Layout,
+        _: &Resource,
+        _: T,
+    ) where
+        T: IntoIterator,
+        T::Item: Borrow<command::BufferImageCopy>,
+    {
+        self.commands.push("copy_image_to_buffer")
+    }
+
+    fn draw(&mut self,
+        _: Range<::VertexCount>,
+        _: Range<::InstanceCount>,
+    ) {
+        self.commands.push("draw")
+    }
+
+    fn draw_indexed(
+        &mut self,
+        _: Range<::IndexCount>,
+        _: ::VertexOffset,
+        _: Range<::InstanceCount>,
+    ) {
+        self.commands.push("draw_indexed")
+    }
+
+    fn draw_indirect(
+        &mut self,
+        _: &Resource,
+        _: buffer::Offset,
+        _: ::DrawCount,
+        _: u32,
+    ) {
+        self.commands.push("draw_indirect")
+    }
+
+    fn draw_indexed_indirect(
+        &mut self,
+        _: &Resource,
+        _: buffer::Offset,
+        _: ::DrawCount,
+        _: u32,
+    ) {
+        self.commands.push("draw_indexed_indirect")
+    }
+
+    fn begin_query(
+        &mut self,
+        _: query::Query<Backend>,
+        _: query::QueryControl,
+    ) {
+        self.commands.push("begin_query")
+    }
+
+    fn end_query(
+        &mut self,
+        _: query::Query<Backend>,
+    ) {
+        self.commands.push("end_query")
+    }
+
+    fn reset_query_pool(
+        &mut self,
+        _: &Resource,
+        _: Range<query::QueryId>,
+    ) {
+        self.commands.push("reset_query_pool")
+    }
+
+    fn write_timestamp(
+        &mut self,
+        _: pso::PipelineStage,
+        _: query::Query<Backend>,
+    ) {
+        self.commands.push("write_timestamp")
+    }
+
+    fn push_graphics_constants(
+        &mut self,
+        _: &Resource,
+        _: pso::ShaderStageFlags,
+        _: u32,
+        _: &[u32],
+    ) {
+        self.commands.push("push_graphics_constants")
+    }
+
+    fn push_compute_constants(
+        &mut self,
+        _: &Resource,
+        _: u32,
+        _: &[u32],
+    ) {
+        self.commands.push("push_compute_constants")
+    }
+
+    fn execute_commands<I>(
+        &mut self,
+        _: I,
+    ) where
+        I: IntoIterator,
+        I::Item: Borrow<RawCommandBuffer>
+    {
+        self.commands.push("execute_commands")
+    }
+
+}
+
+// Dummy descriptor pool.
+#[derive(Debug)]
+pub struct DescriptorPool;
+impl pso::DescriptorPool<Backend> for DescriptorPool {
+    fn free_sets(&mut self, _descriptor_sets: &[Resource]) {
+        unimplemented!()
+    }
+
+    fn reset(&mut self) {
+        unimplemented!()
+    }
+}
+
+/// Surface supported by the listed queue families.
+#[derive(Debug, Default)]
+pub struct Surface {
+    pub families: Vec<usize>,
+}
+impl ::Surface<Backend> for Surface {
+    fn kind(&self) -> ::image::Kind {
+        unimplemented!()
+    }
+
+    fn capabilities_and_formats(
+        &self, _: &PhysicalDevice,
+    ) -> (::SurfaceCapabilities, Option<Vec<(format::Format, ::window::ColorSpace)>>) {
+        unimplemented!()
+    }
+
+    fn supports_queue_family(&self, family: &QueueFamily) -> bool {
+        self.families.contains(&family.1)
+    }
+}
+
+/// Dummy swapchain.
+pub struct Swapchain;
+impl ::Swapchain<Backend> for Swapchain {
+    fn acquire_frame(&mut self, _: ::FrameSync<Backend>) -> Result<::Frame, ()> {
+        unimplemented!()
+    }
+}
+
+pub struct Instance;
+impl ::Instance for Instance {
+    type Backend = Backend;
+    fn enumerate_adapters(&self) -> Vec<::Adapter<Backend>> {
+        unimplemented!()
+    }
+}
@@ -3,7 +3,7 @@
 use format::Format;
 use image;
 use pso::PipelineStage;
-use Backend;
+use {Backend, Device};
 use std::ops::Range;
 
 /// Specifies the operation which will be applied at the beginning of a subpass.
@@ -67,7 +67,7 @@ impl AttachmentOps {
 /// An `Attachment` is a description of a resource provided to a render subpass.
 /// It includes things such as render targets, images that were produced from
 /// previous subpasses, etc.
-#[derive(Clone, Debug, Hash, PartialEq)]
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Attachment {
     /// Attachment format
@@ -93,7 +93,7 @@ pub type AttachmentId = usize;
 pub type AttachmentRef = (AttachmentId, AttachmentLayout);
 
 /// Which other subpasses a particular subpass depends on.
-#[derive(Copy, Clone, Debug, Hash, PartialEq)]
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum SubpassRef {
     /// The subpass depends on something that was submitted to the
@@ -110,7 +110,7 @@ pub enum SubpassRef {
 /// both to describe a source or destination subpass; data either
 /// explicitly passes from this subpass to the next or from another
 /// subpass into this one.
-#[derive(Clone, Debug, Hash)]
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct SubpassDependency {
     /// Other subpasses this one depends on.
@@ -140,6 +140,152 @@ pub struct SubpassDesc<'a> {
     pub preserves: &'a [AttachmentId],
 }
 
+/// Owned description of a subpass, see `SubpassDesc`.
+#[derive(Clone, Debug, Default, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct OwnedSubpassDesc {
+    /// Which attachments will be used as color buffers.
+    pub colors: Vec<AttachmentRef>,
+    /// Which attachments will be used as depth/stencil buffers.
+    pub depth_stencil: Option<AttachmentRef>,
+    /// Which attachments will be used as input attachments.
+    pub inputs: Vec<AttachmentRef>,
+    /// Which attachments will be used as resolve destinations.
+    pub resolves: Vec<AttachmentRef>,
+    /// Attachments that are not used by the subpass but must be preserved to be
+    /// passed on to subsequent passes.
+    pub preserves: Vec<AttachmentId>,
+}
+
+impl OwnedSubpassDesc {
+    /// Borrow as a `SubpassDesc` suitable for renderpass creation.
+    pub fn as_desc(&self) -> SubpassDesc {
+        SubpassDesc {
+            colors: &self.colors,
+            depth_stencil: self.depth_stencil.as_ref(),
+            inputs: &self.inputs,
+            resolves: &self.resolves,
+            preserves: &self.preserves,
+        }
+    }
+}
+
+impl<'a> From<SubpassDesc<'a>> for OwnedSubpassDesc {
+    fn from(desc: SubpassDesc<'a>) -> Self {
+        OwnedSubpassDesc {
+            colors: desc.colors.to_vec(),
+            depth_stencil: desc.depth_stencil.cloned(),
+            inputs: desc.inputs.to_vec(),
+            resolves: desc.resolves.to_vec(),
+            preserves: desc.preserves.to_vec(),
+        }
+    }
+}
+
+/// Owned description of a whole renderpass.
+///
+/// Unlike the raw `B::RenderPass` this can be hashed and compared, which makes it
+/// usable as a key for caching renderpasses, see `is_compatible_with` for sharing
+/// them between pipelines and framebuffers.
+#[derive(Clone, Debug, Default, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct RenderPassDesc {
+    /// Attachments used by the renderpass.
+    pub attachments: Vec<Attachment>,
+    /// Subpasses of the renderpass, in execution order.
+    pub subpasses: Vec<OwnedSubpassDesc>,
+    /// Dependencies between the subpasses.
+    pub dependencies: Vec<SubpassDependency>,
+}
+
+impl RenderPassDesc {
+    /// Create an empty renderpass description.
+    pub fn new() -> Self {
+        RenderPassDesc::default()
+    }
+
+    /// Add an attachment.
+    pub fn with_attachment(mut self, attachment: Attachment) -> Self {
+        self.attachments.push(attachment);
+        self
+    }
+
+    /// Add a subpass.
+    pub fn with_subpass<S: Into<OwnedSubpassDesc>>(mut self, subpass: S) -> Self {
+        self.subpasses.push(subpass.into());
+        self
+    }
+
+    /// Add a subpass dependency.
+    pub fn with_dependency(mut self, dependency: SubpassDependency) -> Self {
+        self.dependencies.push(dependency);
+        self
+    }
+
+    /// Create a renderpass matching this description.
+    pub fn create<B: Backend, D: Device<B>>(&self, device: &D) -> B::RenderPass {
+        let subpasses = self.subpasses
+            .iter()
+            .map(OwnedSubpassDesc::as_desc)
+            .collect::<Vec<_>>();
+        device.create_render_pass(&self.attachments, subpasses, &self.dependencies)
+    }
+
+    /// Check if renderpasses created from `self` and `other` are compatible,
+    /// i.e. pipelines and framebuffers created for one may be used with the other.
+    ///
+    /// Follows the Vulkan rules: both need the same attachment formats and
+    /// sample counts, the same number of subpasses with compatible attachment
+    /// references, and identical dependencies. Load/store operations and
+    /// image layouts are ignored. Missing references (e.g. a shorter color
+    /// list) are treated as unused. For single subpass renderpasses the resolve
+    /// attachments are not taken into account.
+    pub fn is_compatible_with(&self, other: &RenderPassDesc) -> bool {
+        let same_attachments = self.attachments.len() == other.attachments.len() &&
+            self.attachments
+                .iter()
+                .zip(&other.attachments)
+                .all(|(a, b)| a.format == b.format && a.samples == b.samples);
+        if !same_attachments ||
+            self.subpasses.len() != other.subpasses.len() ||
+            self.dependencies != other.dependencies
+        {
+            return false;
+        }
+
+        let single = self.subpasses.len() == 1;
+        self.subpasses
+            .iter()
+            .zip(&other.subpasses)
+            .all(|(a, b)| {
+                self.refs_compatible(&a.colors, other, &b.colors) &&
+                self.refs_compatible(&a.inputs, other, &b.inputs) &&
+                (single || self.refs_compatible(&a.resolves, other, &b.resolves)) &&
+                self.ref_compatible(a.depth_stencil.as_ref(), other, b.depth_stencil.as_ref()) &&
+                a.preserves == b.preserves
+            })
+    }
+
+    fn refs_compatible(
+        &self, refs: &[AttachmentRef], other: &RenderPassDesc, other_refs: &[AttachmentRef]
+    ) -> bool {
+        (0 .. refs.len().max(other_refs.len()))
+            .all(|i| self.ref_compatible(refs.get(i), other, other_refs.get(i)))
+    }
+
+    fn ref_compatible(
+        &self, at: Option<&AttachmentRef>, other: &RenderPassDesc, other_at: Option<&AttachmentRef>
+    ) -> bool {
+        fn lookup(
+            desc: &RenderPassDesc, at: Option<&AttachmentRef>
+        ) -> Option<(Option<Format>, image::NumSamples)> {
+            at.and_then(|&(id, _)| desc.attachments.get(id))
+                .map(|a| (a.format, a.samples))
+        }
+        lookup(self, at) == lookup(other, other_at)
+    }
+}
+
 /// Index of a subpass.
 pub type SubpassId = usize;
 
@@ -170,3 +316,33 @@ impl<'a, B: Backend> PartialEq for Subpass<'a, B> {
 
 impl<'a, B: Backend> Copy for Subpass<'a, B> {}
 impl<'a, B: Backend> Eq for Subpass<'a, B> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn color_pass(format: Format, load: AttachmentLoadOp) -> RenderPassDesc {
+        RenderPassDesc::new()
+            .with_attachment(Attachment {
+                format: Some(format),
+                samples: 1,
+                ops: AttachmentOps::new(load, AttachmentStoreOp::Store),
+                stencil_ops: AttachmentOps::DONT_CARE,
+                layouts: image::Layout::Undefined .. image::Layout::Present,
+            })
+            .with_subpass(OwnedSubpassDesc {
+                colors: vec![(0, image::Layout::ColorAttachmentOptimal)],
+                .. OwnedSubpassDesc::default()
+            })
+    }
+
+    #[test]
+    fn compatibility() {
+        let clear = color_pass(Format::Rgba8Srgb, AttachmentLoadOp::Clear);
+        let load = color_pass(Format::Rgba8Srgb, AttachmentLoadOp::Load);
+        let other = color_pass(Format::Bgra8Srgb, AttachmentLoadOp::Clear);
+        assert_ne!(clear, load);
+        assert!(clear.is_compatible_with(&load));
+        assert!(!clear.is_compatible_with(&other));
+    }
+}
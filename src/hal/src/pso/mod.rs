@@ -13,12 +13,14 @@ mod descriptor;
 mod graphics;
 mod input_assembler;
 mod output_merger;
+mod reflect;
 
 pub use self::compute::*;
 pub use self::descriptor::*;
 pub use self::graphics::*;
 pub use self::input_assembler::*;
 pub use self::output_merger::*;
+pub use self::reflect::*;
 
 use Backend;
 
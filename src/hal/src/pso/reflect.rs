@@ -0,0 +1,942 @@
+//! Shader module reflection.
+//!
+//! Extracts the interface of a SPIR-V module (entry points, descriptors, push constants,
+//! stage inputs/outputs and specialization constants) without going through a backend.
+//! This allows validating pipeline layouts and vertex formats against shaders at load time.
+
+use std::collections::HashMap;
+use std::iter;
+use std::ops::Range;
+
+use {Backend, Device};
+use format::Format;
+use pso::{
+    Constant, DescriptorArrayIndex, DescriptorBinding, DescriptorSetIndex,
+    DescriptorSetLayoutBinding, DescriptorType, ShaderStageFlags, Stage,
+};
+
+const MAGIC_NUMBER: u32 = 0x07230203;
+const HEADER_LENGTH: usize = 5;
+
+// Opcodes
+const OP_NAME: u16 = 5;
+const OP_ENTRY_POINT: u16 = 15;
+const OP_EXECUTION_MODE: u16 = 16;
+const OP_TYPE_BOOL: u16 = 20;
+const OP_TYPE_INT: u16 = 21;
+const OP_TYPE_FLOAT: u16 = 22;
+const OP_TYPE_VECTOR: u16 = 23;
+const OP_TYPE_MATRIX: u16 = 24;
+const OP_TYPE_IMAGE: u16 = 25;
+const OP_TYPE_SAMPLER: u16 = 26;
+const OP_TYPE_SAMPLED_IMAGE: u16 = 27;
+const OP_TYPE_ARRAY: u16 = 28;
+const OP_TYPE_RUNTIME_ARRAY: u16 = 29;
+const OP_TYPE_STRUCT: u16 = 30;
+const OP_TYPE_POINTER: u16 = 32;
+const OP_CONSTANT_TRUE: u16 = 41;
+const OP_CONSTANT_FALSE: u16 = 42;
+const OP_CONSTANT: u16 = 43;
+const OP_CONSTANT_COMPOSITE: u16 = 44;
+const OP_SPEC_CONSTANT_TRUE: u16 = 48;
+const OP_SPEC_CONSTANT_FALSE: u16 = 49;
+const OP_SPEC_CONSTANT: u16 = 50;
+const OP_SPEC_CONSTANT_COMPOSITE: u16 = 51;
+const OP_VARIABLE: u16 = 59;
+const OP_DECORATE: u16 = 71;
+const OP_MEMBER_DECORATE: u16 = 72;
+
+// Decorations
+const DECORATION_SPEC_ID: u32 = 1;
+const DECORATION_BUFFER_BLOCK: u32 = 3;
+const DECORATION_ARRAY_STRIDE: u32 = 6;
+const DECORATION_MATRIX_STRIDE: u32 = 7;
+const DECORATION_BUILT_IN: u32 = 11;
+const DECORATION_LOCATION: u32 = 30;
+const DECORATION_BINDING: u32 = 33;
+const DECORATION_DESCRIPTOR_SET: u32 = 34;
+const DECORATION_OFFSET: u32 = 35;
+
+// Storage classes
+const STORAGE_CLASS_UNIFORM_CONSTANT: u32 = 0;
+const STORAGE_CLASS_INPUT: u32 = 1;
+const STORAGE_CLASS_UNIFORM: u32 = 2;
+const STORAGE_CLASS_OUTPUT: u32 = 3;
+const STORAGE_CLASS_PUSH_CONSTANT: u32 = 9;
+const STORAGE_CLASS_STORAGE_BUFFER: u32 = 12;
+
+const EXECUTION_MODE_LOCAL_SIZE: u32 = 17;
+const BUILT_IN_WORKGROUP_SIZE: u32 = 25;
+const DIM_BUFFER: u32 = 5;
+const DIM_SUBPASS_DATA: u32 = 6;
+
+/// An error reflecting a SPIR-V module.
+#[derive(Fail, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReflectError {
+    /// The data doesn't start with the SPIR-V magic number.
+    #[fail(display = "Not a SPIR-V module.")]
+    InvalidMagic,
+    /// The data ends in the middle of the header or an instruction.
+    #[fail(display = "SPIR-V module is truncated.")]
+    Truncated,
+    /// An instruction is missing operands or references an undefined type.
+    #[fail(display = "Malformed SPIR-V instruction (opcode {}).", _0)]
+    Malformed(u16),
+}
+
+/// Interface of a shader module.
+#[derive(Clone, Debug, Default)]
+pub struct ShaderInterface {
+    /// Entry points declared in the module.
+    pub entry_points: Vec<EntryPointInterface>,
+    /// Descriptors declared in the module, sorted by set and binding.
+    pub descriptors: Vec<DescriptorInterface>,
+    /// Push constant range, in units of `u32`, and the stages of the module's entry points.
+    pub push_constants: Option<(ShaderStageFlags, Range<u32>)>,
+    /// Specialization constants which can be overridden on pipeline creation.
+    pub specialization_constants: Vec<SpecializationConstant>,
+}
+
+impl ShaderInterface {
+    /// Look up an entry point by name.
+    pub fn entry_point(&self, name: &str) -> Option<&EntryPointInterface> {
+        self.entry_points.iter().find(|ep| ep.name == name)
+    }
+
+    /// Look up the descriptor declared at the given set and binding.
+    pub fn descriptor(
+        &self, set: DescriptorSetIndex, binding: DescriptorBinding
+    ) -> Option<&DescriptorInterface> {
+        self.descriptors
+            .iter()
+            .find(|desc| desc.set == set && desc.binding == binding)
+    }
+}
+
+/// Interface of a single shader entry point.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct EntryPointInterface {
+    /// Entry point name.
+    pub name: String,
+    /// Pipeline stage the entry point is executed in.
+    pub stage: Stage,
+    /// User-defined stage inputs, sorted by location.
+    ///
+    /// For vertex shaders these are the vertex attributes.
+    pub inputs: Vec<InterfaceVariable>,
+    /// User-defined stage outputs, sorted by location.
+    ///
+    /// For fragment shaders these are the color targets.
+    pub outputs: Vec<InterfaceVariable>,
+    /// Workgroup size of compute entry points.
+    pub local_size: Option<[u32; 3]>,
+}
+
+/// Stage input or output variable.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct InterfaceVariable {
+    /// Location of the variable.
+    ///
+    /// Matrices are split into one variable per column, occupying consecutive locations.
+    /// Arrays are reported once, at their first location.
+    pub location: u32,
+    /// Variable name, if debug information is present.
+    pub name: Option<String>,
+    /// Format matching the variable type, or `None` if the type isn't a
+    /// scalar or vector of numbers.
+    pub format: Option<Format>,
+}
+
+/// Descriptor binding declared by a shader.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct DescriptorInterface {
+    /// Descriptor set index.
+    pub set: DescriptorSetIndex,
+    /// Binding inside the set.
+    pub binding: DescriptorBinding,
+    /// Type of the bound descriptors.
+    pub ty: DescriptorType,
+    /// Number of descriptors in the array, zero for runtime sized arrays.
+    pub count: DescriptorArrayIndex,
+    /// Stages of the module's entry points.
+    pub stage_flags: ShaderStageFlags,
+    /// Variable or block name, if debug information is present.
+    pub name: Option<String>,
+}
+
+/// Specialization constant declared by a shader.
+#[derive(Clone, Debug)]
+pub struct SpecializationConstant {
+    /// Constant identifier, matching `Specialization::id`.
+    pub id: u32,
+    /// Constant name, if debug information is present.
+    pub name: Option<String>,
+    /// Value used if the constant isn't specialized.
+    pub default: Constant,
+}
+
+#[derive(Clone, Debug)]
+enum Type {
+    Bool,
+    Int { width: u32, signed: bool },
+    Float { width: u32 },
+    Vector { component: u32, count: u32 },
+    Matrix { column: u32, count: u32 },
+    Image { dim: u32, sampled: u32 },
+    Sampler,
+    SampledImage,
+    Array { element: u32, length: u32 },
+    RuntimeArray { element: u32 },
+    Struct { members: Vec<u32> },
+    Pointer { pointee: u32 },
+}
+
+#[derive(Clone, Debug, Default)]
+struct Decorations {
+    spec_id: Option<u32>,
+    buffer_block: bool,
+    array_stride: Option<u32>,
+    matrix_stride: Option<u32>,
+    built_in: Option<u32>,
+    location: Option<u32>,
+    binding: Option<u32>,
+    set: Option<u32>,
+    offset: Option<u32>,
+}
+
+impl Decorations {
+    fn apply(&mut self, decoration: u32, value: Option<u32>) {
+        match decoration {
+            DECORATION_SPEC_ID => self.spec_id = value,
+            DECORATION_BUFFER_BLOCK => self.buffer_block = true,
+            DECORATION_ARRAY_STRIDE => self.array_stride = value,
+            DECORATION_MATRIX_STRIDE => self.matrix_stride = value,
+            DECORATION_BUILT_IN => self.built_in = value,
+            DECORATION_LOCATION => self.location = value,
+            DECORATION_BINDING => self.binding = value,
+            DECORATION_DESCRIPTOR_SET => self.set = value,
+            DECORATION_OFFSET => self.offset = value,
+            _ => {}
+        }
+    }
+}
+
+struct EntryPointDecl {
+    model: u32,
+    id: u32,
+    name: String,
+    interface: Vec<u32>,
+}
+
+#[derive(Default)]
+struct Module {
+    names: HashMap<u32, String>,
+    decorations: HashMap<u32, Decorations>,
+    member_decorations: HashMap<(u32, u32), Decorations>,
+    types: HashMap<u32, Type>,
+    /// Scalar (specialization) constants: type and literal words.
+    constants: HashMap<u32, (u32, Vec<u32>)>,
+    composites: HashMap<u32, Vec<u32>>,
+    spec_constants: Vec<u32>,
+    variables: Vec<(u32, u32, u32)>,
+    entry_points: Vec<EntryPointDecl>,
+    local_sizes: HashMap<u32, [u32; 3]>,
+}
+
+fn operand(operands: &[u32], index: usize, opcode: u16) -> Result<u32, ReflectError> {
+    operands
+        .get(index)
+        .cloned()
+        .ok_or(ReflectError::Malformed(opcode))
+}
+
+/// Decode a nul-terminated literal string, returning it with the number of words consumed.
+fn literal_string(operands: &[u32]) -> (String, usize) {
+    let mut bytes = Vec::new();
+    for (i, &word) in operands.iter().enumerate() {
+        for shift in &[0, 8, 16, 24] {
+            let byte = (word >> shift) as u8;
+            if byte == 0 {
+                return (String::from_utf8_lossy(&bytes).into_owned(), i + 1);
+            }
+            bytes.push(byte);
+        }
+    }
+    (String::from_utf8_lossy(&bytes).into_owned(), operands.len())
+}
+
+impl Module {
+    fn parse(words: &[u32]) -> Result<Self, ReflectError> {
+        let mut module = Module::default();
+        let mut offset = HEADER_LENGTH;
+
+        while offset < words.len() {
+            let word_count = (words[offset] >> 16) as usize;
+            let opcode = words[offset] as u16;
+            if word_count == 0 || offset + word_count > words.len() {
+                return Err(ReflectError::Truncated);
+            }
+            let ops = &words[offset + 1 .. offset + word_count];
+            offset += word_count;
+
+            match opcode {
+                OP_NAME => {
+                    let (name, _) = literal_string(&ops[1.min(ops.len()) ..]);
+                    module.names.insert(operand(ops, 0, opcode)?, name);
+                }
+                OP_ENTRY_POINT => {
+                    let (name, len) = literal_string(&ops[2.min(ops.len()) ..]);
+                    module.entry_points.push(EntryPointDecl {
+                        model: operand(ops, 0, opcode)?,
+                        id: operand(ops, 1, opcode)?,
+                        name,
+                        interface: ops[(2 + len).min(ops.len()) ..].to_vec(),
+                    });
+                }
+                OP_EXECUTION_MODE => {
+                    if operand(ops, 1, opcode)? == EXECUTION_MODE_LOCAL_SIZE {
+                        let size = [
+                            operand(ops, 2, opcode)?,
+                            operand(ops, 3, opcode)?,
+                            operand(ops, 4, opcode)?,
+                        ];
+                        module.local_sizes.insert(ops[0], size);
+                    }
+                }
+                OP_DECORATE => {
+                    module.decorations
+                        .entry(operand(ops, 0, opcode)?)
+                        .or_insert_with(Decorations::default)
+                        .apply(operand(ops, 1, opcode)?, ops.get(2).cloned());
+                }
+                OP_MEMBER_DECORATE => {
+                    let key = (operand(ops, 0, opcode)?, operand(ops, 1, opcode)?);
+                    module.member_decorations
+                        .entry(key)
+                        .or_insert_with(Decorations::default)
+                        .apply(operand(ops, 2, opcode)?, ops.get(3).cloned());
+                }
+                OP_TYPE_BOOL | OP_TYPE_INT | OP_TYPE_FLOAT | OP_TYPE_VECTOR | OP_TYPE_MATRIX |
+                OP_TYPE_IMAGE | OP_TYPE_SAMPLER | OP_TYPE_SAMPLED_IMAGE | OP_TYPE_ARRAY |
+                OP_TYPE_RUNTIME_ARRAY | OP_TYPE_STRUCT | OP_TYPE_POINTER => {
+                    let id = operand(ops, 0, opcode)?;
+                    let ty = module.parse_type(opcode, ops)?;
+                    module.types.insert(id, ty);
+                }
+                OP_CONSTANT_TRUE | OP_CONSTANT_FALSE | OP_CONSTANT |
+                OP_SPEC_CONSTANT_TRUE | OP_SPEC_CONSTANT_FALSE | OP_SPEC_CONSTANT => {
+                    let ty = operand(ops, 0, opcode)?;
+                    let id = operand(ops, 1, opcode)?;
+                    let value = match opcode {
+                        OP_CONSTANT_TRUE | OP_SPEC_CONSTANT_TRUE => vec![1],
+                        OP_CONSTANT_FALSE | OP_SPEC_CONSTANT_FALSE => vec![0],
+                        _ => ops[2 ..].to_vec(),
+                    };
+                    module.constants.insert(id, (ty, value));
+                    if opcode >= OP_SPEC_CONSTANT_TRUE {
+                        module.spec_constants.push(id);
+                    }
+                }
+                OP_CONSTANT_COMPOSITE | OP_SPEC_CONSTANT_COMPOSITE => {
+                    let id = operand(ops, 1, opcode)?;
+                    module.composites.insert(id, ops[2 ..].to_vec());
+                }
+                OP_VARIABLE => {
+                    let ty = operand(ops, 0, opcode)?;
+                    let id = operand(ops, 1, opcode)?;
+                    let class = operand(ops, 2, opcode)?;
+                    module.variables.push((id, ty, class));
+                }
+                _ => {}
+            }
+        }
+
+        Ok(module)
+    }
+
+    fn parse_type(&self, opcode: u16, ops: &[u32]) -> Result<Type, ReflectError> {
+        // Composite types may only reference previously declared types,
+        // which also rules out cycles when walking them later on.
+        let defined = |index: usize| -> Result<u32, ReflectError> {
+            let id = operand(ops, index, opcode)?;
+            if self.types.contains_key(&id) {
+                Ok(id)
+            } else {
+                Err(ReflectError::Malformed(opcode))
+            }
+        };
+
+        Ok(match opcode {
+            OP_TYPE_BOOL => Type::Bool,
+            OP_TYPE_INT => Type::Int {
+                width: operand(ops, 1, opcode)?,
+                signed: operand(ops, 2, opcode)? != 0,
+            },
+            OP_TYPE_FLOAT => Type::Float {
+                width: operand(ops, 1, opcode)?,
+            },
+            OP_TYPE_VECTOR => Type::Vector {
+                component: defined(1)?,
+                count: operand(ops, 2, opcode)?,
+            },
+            OP_TYPE_MATRIX => Type::Matrix {
+                column: defined(1)?,
+                count: operand(ops, 2, opcode)?,
+            },
+            OP_TYPE_IMAGE => Type::Image {
+                dim: operand(ops, 2, opcode)?,
+                sampled: operand(ops, 6, opcode)?,
+            },
+            OP_TYPE_SAMPLER => Type::Sampler,
+            OP_TYPE_SAMPLED_IMAGE => Type::SampledImage,
+            OP_TYPE_ARRAY => Type::Array {
+                element: defined(1)?,
+                length: operand(ops, 2, opcode)?,
+            },
+            OP_TYPE_RUNTIME_ARRAY => Type::RuntimeArray {
+                element: defined(1)?,
+            },
+            OP_TYPE_STRUCT => Type::Struct {
+                members: (1 .. ops.len()).map(defined).collect::<Result<_, _>>()?,
+            },
+            OP_TYPE_POINTER => Type::Pointer {
+                pointee: operand(ops, 2, opcode)?,
+            },
+            _ => unreachable!(),
+        })
+    }
+
+    fn name(&self, id: u32) -> Option<String> {
+        self.names
+            .get(&id)
+            .filter(|name| !name.is_empty())
+            .cloned()
+    }
+
+    fn decorations(&self, id: u32) -> Decorations {
+        self.decorations.get(&id).cloned().unwrap_or_default()
+    }
+
+    fn constant_u32(&self, id: u32) -> Option<u32> {
+        self.constants
+            .get(&id)
+            .and_then(|&(_, ref value)| value.first().cloned())
+    }
+
+    /// Strip pointer and array levels from a variable type, returning
+    /// the element type and total number of elements.
+    ///
+    /// Returns `None` for cyclic types or element counts overflowing `usize`.
+    fn element_type(&self, mut id: u32) -> Option<(u32, usize)> {
+        let mut count: usize = 1;
+        // Every level is a distinct type, so a longer chain must be a cycle.
+        for _ in 0 ..= self.types.len() {
+            match *self.types.get(&id)? {
+                Type::Pointer { pointee, .. } => id = pointee,
+                Type::Array { element, length } => {
+                    count = count.checked_mul(self.constant_u32(length)? as usize)?;
+                    id = element;
+                }
+                Type::RuntimeArray { element } => {
+                    count = 0;
+                    id = element;
+                }
+                _ => return Some((id, count)),
+            }
+        }
+        None
+    }
+
+    /// Size of a type in bytes, following explicit layout decorations.
+    fn type_size(&self, id: u32, matrix_stride: Option<u32>) -> Option<u32> {
+        Some(match *self.types.get(&id)? {
+            Type::Bool => 4,
+            Type::Int { width, .. } | Type::Float { width } => width / 8,
+            Type::Vector { component, count } => count * self.type_size(component, None)?,
+            Type::Matrix { column, count } => match matrix_stride {
+                Some(stride) => count * stride,
+                None => count * self.type_size(column, None)?,
+            },
+            Type::Array { element, length } => {
+                let stride = match self.decorations(id).array_stride {
+                    Some(stride) => stride,
+                    None => self.type_size(element, matrix_stride)?,
+                };
+                self.constant_u32(length)? * stride
+            }
+            Type::RuntimeArray { .. } => 0,
+            Type::Struct { ref members } => {
+                let mut size = 0;
+                for (i, &member) in members.iter().enumerate() {
+                    let decorations = self.member_decorations
+                        .get(&(id, i as u32))
+                        .cloned()
+                        .unwrap_or_default();
+                    let offset = decorations.offset.unwrap_or(size);
+                    size = size.max(offset + self.type_size(member, decorations.matrix_stride)?);
+                }
+                size
+            }
+            Type::Pointer { .. } => 8,
+            Type::Image { .. } | Type::Sampler | Type::SampledImage => return None,
+        })
+    }
+
+    fn format(&self, id: u32) -> Option<Format> {
+        let (scalar, count) = match *self.types.get(&id)? {
+            Type::Vector { component, count } => (self.types.get(&component)?, count),
+            ref ty => (ty, 1),
+        };
+        let formats = match *scalar {
+            Type::Float { width: 16 } =>
+                [Format::R16Float, Format::Rg16Float, Format::Rgb16Float, Format::Rgba16Float],
+            Type::Float { width: 32 } =>
+                [Format::R32Float, Format::Rg32Float, Format::Rgb32Float, Format::Rgba32Float],
+            Type::Float { width: 64 } =>
+                [Format::R64Float, Format::Rg64Float, Format::Rgb64Float, Format::Rgba64Float],
+            Type::Int { width: 16, signed: true } =>
+                [Format::R16Int, Format::Rg16Int, Format::Rgb16Int, Format::Rgba16Int],
+            Type::Int { width: 32, signed: true } =>
+                [Format::R32Int, Format::Rg32Int, Format::Rgb32Int, Format::Rgba32Int],
+            Type::Int { width: 64, signed: true } =>
+                [Format::R64Int, Format::Rg64Int, Format::Rgb64Int, Format::Rgba64Int],
+            Type::Int { width: 16, signed: false } =>
+                [Format::R16Uint, Format::Rg16Uint, Format::Rgb16Uint, Format::Rgba16Uint],
+            Type::Int { width: 32, signed: false } =>
+                [Format::R32Uint, Format::Rg32Uint, Format::Rgb32Uint, Format::Rgba32Uint],
+            Type::Int { width: 64, signed: false } =>
+                [Format::R64Uint, Format::Rg64Uint, Format::Rgb64Uint, Format::Rgba64Uint],
+            _ => return None,
+        };
+        formats.get(count.checked_sub(1)? as usize).cloned()
+    }
+
+    fn descriptor_type(&self, id: u32, class: u32) -> Option<DescriptorType> {
+        let ty = self.types.get(&id)?;
+        Some(match (class, ty) {
+            (STORAGE_CLASS_UNIFORM_CONSTANT, &Type::Sampler) => DescriptorType::Sampler,
+            (STORAGE_CLASS_UNIFORM_CONSTANT, &Type::SampledImage) => DescriptorType::CombinedImageSampler,
+            (STORAGE_CLASS_UNIFORM_CONSTANT, &Type::Image { dim, sampled }) => match (dim, sampled) {
+                (DIM_BUFFER, 2) => DescriptorType::StorageTexelBuffer,
+                (DIM_BUFFER, _) => DescriptorType::UniformTexelBuffer,
+                (DIM_SUBPASS_DATA, _) => DescriptorType::InputAttachment,
+                (_, 2) => DescriptorType::StorageImage,
+                (_, _) => DescriptorType::SampledImage,
+            },
+            (STORAGE_CLASS_UNIFORM, &Type::Struct { .. }) => if self.decorations(id).buffer_block {
+                DescriptorType::StorageBuffer
+            } else {
+                DescriptorType::UniformBuffer
+            },
+            (STORAGE_CLASS_STORAGE_BUFFER, &Type::Struct { .. }) => DescriptorType::StorageBuffer,
+            _ => return None,
+        })
+    }
+
+    fn interface_variables(&self, ids: &[u32], class: u32) -> Vec<InterfaceVariable> {
+        let mut variables = Vec::new();
+        for &(id, ty, var_class) in &self.variables {
+            if var_class != class || !ids.contains(&id) {
+                continue;
+            }
+            let decorations = self.decorations(id);
+            let location = match (decorations.location, decorations.built_in) {
+                (Some(location), None) => location,
+                _ => continue,
+            };
+            let element = match self.types.get(&ty) {
+                Some(&Type::Pointer { pointee, .. }) => match self.strip_arrays(pointee) {
+                    Some(element) => element,
+                    None => continue,
+                },
+                _ => continue,
+            };
+            let (column, columns) = match self.types.get(&element) {
+                Some(&Type::Matrix { column, count }) => (column, count),
+                _ => (element, 1),
+            };
+            for i in 0 .. columns {
+                variables.push(InterfaceVariable {
+                    location: location + i,
+                    name: self.name(id),
+                    format: self.format(column),
+                });
+            }
+        }
+        variables.sort_by_key(|var| var.location);
+        variables
+    }
+
+    /// Strip array levels from a type, returning `None` for cyclic types.
+    fn strip_arrays(&self, mut id: u32) -> Option<u32> {
+        for _ in 0 ..= self.types.len() {
+            match self.types.get(&id) {
+                Some(&Type::Array { element, .. }) |
+                Some(&Type::RuntimeArray { element }) => id = element,
+                _ => return Some(id),
+            }
+        }
+        None
+    }
+
+    fn specialization_constant(&self, id: u32) -> Option<SpecializationConstant> {
+        let spec_id = self.decorations(id).spec_id?;
+        let &(ty, ref value) = self.constants.get(&id)?;
+        let low = *value.first()?;
+        let wide = || value.get(1).map(|&high| (high as u64) << 32 | low as u64);
+        let default = match *self.types.get(&ty)? {
+            Type::Bool => Constant::Bool(low != 0),
+            Type::Int { width: 64, signed: true } => Constant::I64(wide()? as i64),
+            Type::Int { width: 64, signed: false } => Constant::U64(wide()?),
+            Type::Int { signed: true, .. } => Constant::I32(low as i32),
+            Type::Int { signed: false, .. } => Constant::U32(low),
+            Type::Float { width: 32 } => Constant::F32(f32::from_bits(low)),
+            Type::Float { width: 64 } => Constant::F64(f64::from_bits(wide()?)),
+            _ => return None,
+        };
+        Some(SpecializationConstant {
+            id: spec_id,
+            name: self.name(id),
+            default,
+        })
+    }
+
+    fn interface(&self) -> ShaderInterface {
+        // A constant decorated as `WorkgroupSize` overrides the `LocalSize` execution mode.
+        let workgroup_size = self.composites
+            .iter()
+            .find(|&(&id, _)| self.decorations(id).built_in == Some(BUILT_IN_WORKGROUP_SIZE))
+            .and_then(|(_, constituents)| {
+                let size = constituents
+                    .iter()
+                    .map(|&c| self.constant_u32(c))
+                    .collect::<Option<Vec<_>>>()?;
+                if size.len() == 3 {
+                    Some([size[0], size[1], size[2]])
+                } else {
+                    None
+                }
+            });
+
+        let entry_points = self.entry_points
+            .iter()
+            .filter_map(|ep| {
+                let stage = match ep.model {
+                    0 => Stage::Vertex,
+                    1 => Stage::Hull,
+                    2 => Stage::Domain,
+                    3 => Stage::Geometry,
+                    4 => Stage::Fragment,
+                    5 => Stage::Compute,
+                    _ => return None,
+                };
+                let local_size = match stage {
+                    Stage::Compute => workgroup_size.or_else(|| self.local_sizes.get(&ep.id).cloned()),
+                    _ => None,
+                };
+                Some(EntryPointInterface {
+                    name: ep.name.clone(),
+                    stage,
+                    inputs: self.interface_variables(&ep.interface, STORAGE_CLASS_INPUT),
+                    outputs: self.interface_variables(&ep.interface, STORAGE_CLASS_OUTPUT),
+                    local_size,
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let stage_flags = entry_points
+            .iter()
+            .fold(ShaderStageFlags::empty(), |flags, ep| flags | ep.stage.into());
+
+        let mut descriptors = Vec::new();
+        let mut push_constants = None;
+        for &(id, ty, class) in &self.variables {
+            match class {
+                STORAGE_CLASS_UNIFORM_CONSTANT |
+                STORAGE_CLASS_UNIFORM |
+                STORAGE_CLASS_STORAGE_BUFFER => {
+                    let (element, count) = match self.element_type(ty) {
+                        Some(element) => element,
+                        None => continue,
+                    };
+                    let ty = match self.descriptor_type(element, class) {
+                        Some(ty) => ty,
+                        None => continue,
+                    };
+                    let decorations = self.decorations(id);
+                    descriptors.push(DescriptorInterface {
+                        set: decorations.set.unwrap_or(0) as DescriptorSetIndex,
+                        binding: decorations.binding.unwrap_or(0),
+                        ty,
+                        count,
+                        stage_flags,
+                        name: self.name(id).or_else(|| self.name(element)),
+                    });
+                }
+                STORAGE_CLASS_PUSH_CONSTANT => {
+                    let block = match self.types.get(&ty) {
+                        Some(&Type::Pointer { pointee, .. }) => pointee,
+                        _ => continue,
+                    };
+                    let start = match self.types.get(&block) {
+                        Some(&Type::Struct { ref members }) => (0 .. members.len() as u32)
+                            .filter_map(|i| self.member_decorations.get(&(block, i)))
+                            .filter_map(|decorations| decorations.offset)
+                            .min()
+                            .unwrap_or(0),
+                        _ => continue,
+                    };
+                    if let Some(end) = self.type_size(block, None) {
+                        push_constants = Some((stage_flags, start / 4 .. (end + 3) / 4));
+                    }
+                }
+                _ => {}
+            }
+        }
+        descriptors.sort_by_key(|desc| (desc.set, desc.binding));
+
+        let specialization_constants = self.spec_constants
+            .iter()
+            .filter_map(|&id| self.specialization_constant(id))
+            .collect();
+
+        ShaderInterface {
+            entry_points,
+            descriptors,
+            push_constants,
+            specialization_constants,
+        }
+    }
+}
+
+/// Reflect the interface of a SPIR-V module.
+///
+/// Only the declarations of the module are inspected: descriptors and push constants
+/// are reported for all entry points of the module, regardless of whether they are
+/// statically used by a particular entry point.
+pub fn reflect(spirv: &[u8]) -> Result<ShaderInterface, ReflectError> {
+    if spirv.len() % 4 != 0 || spirv.len() < HEADER_LENGTH * 4 {
+        return Err(ReflectError::Truncated);
+    }
+    let little_endian = spirv
+        .chunks(4)
+        .map(|c| c[0] as u32 | (c[1] as u32) << 8 | (c[2] as u32) << 16 | (c[3] as u32) << 24)
+        .collect::<Vec<_>>();
+    let words = if little_endian[0] == MAGIC_NUMBER {
+        little_endian
+    } else if little_endian[0].swap_bytes() == MAGIC_NUMBER {
+        little_endian.into_iter().map(u32::swap_bytes).collect()
+    } else {
+        return Err(ReflectError::InvalidMagic);
+    };
+
+    Module::parse(&words).map(|module| module.interface())
+}
+
+/// An error deriving a pipeline layout from shaders.
+#[derive(Fail, Clone, Debug, PartialEq, Eq)]
+pub enum LayoutError {
+    /// A shader module couldn't be reflected.
+    #[fail(display = "Shader reflection failed: {}", _0)]
+    Reflect(#[cause] ReflectError),
+    /// The requested entry point doesn't exist in the module.
+    #[fail(display = "Missing entry point `{}`.", _0)]
+    MissingEntryPoint(String),
+    /// Stages declare different descriptor types or counts for the same
+    /// set and binding.
+    #[fail(display = "Conflicting declarations of set {} binding {}.", _0, _1)]
+    ConflictingBinding(DescriptorSetIndex, DescriptorBinding),
+}
+
+impl From<ReflectError> for LayoutError {
+    fn from(error: ReflectError) -> Self {
+        LayoutError::Reflect(error)
+    }
+}
+
+/// Descriptor set layouts and push constant ranges required by a set of shader entry points,
+/// with stage flags merged across all stages.
+///
+/// Shader modules are opaque once created, so the layout is derived from the SPIR-V
+/// the modules of a `GraphicsShaderSet` or compute `EntryPoint` are created from.
+///
+/// *Note*: Runtime sized descriptor arrays are reported with a count of zero and
+/// need to be given an upper bound before creating the layout.
+#[derive(Clone, Debug, Default)]
+pub struct PipelineLayoutDesc {
+    /// Bindings of each descriptor set, indexed by set.
+    ///
+    /// Sets in between which aren't used by any shader are empty.
+    pub sets: Vec<Vec<DescriptorSetLayoutBinding>>,
+    /// Push constant ranges in units of `u32`, as expected by `Device::create_pipeline_layout`.
+    pub push_constants: Vec<(ShaderStageFlags, Range<u32>)>,
+}
+
+impl PipelineLayoutDesc {
+    /// Create an empty layout description.
+    pub fn new() -> Self {
+        PipelineLayoutDesc::default()
+    }
+
+    /// Derive the layout used by the given SPIR-V modules and entry point names.
+    pub fn from_spirv<'a, I>(shaders: I) -> Result<Self, LayoutError>
+    where
+        I: IntoIterator<Item = (&'a [u8], &'a str)>,
+    {
+        let mut desc = PipelineLayoutDesc::new();
+        for (spirv, entry) in shaders {
+            desc.add_entry_point(&reflect(spirv)?, entry)?;
+        }
+        Ok(desc)
+    }
+
+    /// Merge the resources of an entry point of a reflected module into the layout.
+    ///
+    /// Descriptors and push constants declared in the module are assigned to the
+    /// stage of the entry point.
+    pub fn add_entry_point(
+        &mut self, interface: &ShaderInterface, entry: &str
+    ) -> Result<(), LayoutError> {
+        let stage = match interface.entry_point(entry) {
+            Some(ep) => ShaderStageFlags::from(ep.stage),
+            None => return Err(LayoutError::MissingEntryPoint(entry.to_string())),
+        };
+
+        for desc in &interface.descriptors {
+            let set = desc.set as usize;
+            if self.sets.len() <= set {
+                self.sets.resize(set + 1, Vec::new());
+            }
+            let bindings = &mut self.sets[set];
+            if let Some(binding) = bindings.iter_mut().find(|b| b.binding == desc.binding) {
+                if binding.ty != desc.ty || binding.count != desc.count {
+                    return Err(LayoutError::ConflictingBinding(desc.set, desc.binding));
+                }
+                binding.stage_flags |= stage;
+                continue;
+            }
+            bindings.push(DescriptorSetLayoutBinding {
+                binding: desc.binding,
+                ty: desc.ty,
+                count: desc.count,
+                stage_flags: stage,
+                immutable_samplers: false,
+            });
+            bindings.sort_by_key(|b| b.binding);
+        }
+
+        if let Some((_, ref range)) = interface.push_constants {
+            match self.push_constants.iter_mut().find(|&&mut (_, ref r)| r == range) {
+                Some(&mut (ref mut flags, _)) => *flags |= stage,
+                None => self.push_constants.push((stage, range.clone())),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Create the descriptor set layouts and the pipeline layout referencing them.
+    pub fn create<B: Backend, D: Device<B>>(
+        &self, device: &D
+    ) -> (Vec<B::DescriptorSetLayout>, B::PipelineLayout) {
+        let set_layouts = self.sets
+            .iter()
+            .map(|bindings| device.create_descriptor_set_layout(bindings, iter::empty::<B::Sampler>()))
+            .collect::<Vec<_>>();
+        let layout = device.create_pipeline_layout(&set_layouts, &self.push_constants);
+        (set_layouts, layout)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reflect_vertex() {
+        let spirv = include_bytes!("../../tests/data/vert.spv");
+        let interface = reflect(spirv).unwrap();
+
+        let ep = interface.entry_point("main").unwrap();
+        assert_eq!(ep.stage, Stage::Vertex);
+        let inputs = ep.inputs.iter().map(|var| (var.location, var.format)).collect::<Vec<_>>();
+        assert_eq!(inputs, vec![(0, Some(Format::Rgba32Float)), (1, Some(Format::Rg32Float))]);
+        assert_eq!(ep.outputs.len(), 1);
+        assert_eq!(ep.outputs[0].format, Some(Format::Rg32Float));
+
+        let locals = interface.descriptor(0, 0).unwrap();
+        assert_eq!(locals.ty, DescriptorType::UniformBuffer);
+        assert_eq!(locals.count, 1);
+        assert_eq!(locals.stage_flags, ShaderStageFlags::VERTEX);
+    }
+
+    #[test]
+    fn test_reflect_fragment() {
+        let spirv = include_bytes!("../../tests/data/frag.spv");
+        let interface = reflect(spirv).unwrap();
+
+        let ep = interface.entry_point("main").unwrap();
+        assert_eq!(ep.stage, Stage::Fragment);
+        assert_eq!(ep.outputs[0].location, 0);
+        assert_eq!(ep.outputs[0].format, Some(Format::Rgba32Float));
+
+        let types = interface.descriptors.iter().map(|desc| (desc.binding, desc.ty)).collect::<Vec<_>>();
+        assert_eq!(types, vec![(1, DescriptorType::SampledImage), (2, DescriptorType::Sampler)]);
+        assert!(interface.push_constants.is_none());
+    }
+
+    #[test]
+    fn test_layout_from_spirv() {
+        let vs = include_bytes!("../../tests/data/vert.spv");
+        let fs = include_bytes!("../../tests/data/frag.spv");
+        let desc = PipelineLayoutDesc::from_spirv(vec![(&vs[..], "main"), (&fs[..], "main")]).unwrap();
+
+        assert_eq!(desc.sets.len(), 1);
+        let bindings = desc.sets[0]
+            .iter()
+            .map(|b| (b.binding, b.ty, b.stage_flags))
+            .collect::<Vec<_>>();
+        assert_eq!(bindings, vec![
+            (0, DescriptorType::UniformBuffer, ShaderStageFlags::VERTEX),
+            (1, DescriptorType::SampledImage, ShaderStageFlags::FRAGMENT),
+            (2, DescriptorType::Sampler, ShaderStageFlags::FRAGMENT),
+        ]);
+        assert!(desc.push_constants.is_empty());
+
+        assert_eq!(
+            PipelineLayoutDesc::from_spirv(vec![(&vs[..], "foo")]).unwrap_err(),
+            LayoutError::MissingEntryPoint("foo".to_string()),
+        );
+    }
+
+    #[test]
+    fn test_reflect_invalid() {
+        assert_eq!(reflect(&[0; 20]).unwrap_err(), ReflectError::InvalidMagic);
+        assert_eq!(reflect(&[3, 2, 0x23, 7]).unwrap_err(), ReflectError::Truncated);
+    }
+
+    #[test]
+    fn test_cyclic_types() {
+        let mut module = Module::default();
+        module.types.insert(1, Type::Array { element: 2, length: 3 });
+        module.types.insert(2, Type::RuntimeArray { element: 1 });
+        module.types.insert(3, Type::Pointer { pointee: 1 });
+        assert_eq!(module.strip_arrays(1), None);
+        assert_eq!(module.element_type(3), None);
+    }
+
+    #[test]
+    fn test_element_count_overflow() {
+        let mut module = Module::default();
+        module.constants.insert(1, (0, vec![!0]));
+        module.types.insert(2, Type::Sampler);
+        module.types.insert(3, Type::Array { element: 2, length: 1 });
+        module.types.insert(4, Type::Array { element: 3, length: 1 });
+        module.types.insert(5, Type::Array { element: 4, length: 1 });
+        assert_eq!(module.element_type(3), Some((2, !0u32 as usize)));
+        assert_eq!(module.element_type(5), None);
+    }
+}
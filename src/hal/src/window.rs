@@ -79,6 +79,78 @@ impl From<image::Extent> for Extent2D {
     }
 }
 
+impl Extent2D {
+    /// Convert into a regular image extent, with a depth of 1.
+    pub fn to_extent(&self) -> image::Extent {
+        image::Extent {
+            width: self.width,
+            height: self.height,
+            depth: 1,
+        }
+    }
+}
+
+/// Color space in which the presentation engine interprets the values
+/// of the backbuffer images.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ColorSpace {
+    /// sRGB primaries with the sRGB transfer function.
+    SrgbNonlinear,
+    /// sRGB primaries with linear encoding, values may exceed `[0, 1]`.
+    ///
+    /// Usually paired with `Rgba16Float` (scRGB).
+    ExtendedSrgbLinear,
+    /// sRGB primaries with the sRGB transfer function, values may exceed `[0, 1]`.
+    ExtendedSrgbNonlinear,
+    /// Display-P3 primaries with the sRGB transfer function.
+    DisplayP3Nonlinear,
+    /// Display-P3 primaries with linear encoding.
+    DisplayP3Linear,
+    /// DCI-P3 primaries with the DCI-P3 transfer function.
+    DciP3Nonlinear,
+    /// BT.709 primaries with linear encoding.
+    Bt709Linear,
+    /// BT.709 primaries with the BT.709 transfer function.
+    Bt709Nonlinear,
+    /// BT.2020 primaries with linear encoding.
+    Bt2020Linear,
+    /// BT.2020 primaries with the SMPTE ST 2084 (PQ) transfer function.
+    ///
+    /// Usually paired with `A2b10g10r10Unorm`.
+    Hdr10St2084,
+    /// BT.2020 primaries with the hybrid log-gamma transfer function.
+    Hdr10Hlg,
+    /// Dolby Vision, BT.2020 primaries with a proprietary transfer function.
+    DolbyVision,
+    /// Adobe RGB primaries with linear encoding.
+    AdobeRgbLinear,
+    /// Adobe RGB primaries with the gamma 2.2 transfer function.
+    AdobeRgbNonlinear,
+    /// Values are passed to the display as-is.
+    PassThrough,
+}
+
+impl ColorSpace {
+    /// Returns if the color space covers a high dynamic range.
+    pub fn is_hdr(&self) -> bool {
+        match *self {
+            ColorSpace::ExtendedSrgbLinear |
+            ColorSpace::ExtendedSrgbNonlinear |
+            ColorSpace::Hdr10St2084 |
+            ColorSpace::Hdr10Hlg |
+            ColorSpace::DolbyVision => true,
+            _ => false,
+        }
+    }
+}
+
+impl Default for ColorSpace {
+    fn default() -> Self {
+        ColorSpace::SrgbNonlinear
+    }
+}
+
 /// Describes information about what a `Surface`'s properties are.
 /// Fetch this with `surface.capabilities_and_formats(device)`.
 #[derive(Debug, Clone)]
@@ -126,10 +198,14 @@ pub trait Surface<B: Backend>: Any + Send + Sync {
     ///
     /// Use this function for configuring swapchain creation.
     ///
-    /// Returns a tuple of surface capabilities and formats.
+    /// Returns a tuple of surface capabilities and formats, each paired with
+    /// a color space it can be presented in. A format may be listed multiple
+    /// times with different color spaces.
     /// If formats is `None` than the surface has no preferred format and the
-    /// application may use any desired format.
-    fn capabilities_and_formats(&self, physical_device: &B::PhysicalDevice) -> (SurfaceCapabilities, Option<Vec<Format>>);
+    /// application may use any desired format in `ColorSpace::SrgbNonlinear`.
+    fn capabilities_and_formats(
+        &self, physical_device: &B::PhysicalDevice,
+    ) -> (SurfaceCapabilities, Option<Vec<(Format, ColorSpace)>>);
 }
 
 /// Handle to a backbuffer of the swapchain.
@@ -196,6 +272,8 @@ pub enum FrameSync<'a, B: Backend> {
 pub struct SwapchainConfig {
     /// Color format of the backbuffer images.
     pub color_format: Format,
+    /// Color space the backbuffer images are presented in.
+    pub color_space: ColorSpace,
     /// Depth stencil format of the backbuffer images (optional).
     pub depth_stencil_format: Option<Format>,
     /// Number of images in the swapchain.
@@ -215,6 +293,7 @@ impl SwapchainConfig {
     pub fn new() -> Self {
         SwapchainConfig {
             color_format: Format::Bgra8Unorm, // TODO: try to find best default format
+            color_space: ColorSpace::SrgbNonlinear,
             depth_stencil_format: None,
             image_count: 2,
             image_usage: image::Usage::empty(),
@@ -233,6 +312,31 @@ impl SwapchainConfig {
         self
     }
 
+    /// Specify the color space for the backbuffer images.
+    ///
+    /// The pair of color format and color space has to be one of the pairs
+    /// reported by `Surface::capabilities_and_formats`, `Device::create_swapchain`
+    /// panics otherwise.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # extern crate gfx_hal;
+    /// # fn main() {
+    /// # use gfx_hal::SwapchainConfig;
+    /// # use gfx_hal::format::Format;
+    /// use gfx_hal::window::ColorSpace;
+    ///
+    /// let config = SwapchainConfig::new()
+    ///     .with_color(Format::A2b10g10r10Unorm)
+    ///     .with_color_space(ColorSpace::Hdr10St2084);
+    /// # }
+    /// ```
+    pub fn with_color_space(mut self, color_space: ColorSpace) -> Self {
+        self.color_space = color_space;
+        self
+    }
+
     /// Specify the depth stencil format for the backbuffer images.
     ///
     /// The Swapchain will create additional depth-stencil images for each backbuffer.
@@ -0,0 +1,8 @@
+#!/bin/sh
+
+echo Compiling...
+glslangValidator -V -o mipmap.vert.spv mipmap.vert
+glslangValidator -V -o mipmap.frag.spv mipmap.frag
+echo Validating...
+spirv-val mipmap.vert.spv
+spirv-val mipmap.frag.spv
@@ -0,0 +1,336 @@
+use std::sync::{mpsc, Arc};
+use std::collections::{BTreeSet, HashMap};
+use std::cmp::max;
+
+use hal::{Device as Device_, Limits, MemoryTypeId};
+use hal::memory::Requirements;
+use memory::{self, Allocator, Memory, ReleaseFn, Provider, Dependency};
+use {buffer, image};
+use {Backend, Device};
+use super::stack::complete_requirements;
+
+/// Size of the smallest block handed out, blocks are aligned to their size.
+const MIN_BLOCK_SIZE: u64 = 256;
+
+fn block_size(order: usize) -> u64 {
+    MIN_BLOCK_SIZE << order
+}
+
+/// Smallest order of a block holding `size` bytes.
+fn order_of(size: u64) -> usize {
+    let blocks = (size + MIN_BLOCK_SIZE - 1) / MIN_BLOCK_SIZE;
+    max(blocks, 1).next_power_of_two().trailing_zeros() as usize
+}
+
+/// General-purpose allocator splitting memory chunks into power-of-two blocks.
+///
+/// Unlike `StackAllocator`, memory can be released in any order and
+/// the freed blocks are merged back with their buddies.
+/// Chunks are kept per memory type. Buffers and images are placed in separate
+/// chunks when the `buffer_image_granularity` of the device exceeds
+/// the minimal block size.
+pub struct BuddyAllocator<B: Backend>(Provider<InnerBuddyAllocator<B>>);
+
+pub struct InnerBuddyAllocator<B: Backend> {
+    device: Arc<B::Device>,
+    usage: memory::Usage,
+    limits: Limits, // TODO: only store relevant data
+    // heaps by memory type and linear tiling
+    heaps: HashMap<(MemoryTypeId, bool), Heap<B>>,
+    chunk_size: u64,
+    separate_tiling: bool,
+}
+
+impl<B: Backend> Drop for InnerBuddyAllocator<B> {
+    fn drop(&mut self) {
+        self.shrink();
+    }
+}
+
+impl<B: Backend> BuddyAllocator<B> {
+    pub fn new(usage: memory::Usage, device: &Device<B>, limits: Limits) -> Self {
+        let mega = 1 << 20;
+        Self::with_chunk_size(usage, device, limits, 64 * mega)
+    }
+
+    /// The chunk size is rounded up to a power of two.
+    /// Larger resources get a chunk of their own.
+    pub fn with_chunk_size(
+        usage: memory::Usage,
+        device: &Device<B>,
+        limits: Limits,
+        chunk_size: u64
+    ) -> Self {
+        BuddyAllocator(Provider::new(InnerBuddyAllocator {
+            device: Arc::clone(&device.raw),
+            usage,
+            separate_tiling: limits.buffer_image_granularity > MIN_BLOCK_SIZE,
+            limits,
+            heaps: HashMap::new(),
+            chunk_size,
+        }))
+    }
+
+    /// Free the chunks without any allocated block.
+    pub fn shrink(&mut self) {
+        self.0.shrink();
+    }
+}
+
+impl<B: Backend> InnerBuddyAllocator<B> {
+    fn shrink(&mut self) {
+        for (_, heap) in &mut self.heaps {
+            heap.shrink(&self.device);
+        }
+    }
+
+    fn heap(&mut self, memory_type: MemoryTypeId, linear: bool) -> &mut Heap<B> {
+        let key = (memory_type, linear && self.separate_tiling);
+        self.heaps.entry(key)
+            .or_insert_with(|| Heap::new(memory_type))
+    }
+}
+
+impl<B: Backend> Allocator<B> for BuddyAllocator<B> {
+    fn allocate_buffer(&mut self,
+        device: &Device<B>,
+        usage: buffer::Usage,
+        buffer: B::UnboundBuffer
+    ) -> (B::Buffer, Memory) {
+        let dependency = self.0.dependency();
+        let inner: &mut InnerBuddyAllocator<B> = &mut self.0;
+        let requirements = complete_requirements::<B>(
+            &device.raw,
+            &buffer,
+            &inner.limits,
+            usage,
+        );
+        let memory_type = device.find_usage_memory(inner.usage, requirements.type_mask)
+            .expect("could not find suitable memory");
+        let chunk_size = inner.chunk_size;
+        let (memory, offset, release) = inner.heap(memory_type, true).allocate(
+            device,
+            chunk_size,
+            requirements,
+            dependency,
+        );
+        let buffer = device.raw
+            .bind_buffer_memory(memory, offset, buffer)
+            .unwrap();
+        (buffer, Memory::new(release, inner.usage))
+    }
+
+    fn allocate_image(&mut self,
+        device: &mut Device<B>,
+        _: image::Usage,
+        image: B::UnboundImage
+    ) -> (B::Image, Memory) {
+        let dependency = self.0.dependency();
+        let inner: &mut InnerBuddyAllocator<B> = &mut self.0;
+        let requirements = device.raw.get_image_requirements(&image);
+        let memory_type = device.find_usage_memory(inner.usage, requirements.type_mask)
+            .expect("could not find suitable memory");
+        let chunk_size = inner.chunk_size;
+        // images are always created with optimal tiling
+        let (memory, offset, release) = inner.heap(memory_type, false).allocate(
+            device,
+            chunk_size,
+            requirements,
+            dependency,
+        );
+        let image = device.raw
+            .bind_image_memory(memory, offset, image)
+            .unwrap();
+        (image, Memory::new(release, inner.usage))
+    }
+}
+
+/// Free blocks of a single chunk.
+#[derive(Debug)]
+struct Buddy {
+    // free block offsets by order
+    free: Vec<BTreeSet<u64>>,
+}
+
+impl Buddy {
+    fn new(max_order: usize) -> Self {
+        let mut free = vec![BTreeSet::new(); max_order + 1];
+        free[max_order].insert(0);
+        Buddy { free }
+    }
+
+    fn max_order(&self) -> usize {
+        self.free.len() - 1
+    }
+
+    fn is_unused(&self) -> bool {
+        self.free[self.max_order()].contains(&0)
+    }
+
+    fn allocate(&mut self, order: usize) -> Option<u64> {
+        if order > self.max_order() {
+            return None;
+        }
+        let found = match (order .. self.free.len()).find(|&o| !self.free[o].is_empty()) {
+            Some(found) => found,
+            None => return None,
+        };
+        let offset = *self.free[found].iter().next().unwrap();
+        self.free[found].remove(&offset);
+        // split, keeping the lower halves
+        for o in order .. found {
+            self.free[o].insert(offset + block_size(o));
+        }
+        Some(offset)
+    }
+
+    fn release(&mut self, mut offset: u64, mut order: usize) {
+        while order < self.max_order() {
+            let buddy = offset ^ block_size(order);
+            if !self.free[order].remove(&buddy) {
+                break;
+            }
+            offset = offset & !block_size(order);
+            order += 1;
+        }
+        self.free[order].insert(offset);
+    }
+}
+
+struct Chunk<B: Backend> {
+    memory: B::Memory,
+    buddy: Buddy,
+}
+
+struct BuddyAlloc {
+    chunk_index: usize,
+    offset: u64,
+    order: usize,
+}
+
+struct Heap<B: Backend> {
+    memory_type: MemoryTypeId,
+    chunks: Vec<Option<Chunk<B>>>,
+    receiver: mpsc::Receiver<BuddyAlloc>,
+    sender: mpsc::Sender<BuddyAlloc>,
+}
+
+impl<B: Backend> Heap<B> {
+    fn new(memory_type: MemoryTypeId) -> Self {
+        let (sender, receiver) = mpsc::channel();
+
+        Heap {
+            memory_type,
+            chunks: Vec::new(),
+            receiver,
+            sender,
+        }
+    }
+
+    fn allocate(&mut self,
+        device: &Device<B>,
+        chunk_size: u64,
+        req: Requirements,
+        dependency: Dependency<InnerBuddyAllocator<B>>,
+    ) -> (&B::Memory, u64, ReleaseFn)
+    {
+        self.update_allocs();
+        let order = order_of(max(req.size, req.alignment));
+
+        let found = self.chunks.iter_mut()
+            .enumerate()
+            .filter_map(|(i, c)| c.as_mut().map(|c| (i, c)))
+            .filter_map(|(i, c)| c.buddy.allocate(order).map(|offset| (i, offset)))
+            .next();
+        let (chunk_index, offset) = match found {
+            Some(found) => found,
+            None => self.grow(device, max(order_of(chunk_size), order), order),
+        };
+
+        let sender = self.sender.clone();
+        let alloc = BuddyAlloc { chunk_index, offset, order };
+        let mut alloc = Some(alloc);
+        (&self.chunks[chunk_index].as_ref().unwrap().memory, offset, Box::new(move || {
+            let _ = dependency;
+            if let Some(alloc) = alloc.take() {
+                sender.send(alloc).unwrap_or_else(|_| {
+                    error!("could not release BuddyAllocator's memory")
+                });
+            }
+        }))
+    }
+
+    fn grow(&mut self,
+        device: &Device<B>,
+        max_order: usize,
+        order: usize,
+    ) -> (usize, u64) {
+        let memory = device.raw
+            .allocate_memory(self.memory_type, block_size(max_order))
+            .unwrap();
+        let mut buddy = Buddy::new(max_order);
+        let offset = buddy.allocate(order).unwrap();
+        let chunk = Some(Chunk { memory, buddy });
+
+        match self.chunks.iter().position(|c| c.is_none()) {
+            Some(index) => {
+                self.chunks[index] = chunk;
+                (index, offset)
+            }
+            None => {
+                self.chunks.push(chunk);
+                (self.chunks.len() - 1, offset)
+            }
+        }
+    }
+
+    fn shrink(&mut self, device: &B::Device) {
+        self.update_allocs();
+
+        for slot in &mut self.chunks {
+            if slot.as_ref().map(|c| c.buddy.is_unused()).unwrap_or(false) {
+                device.free_memory(slot.take().unwrap().memory);
+            }
+        }
+    }
+
+    fn update_allocs(&mut self) {
+        for alloc in self.receiver.try_iter() {
+            self.chunks[alloc.chunk_index].as_mut()
+                .expect("released memory of a freed chunk")
+                .buddy.release(alloc.offset, alloc.order);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{block_size, order_of, Buddy};
+
+    #[test]
+    fn order() {
+        assert_eq!(order_of(0), 0);
+        assert_eq!(order_of(1), 0);
+        assert_eq!(order_of(256), 0);
+        assert_eq!(order_of(257), 1);
+        assert_eq!(order_of(1024), 2);
+    }
+
+    #[test]
+    fn split_and_merge() {
+        let mut buddy = Buddy::new(3);
+        let a = buddy.allocate(0).unwrap();
+        let b = buddy.allocate(1).unwrap();
+        let c = buddy.allocate(0).unwrap();
+        assert_eq!((a, b, c), (0, block_size(1), block_size(0)));
+        assert_eq!(buddy.allocate(3), None);
+
+        // out of order frees
+        buddy.release(a, 0);
+        buddy.release(b, 1);
+        assert!(!buddy.is_unused());
+        buddy.release(c, 0);
+        assert!(buddy.is_unused());
+        assert_eq!(buddy.allocate(3), Some(0));
+    }
+}
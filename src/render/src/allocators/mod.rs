@@ -1,5 +1,7 @@
 mod boxed;
+mod buddy;
 mod stack;
 
 pub use self::boxed::BoxedAllocator;
+pub use self::buddy::BuddyAllocator;
 pub use self::stack::StackAllocator;
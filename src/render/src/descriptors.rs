@@ -0,0 +1,345 @@
+//! Allocation of descriptor sets from a growing list of pools.
+//!
+//! Pools are sized after the descriptors requested so far, and a new pool is
+//! created whenever the existing ones run out of space. Pools can't free sets
+//! individually on every backend, so a pool is reset once all of its sets have
+//! been dropped. Dropped sets might still be used by the device: their pools
+//! are only updated when the `Release` of the frame they were dropped in is
+//! passed back, which `Context` does when the fence of the frame has signaled.
+//! A `Device` used without a `Context` has to do the same with
+//! `DescriptorAllocator::flush` and `DescriptorAllocator::release`.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::{cmp, fmt};
+
+use hal::Device as Device_;
+use hal::pso::{AllocationError, DescriptorPool as DescriptorPool_};
+use hal::pso::{DescriptorRangeDesc, DescriptorSetLayoutBinding, DescriptorType};
+
+use {handle, pso};
+use Backend;
+
+const MIN_POOL_SETS: usize = 16;
+const MAX_POOL_SETS: usize = 1024;
+
+/// Pools of the sets dropped during a frame.
+///
+/// Has to be passed to `DescriptorAllocator::release` once the frame has been
+/// executed.
+#[derive(Debug)]
+#[must_use]
+pub struct Release {
+    pools: Vec<usize>,
+}
+
+/// Pool a descriptor set has been allocated from.
+pub(crate) struct PoolRef<B: Backend> {
+    index: usize,
+    pools: Arc<Mutex<Pools<B>>>,
+}
+
+impl<B: Backend> Drop for PoolRef<B> {
+    fn drop(&mut self) {
+        self.pools.lock().unwrap().dropped.push(self.index);
+    }
+}
+
+impl<B: Backend> fmt::Debug for PoolRef<B> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(f, "PoolRef({})", self.index)
+    }
+}
+
+/// Allocates descriptor sets, growing its list of pools as needed.
+///
+/// Cloning an allocator shares its pools.
+///
+/// Without a `Context`, `flush` and `release` have to be called for every
+/// submitted frame: the dropped sets are kept until then, their pools are
+/// never reset otherwise.
+pub struct DescriptorAllocator<B: Backend>(Arc<Mutex<Pools<B>>>);
+
+impl<B: Backend> Clone for DescriptorAllocator<B> {
+    fn clone(&self) -> Self {
+        DescriptorAllocator(self.0.clone())
+    }
+}
+
+/// Set bookkeeping of a pool.
+#[derive(Debug, PartialEq)]
+struct SetCounts {
+    max_sets: usize,
+    // sets allocated since the last reset
+    allocated: usize,
+    // sets not dropped yet
+    live: usize,
+}
+
+impl SetCounts {
+    fn new(max_sets: usize) -> Self {
+        SetCounts { max_sets, allocated: 0, live: 0 }
+    }
+
+    fn is_full(&self) -> bool {
+        self.allocated == self.max_sets
+    }
+
+    fn allocate(&mut self) {
+        self.allocated += 1;
+        self.live += 1;
+    }
+
+    /// Stop allocating from the pool until it is reset.
+    fn exhaust(&mut self) {
+        self.allocated = self.max_sets;
+    }
+
+    /// Account for a released set, returns if the pool has to be reset.
+    fn release(&mut self) -> bool {
+        self.live -= 1;
+        if self.live == 0 {
+            self.allocated = 0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+struct Pool<B: Backend> {
+    raw: B::DescriptorPool,
+    counts: SetCounts,
+}
+
+/// Number of sets of a new pool following `pools` others, holding at least `count` sets.
+fn pool_sets(pools: usize, count: usize) -> usize {
+    let max_shift = (MAX_POOL_SETS / MIN_POOL_SETS).trailing_zeros() as usize;
+    cmp::max(count, MIN_POOL_SETS << cmp::min(pools, max_shift))
+}
+
+/// Descriptor ranges of a new pool of `max_sets` sets, sized after the `usage`
+/// of the `requested_sets` so far and holding at least `count` sets of `bindings`.
+fn pool_ranges(
+    usage: &HashMap<DescriptorType, usize>,
+    requested_sets: usize,
+    bindings: &[DescriptorSetLayoutBinding],
+    count: usize,
+    max_sets: usize,
+) -> Vec<DescriptorRangeDesc> {
+    usage.iter().map(|(&ty, &used)| {
+        let required = bindings.iter()
+            .filter(|binding| binding.ty == ty)
+            .map(|binding| binding.count * count)
+            .sum();
+        let expected = (used * max_sets + requested_sets - 1) / requested_sets;
+        DescriptorRangeDesc {
+            ty,
+            count: cmp::max(expected, required),
+        }
+    }).collect()
+}
+
+struct Pools<B: Backend> {
+    device: Arc<B::Device>,
+    pools: Vec<Pool<B>>,
+    // descriptors by type and sets requested so far, to size new pools
+    usage: HashMap<DescriptorType, usize>,
+    requested_sets: usize,
+    // pools of the sets dropped since the last flush
+    dropped: Vec<usize>,
+}
+
+impl<B: Backend> Drop for Pools<B> {
+    fn drop(&mut self) {
+        for pool in self.pools.drain(..) {
+            self.device.destroy_descriptor_pool(pool.raw);
+        }
+    }
+}
+
+impl<B: Backend> Pools<B> {
+    fn create_pool(&mut self, bindings: &[DescriptorSetLayoutBinding], count: usize) -> usize {
+        let max_sets = pool_sets(self.pools.len(), count);
+        let ranges = pool_ranges(&self.usage, self.requested_sets, bindings, count, max_sets);
+        let raw = self.device.create_descriptor_pool(max_sets, &ranges[..]);
+        self.pools.push(Pool { raw, counts: SetCounts::new(max_sets) });
+        self.pools.len() - 1
+    }
+
+    fn allocate_set(
+        &mut self,
+        layout: &B::DescriptorSetLayout,
+        bindings: &[DescriptorSetLayoutBinding],
+        remaining: usize,
+    ) -> Option<(usize, B::DescriptorSet)> {
+        let mut index = 0;
+        loop {
+            if index == self.pools.len() {
+                self.create_pool(bindings, remaining);
+            }
+            let pool = &mut self.pools[index];
+            if !pool.counts.is_full() {
+                match pool.raw.allocate_set(layout) {
+                    Ok(set) => {
+                        pool.counts.allocate();
+                        return Some((index, set));
+                    }
+                    Err(AllocationError::OutOfPoolMemory) |
+                    Err(AllocationError::FragmentedPool) => {
+                        // a fresh pool has to satisfy the request
+                        if pool.counts.allocated == 0 {
+                            error!("could not allocate from a new descriptor pool");
+                            return None;
+                        }
+                        pool.counts.exhaust();
+                    }
+                    Err(e) => {
+                        error!("could not allocate descriptor set: {}", e);
+                        return None;
+                    }
+                }
+            }
+            index += 1;
+        }
+    }
+}
+
+impl<B: Backend> DescriptorAllocator<B> {
+    pub(crate) fn new(device: Arc<B::Device>) -> Self {
+        DescriptorAllocator(Arc::new(Mutex::new(Pools {
+            device,
+            pools: Vec::new(),
+            usage: HashMap::new(),
+            requested_sets: 0,
+            dropped: Vec::new(),
+        })))
+    }
+
+    /// Allocate `count` sets of `layout`, described by `bindings`.
+    pub(crate) fn allocate(
+        &self,
+        layout: &handle::raw::DescriptorSetLayout<B>,
+        bindings: &[DescriptorSetLayoutBinding],
+        count: usize,
+    ) -> Vec<pso::RawDescriptorSet<B>> {
+        let mut pools = self.0.lock().unwrap();
+        for binding in bindings {
+            *pools.usage.entry(binding.ty).or_insert(0) += binding.count * count;
+        }
+        pools.requested_sets += count;
+
+        let mut sets = Vec::with_capacity(count);
+        for remaining in (1 .. count + 1).rev() {
+            match pools.allocate_set(layout.resource(), bindings, remaining) {
+                Some((index, resource)) => sets.push(pso::RawDescriptorSet {
+                    resource,
+                    pool: PoolRef { index, pools: self.0.clone() },
+                }),
+                None => break,
+            }
+        }
+        sets
+    }
+
+    /// Take the sets dropped during the current frame.
+    ///
+    /// Has to be called when submitting the frame.
+    pub fn flush(&self) -> Release {
+        let mut pools = self.0.lock().unwrap();
+        Release { pools: pools.dropped.drain(..).collect() }
+    }
+
+    /// Account for the sets dropped during an executed frame,
+    /// resetting the pools without any set left.
+    pub fn release(&self, release: Release) {
+        let mut pools = self.0.lock().unwrap();
+        for index in release.pools {
+            let pool = &mut pools.pools[index];
+            if pool.counts.release() {
+                pool.raw.reset();
+            }
+        }
+    }
+}
+
+impl<B: Backend> fmt::Debug for DescriptorAllocator<B> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(f, "DescriptorAllocator")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use hal::pso::{DescriptorSetLayoutBinding, DescriptorType, ShaderStageFlags};
+    use super::{pool_ranges, pool_sets, SetCounts, MAX_POOL_SETS, MIN_POOL_SETS};
+
+    #[test]
+    fn pool_growth() {
+        assert_eq!(pool_sets(0, 1), MIN_POOL_SETS);
+        assert_eq!(pool_sets(1, 1), MIN_POOL_SETS * 2);
+        assert_eq!(pool_sets(0, 100), 100);
+        assert_eq!(pool_sets(6, 1), MAX_POOL_SETS);
+        // the size stays clamped however many pools were created
+        assert_eq!(pool_sets(64, 1), MAX_POOL_SETS);
+        assert_eq!(pool_sets(1000, 1), MAX_POOL_SETS);
+        assert_eq!(pool_sets(1000, 2000), 2000);
+    }
+
+    #[test]
+    fn pool_ranges_follow_usage() {
+        let binding = |ty, count| DescriptorSetLayoutBinding {
+            binding: 0,
+            ty,
+            count,
+            stage_flags: ShaderStageFlags::FRAGMENT,
+            immutable_samplers: false,
+        };
+        let mut usage = HashMap::new();
+        usage.insert(DescriptorType::SampledImage, 6);
+        usage.insert(DescriptorType::Sampler, 3);
+        // 3 sets requested so far, averaging 2 images and 1 sampler per set
+        let bindings = [binding(DescriptorType::SampledImage, 8), binding(DescriptorType::Sampler, 1)];
+        let counts = |count, max_sets| {
+            let mut counts = pool_ranges(&usage, 3, &bindings, count, max_sets)
+                .into_iter()
+                .map(|range| (range.ty, range.count))
+                .collect::<Vec<_>>();
+            counts.sort_by_key(|&(_, count)| count);
+            counts
+        };
+        assert_eq!(counts(2, 16), vec![
+            (DescriptorType::Sampler, 16),
+            (DescriptorType::SampledImage, 32),
+        ]);
+        // the request itself needs more images than the average
+        assert_eq!(counts(4, 4), vec![
+            (DescriptorType::Sampler, 4),
+            (DescriptorType::SampledImage, 32),
+        ]);
+    }
+
+    #[test]
+    fn reset_once_all_sets_released() {
+        let mut counts = SetCounts::new(2);
+        counts.allocate();
+        counts.allocate();
+        assert!(counts.is_full());
+        assert!(!counts.release());
+        // released sets don't free space until the reset
+        assert!(counts.is_full());
+        assert!(counts.release());
+        assert_eq!(counts, SetCounts::new(2));
+    }
+
+    #[test]
+    fn exhausted_until_reset() {
+        let mut counts = SetCounts::new(4);
+        counts.allocate();
+        counts.exhaust();
+        assert!(counts.is_full());
+        assert!(counts.release());
+        assert!(!counts.is_full());
+    }
+}
@@ -1,6 +1,7 @@
+use std::collections::HashMap;
 use std::mem;
 use std::ops::Range;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
 use hal::{self, Device as CoreDevice, MemoryType, MemoryTypeId};
 use hal::memory::{Properties};
@@ -8,7 +9,7 @@ use hal::memory::{Properties};
 use memory::{self, Allocator, Typed};
 use handle::{self, GarbageSender};
 use handle::inner::*;
-use {buffer, image, format, pso};
+use {buffer, descriptors, image, format, pso};
 use {Backend, Primitive};
 
 pub use hal::device::{FramebufferError};
@@ -18,7 +19,11 @@ pub struct Device<B: Backend> {
     // TODO: could be shared instead of cloned
     memory_types: Vec<MemoryType>,
     memory_heaps: Vec<u64>,
-    garbage: GarbageSender<B>,
+    pub(crate) garbage: GarbageSender<B>,
+    render_passes: Arc<Mutex<HashMap<hal::pass::RenderPassDesc, handle::raw::RenderPass<B>>>>,
+    descriptors: descriptors::DescriptorAllocator<B>,
+    // indexed by format, excluding `Undefined`
+    format_properties: Arc<Vec<format::Properties>>,
 }
 
 impl<B: Backend> Clone for Device<B> {
@@ -28,6 +33,9 @@ impl<B: Backend> Clone for Device<B> {
             memory_types: self.memory_types.clone(),
             memory_heaps: self.memory_heaps.clone(),
             garbage: self.garbage.clone(),
+            render_passes: self.render_passes.clone(),
+            descriptors: self.descriptors.clone(),
+            format_properties: self.format_properties.clone(),
         }
     }
 }
@@ -41,11 +49,22 @@ impl<B: Backend> Device<B> {
         raw: B::Device,
         memory_types: Vec<MemoryType>,
         memory_heaps: Vec<u64>,
+        format_properties: Vec<format::Properties>,
     ) -> (Self, handle::GarbageCollector<B>)
     {
         let arc = Arc::new(raw);
         let (garbage, collector) = handle::garbage(&arc);
-        (Device { raw: arc, memory_types, memory_heaps, garbage }, collector)
+        let render_passes = Arc::new(Mutex::new(HashMap::new()));
+        let descriptors = descriptors::DescriptorAllocator::new(arc.clone());
+        let format_properties = Arc::new(format_properties);
+        let device = Device {
+            raw: arc, memory_types, memory_heaps, garbage, render_passes, descriptors, format_properties,
+        };
+        (device, collector)
+    }
+
+    pub fn format_properties(&self, format: format::Format) -> format::Properties {
+        self.format_properties[format as usize - 1]
     }
 
     pub fn memory_types(&self) -> &[MemoryType] {
@@ -203,7 +222,8 @@ impl<B: Backend> Device<B> {
             _ => Layout::General,
         };
         let stable_state = (stable_access, stable_layout);
-        let info = image::Info { aspects, usage, kind, mip_levels, format, origin, stable_state };
+        let features = self.format_properties(format).optimal_tiling;
+        let info = image::Info { aspects, usage, kind, mip_levels, format, features, origin, stable_state };
         let handle = handle::raw::Image::from(
             Image::new(image, info, self.garbage.clone()));
         let token = InitToken { handle: handle.clone().into() };
@@ -264,38 +284,26 @@ impl<B: Backend> Device<B> {
         ).into()
     }
 
-    // TODO: smarter allocation
     pub fn create_descriptors<D>(&mut self, count: usize) -> Vec<(D, D::Data)>
         where D: pso::Descriptors<B>
     {
-        use hal::pso::DescriptorPool as CDP;
-
         let bindings = &D::layout_bindings()[..];
         let layout = self.create_descriptor_set_layout(bindings);
-        let ranges = bindings.iter().map(|binding| {
-            hal::pso::DescriptorRangeDesc {
-                ty: binding.ty,
-                count: binding.count * count,
-            }
-        }).collect::<Vec<_>>();
-
-        let mut pool = self.raw.create_descriptor_pool(count, &ranges[..]);
-        let sets = {
-            let layout_refs = (0..count).map(|_| layout.resource());
-            pool.allocate_sets(layout_refs)
-        };
+        self.descriptors.allocate(&layout, bindings, count)
+            .into_iter()
+            .map(|set| D::from_raw(layout.clone(), set))
+            .collect()
+    }
 
-        let pool = handle::raw::DescriptorPool::from(
-            DescriptorPool::new(pool, (), self.garbage.clone()));
-        sets.into_iter().filter_map(Result::ok).map(|set| {
-            D::from_raw(layout.clone(), pso::RawDescriptorSet {
-                resource: set,
-                pool: pool.clone()
-            })
-        }).collect()
+    /// Allocator of the descriptor sets created by this device.
+    ///
+    /// A `Context` flushes and releases it with its frames,
+    /// see `DescriptorAllocator` for using the device without one.
+    pub fn descriptor_allocator(&self) -> &descriptors::DescriptorAllocator<B> {
+        &self.descriptors
     }
 
-    fn create_descriptor_set_layout(
+    pub(crate) fn create_descriptor_set_layout(
         &mut self,
         bindings: &[hal::pso::DescriptorSetLayoutBinding],
     ) -> handle::raw::DescriptorSetLayout<B> {
@@ -318,6 +326,32 @@ impl<B: Backend> Device<B> {
         RenderPass::new(pass, (), self.garbage.clone()).into()
     }
 
+    /// Get a render pass matching `desc`, creating it on first use.
+    ///
+    /// Render passes are cached, so pipelines and framebuffers built from equal
+    /// descriptions share the same pass. The cache is shared by the clones of the
+    /// device and never evicts on its own: it keeps every pass alive until
+    /// `clear_render_passes` is called.
+    pub fn render_pass(&self, desc: &hal::pass::RenderPassDesc) -> handle::raw::RenderPass<B> {
+        let mut render_passes = self.render_passes.lock().unwrap();
+        if let Some(pass) = render_passes.get(desc) {
+            return pass.clone();
+        }
+        let pass = desc.create(&*self.raw);
+        let handle: handle::raw::RenderPass<B> =
+            RenderPass::new(pass, (), self.garbage.clone()).into();
+        render_passes.insert(desc.clone(), handle.clone());
+        handle
+    }
+
+    /// Empty the render pass cache.
+    ///
+    /// The passes are destroyed once the pipelines, framebuffers and encoders
+    /// using them are dropped, later requests create new ones.
+    pub fn clear_render_passes(&self) {
+        self.render_passes.lock().unwrap().clear();
+    }
+
     #[doc(hidden)]
     pub fn create_pipeline_layout_raw(
         &mut self,
@@ -349,6 +383,25 @@ impl<B: Backend> Device<B> {
         init.create(self, shader_entries, primitive, rasterizer)
     }
 
+    #[doc(hidden)]
+    pub fn create_compute_pipeline_raw(
+        &mut self,
+        desc: hal::pso::ComputePipelineDesc<B>,
+    ) -> Result<handle::raw::ComputePipeline<B>, pso::CreationError> {
+        let pipeline = self.raw.create_compute_pipeline(&desc)?;
+        Ok(ComputePipeline::new(pipeline, (), self.garbage.clone()).into())
+    }
+
+    pub fn create_compute_pipeline<I>(
+        &mut self,
+        shader: hal::pso::EntryPoint<B>,
+        init: I,
+    ) -> Result<I::Pipeline, pso::CreationError>
+        where I: pso::ComputePipelineInit<B>
+    {
+        init.create(self, shader)
+    }
+
     // TODO?: typed
     pub fn create_framebuffer<P>(
         &mut self,
@@ -358,8 +411,17 @@ impl<B: Backend> Device<B> {
     ) -> Result<handle::raw::Framebuffer<B>, FramebufferError>
         where P: pso::GraphicsPipelineMeta<B>
     {
+        self.create_framebuffer_raw(pipeline.render_pass(), attachments, extent)
+    }
+
+    pub fn create_framebuffer_raw(
+        &mut self,
+        render_pass: &B::RenderPass,
+        attachments: &[&handle::raw::ImageView<B>],
+        extent: image::Extent,
+    ) -> Result<handle::raw::Framebuffer<B>, FramebufferError> {
         let resources = attachments.iter().map(|&view| view.resource());
-        let buffer = self.raw.create_framebuffer(pipeline.render_pass(), resources, extent)?;
+        let buffer = self.raw.create_framebuffer(render_pass, resources, extent)?;
         let info = handle::FramebufferInfo {
             attachments: attachments.iter().cloned().cloned().collect(),
             extent,
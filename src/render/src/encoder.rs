@@ -1,11 +1,12 @@
 //! Commands encoder.
 
-use std::mem;
+use std::error::Error;
+use std::{fmt, mem};
 use std::ops::Range;
 use std::sync::mpsc;
 use std::collections::{HashMap, HashSet};
 
-use hal::{self, buffer as b, image as i, CommandPool};
+use hal::{self, buffer as b, image as i, CommandPool, WorkGroupCount};
 use hal::command::CommandBuffer;
 use hal::format::Aspects;
 use hal::memory::{Barrier, Dependencies};
@@ -13,15 +14,33 @@ use hal::pso::PipelineStage;
 
 use memory::{Provider, Dependency, cast_slice};
 use device::InitToken;
+use mipmap::MipmapFallback;
 use {handle, buffer, image, format, pso};
-use {Backend, Supports, Transfer, Graphics};
-use {VertexCount};
+use {Backend, Supports, Transfer, Graphics, Compute};
+use slice::{offset_vertices, Slice};
 
 pub use hal::command::{
-    BufferCopy, ImageCopy, BufferImageCopy,
+    BufferCopy, ImageCopy, BufferImageCopy, ImageBlit,
     ClearColor, ClearDepthStencil,
 };
 
+/// Error returned when the mip chain of an image can't be generated, because
+/// images of the format can neither be blitted nor rendered by a `MipmapFallback`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MipmapError(pub format::Format);
+
+impl fmt::Display for MipmapError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {:?}", self.description(), self.0)
+    }
+}
+
+impl Error for MipmapError {
+    fn description(&self) -> &str {
+        "Mipmap generation is not supported for the image format"
+    }
+}
+
 pub struct Pool<B: Backend, C>(Provider<PoolInner<B, C>>);
 
 #[derive(Clone)]
@@ -214,6 +233,41 @@ impl<'a, B: Backend, C> Encoder<'a, B, C>
         self.buffer.pipeline_barrier(stage_transition, Dependencies::empty(), barriers);
     }
 
+    /// Blit the backbuffer `image` into the opaque `framebuffer` of a surface.
+    pub(crate) fn blit_to_framebuffer(
+        &mut self,
+        image: &handle::raw::Image<B>,
+        framebuffer: &B::Framebuffer,
+    ) {
+        use hal::command::RawCommandBuffer;
+
+        let info = image.info();
+        let state = (i::Access::TRANSFER_READ, i::Layout::TransferSrcOptimal);
+        let subresource = image::Subresource { aspects: info.aspects, level: 0, layer: 0 };
+        self.require_state(PipelineStage::TRANSFER, &[], &[(image, subresource, state)]);
+
+        let extent = info.kind.extent();
+        let layers = image::SubresourceLayers {
+            aspects: info.aspects,
+            level: 0,
+            layers: 0 .. 1,
+        };
+        let bounds = i::Offset { x: 0, y: 0, z: 0 } ..
+            i::Offset { x: extent.width as _, y: extent.height as _, z: 1 };
+        let region = ImageBlit {
+            src_subresource: layers.clone(),
+            src_bounds: bounds.clone(),
+            dst_subresource: layers,
+            dst_bounds: bounds,
+        };
+        // opaque framebuffers are presented from graphics queues
+        self.buffer.as_raw_mut().blit_image_to_framebuffer(
+            image.resource(), state.1,
+            framebuffer,
+            i::Filter::Nearest,
+            Some(region));
+    }
+
     fn init_image<'b>(
         &mut self, image: &'b handle::raw::Image<B>
     ) -> Barrier<'b, B> {
@@ -231,13 +285,15 @@ impl<'a, B: Backend, C> Encoder<'a, B, C>
                 levels: 0 .. num_levels,
                 layers: 0 .. num_layers,
             },
+            families: None,
         }
     }
 
     fn require_buffer_state<'b>(
         &mut self,
         buffer: &'b handle::raw::Buffer<B>,
-        state: hal::buffer::State
+        state: hal::buffer::State,
+        force: bool,
     ) -> Option<Barrier<'b, B>> {
         if !self.buffer_states.contains_key(buffer) {
             self.buffer_states.insert(buffer.clone(), buffer.info().stable_state);
@@ -245,7 +301,8 @@ impl<'a, B: Backend, C> Encoder<'a, B, C>
         Self::transition_buffer(
             buffer,
             self.buffer_states.get_mut(buffer).unwrap(),
-            state)
+            state,
+            force)
     }
 
     fn require_image_state<'b>(
@@ -254,6 +311,7 @@ impl<'a, B: Backend, C> Encoder<'a, B, C>
         level: image::Level,
         layer: image::Layer,
         state: hal::image::State,
+        force: bool,
     ) -> Option<Barrier<'b, B>> {
         if !self.image_states.contains_key(image) {
             let levels = image.info().mip_levels;
@@ -266,19 +324,23 @@ impl<'a, B: Backend, C> Encoder<'a, B, C>
             level,
             layer,
             self.image_states.get_mut(image).unwrap().get_mut(level, layer),
-            state)
+            state,
+            force)
     }
 
     fn transition_buffer<'b>(
         buffer: &'b handle::raw::Buffer<B>,
         current: &mut hal::buffer::State,
-        next: hal::buffer::State
+        next: hal::buffer::State,
+        force: bool,
     ) -> Option<Barrier<'b, B>> {
         let state = mem::replace(current, next);
-        if state != next {
+        // writes need to be made visible even without state change
+        if force || state != next || (state | next).is_write() {
             Some(Barrier::Buffer {
                 states: state..next,
                 target: buffer.resource(),
+                families: None,
             })
         } else {
             None
@@ -291,9 +353,11 @@ impl<'a, B: Backend, C> Encoder<'a, B, C>
         layer: image::Layer,
         current: &mut hal::image::State,
         next: hal::image::State,
+        force: bool,
     ) -> Option<Barrier<'b, B>> {
         let state = mem::replace(current, next);
-        if state != next {
+        // writes need to be made visible even without state change
+        if force || state != next || (state.0 | next.0).is_write() {
             Some(Barrier::Image {
                 states: state .. next,
                 target: image.resource(),
@@ -302,6 +366,7 @@ impl<'a, B: Backend, C> Encoder<'a, B, C>
                     levels: level .. (level+1),
                     layers: layer .. (layer+1),
                 },
+                families: None,
             })
         } else {
             None
@@ -315,12 +380,38 @@ impl<'a, B: Backend, C> Encoder<'a, B, C>
         buffer_states: &[(&handle::raw::Buffer<B>, hal::buffer::State)],
         image_states: &[(&handle::raw::Image<B>, image::Subresource, hal::image::State)],
     ) {
+        self.require_states(
+            stage,
+            buffer_states.iter().map(|&(buffer, state)| (buffer, state, false)),
+            image_states.iter().map(|&(image, subresource, state)| (image, subresource, state, false)),
+        );
+    }
+
+    /// Same as `require_state`, also emitting barriers for the flagged resources
+    /// when their state doesn't change.
+    #[doc(hidden)]
+    pub fn require_dependent_state(
+        &mut self,
+        stage: hal::pso::PipelineStage,
+        buffer_states: &[(&handle::raw::Buffer<B>, hal::buffer::State, bool)],
+        image_states: &[(&handle::raw::Image<B>, image::Subresource, hal::image::State, bool)],
+    ) {
+        self.require_states(stage, buffer_states.iter().cloned(), image_states.iter().cloned());
+    }
+
+    fn require_states<'b, I, J>(&mut self, stage: hal::pso::PipelineStage, buffer_states: I, image_states: J)
+    where
+        I: IntoIterator<Item = (&'b handle::raw::Buffer<B>, hal::buffer::State, bool)>,
+        J: IntoIterator<Item = (&'b handle::raw::Image<B>, image::Subresource, hal::image::State, bool)>,
+        B: 'b,
+    {
         let mut barriers = Vec::new();
-        for &(buffer, state) in buffer_states {
-            barriers.extend(self.require_buffer_state(buffer, state));
+        for (buffer, state, force) in buffer_states {
+            barriers.extend(self.require_buffer_state(buffer, state, force));
         }
-        for &(image, subresource, state) in image_states {
-            barriers.extend(self.require_image_state(image, subresource.level, subresource.layer, state));
+        for (image, subresource, state, force) in image_states {
+            barriers.extend(self.require_image_state(
+                image, subresource.level, subresource.layer, state, force));
         }
         let current_stage = mem::replace(&mut self.pipeline_stage, stage);
         if (current_stage != stage) || !barriers.is_empty() {
@@ -336,14 +427,14 @@ impl<'a, B: Backend, C> Encoder<'a, B, C>
     fn transition_to_stable_state(&mut self) {
         let mut barriers = Vec::new();
         for (buffer, state) in &mut self.buffer_states {
-            barriers.extend(Self::transition_buffer(buffer, state, buffer.info().stable_state));
+            barriers.extend(Self::transition_buffer(buffer, state, buffer.info().stable_state, false));
         }
         for (image, states) in &mut self.image_states {
             let (levels, layers) = states.ranges();
             for level in levels {
                 for layer in layers.clone() {
                     let state = states.get_mut(level, layer);
-                    barriers.extend(Self::transition_image(image, level, layer, state, image.info().stable_state));
+                    barriers.extend(Self::transition_image(image, level, layer, state, image.info().stable_state, false));
                 }
             }
         }
@@ -662,28 +753,184 @@ impl<'a, B: Backend, C> Encoder<'a, B, C>
         self.clear_image_raw(image.as_ref(), color, depth_stencil);
     }
 
+    /// Draw `slice` with the given pipeline.
     pub fn draw<D>(
         &mut self,
-        vertices: Range<VertexCount>,
+        slice: &Slice<B>,
         pipeline: &D::Pipeline,
         data: D
     )
         where D: pso::GraphicsPipelineData<B>
     {
-        // TODO: instances
-        data.begin_renderpass(self, pipeline).draw(vertices, 0..1);
+        let index = slice.buffer.raw();
+        // barriers can't be recorded inside of the render pass
+        {
+            let mut stage = PipelineStage::VERTEX_INPUT;
+            let mut buffer_states = Vec::new();
+            let mut image_states = Vec::new();
+            data.require(&mut buffer_states, &mut image_states, &mut self.handles);
+            if let Some((buffer, _)) = index {
+                debug_assert!(buffer.info().usage.contains(b::Usage::INDEX),
+                    "missing INDEX usage flag");
+                buffer_states.push((buffer, b::Access::INDEX_BUFFER_READ));
+            }
+            if let Some(ref indirect) = slice.indirect {
+                debug_assert!(indirect.buffer.info().usage.contains(b::Usage::INDIRECT),
+                    "missing INDIRECT usage flag");
+                stage |= PipelineStage::DRAW_INDIRECT;
+                buffer_states.push((&indirect.buffer, b::Access::INDIRECT_COMMAND_READ));
+            }
+            self.require_state(stage, &buffer_states[..], &image_states[..]);
+        }
+
+        let mut pass = data.begin_renderpass(self, pipeline);
+        if let Some((buffer, index_type)) = index {
+            pass.bind_index_buffer(b::IndexBufferView {
+                buffer: buffer.resource(),
+                offset: 0,
+                index_type,
+            });
+        }
+        match (&slice.indirect, index) {
+            (&Some(ref indirect), None) => pass.draw_indirect(
+                indirect.buffer.resource(), indirect.offset, indirect.draw_count, indirect.stride),
+            (&Some(ref indirect), Some(_)) => pass.draw_indexed_indirect(
+                indirect.buffer.resource(), indirect.offset, indirect.draw_count, indirect.stride),
+            (&None, None) => pass.draw(
+                offset_vertices(slice.start .. slice.end, slice.base_vertex), slice.instances.clone()),
+            (&None, Some(_)) => pass.draw_indexed(
+                slice.start .. slice.end, slice.base_vertex, slice.instances.clone()),
+        }
     }
 
-/*
-    /// Generate a mipmap chain for the given resource view.
-    pub fn generate_mipmap<T: format::BlendFormat>(&mut self, view: &handle::ShaderResourceView<B, T>) {
-        self.generate_mipmap_raw(view.raw())
+    /// Generate the mip chain of `image` by successively downsampling level 0.
+    ///
+    /// Levels are blitted with linear filtering if the format supports it and
+    /// nearest filtering otherwise. Formats which can't be blitted are rendered
+    /// with `fallback`, if given. All the layers of the image are processed,
+    /// which covers array and cube images.
+    pub fn generate_mipmap<F>(
+        &mut self,
+        image: &handle::Image<B, F>,
+        fallback: Option<&mut MipmapFallback<B>>,
+    ) -> Result<(), MipmapError>
+    where
+        F: format::AsFormat,
+    {
+        self.generate_mipmap_raw(image.as_ref(), fallback)
     }
 
     /// Untyped version of mipmap generation.
-    pub fn generate_mipmap_raw(&mut self, view: &handle::RawShaderResourceView<B>) {
-        let srv = self.handles.ref_srv(view).clone();
-        self.command_buffer.generate_mipmap(srv);
+    pub fn generate_mipmap_raw(
+        &mut self,
+        image: &handle::raw::Image<B>,
+        fallback: Option<&mut MipmapFallback<B>>,
+    ) -> Result<(), MipmapError> {
+        let info = image.info();
+        if info.mip_levels <= 1 { return Ok(()) };
+
+        let blit = format::ImageFeature::BLIT_SRC | format::ImageFeature::BLIT_DST;
+        if !info.features.contains(blit) {
+            return match fallback {
+                Some(fallback) => fallback.record(self, image),
+                None => Err(MipmapError(info.format)),
+            };
+        }
+        let filter = if info.features.contains(format::ImageFeature::SAMPLED_LINEAR) {
+            i::Filter::Linear
+        } else {
+            i::Filter::Nearest
+        };
+        debug_assert!(info.usage.contains(i::Usage::TRANSFER_SRC | i::Usage::TRANSFER_DST),
+            "missing TRANSFER_SRC or TRANSFER_DST usage flag");
+
+        let src_state = (i::Access::TRANSFER_READ, i::Layout::TransferSrcOptimal);
+        let dst_state = (i::Access::TRANSFER_WRITE, i::Layout::TransferDstOptimal);
+        let layers = info.kind.num_layers();
+        let bounds = |level| {
+            let extent = info.kind.level_extent(level);
+            i::Offset { x: 0, y: 0, z: 0 } ..
+                i::Offset { x: extent.width as _, y: extent.height as _, z: extent.depth as _ }
+        };
+        for level in 1 .. info.mip_levels {
+            let mut image_states = Vec::new();
+            for layer in 0 .. layers {
+                let subresource = |level| image::Subresource { aspects: info.aspects, level, layer };
+                image_states.push((image, subresource(level - 1), src_state));
+                image_states.push((image, subresource(level), dst_state));
+            }
+            self.require_state(PipelineStage::TRANSFER, &[], &image_states[..]);
+
+            let region = ImageBlit {
+                src_subresource: image::SubresourceLayers {
+                    aspects: info.aspects,
+                    level: level - 1,
+                    layers: 0 .. layers,
+                },
+                src_bounds: bounds(level - 1),
+                dst_subresource: image::SubresourceLayers {
+                    aspects: info.aspects,
+                    level,
+                    layers: 0 .. layers,
+                },
+                dst_bounds: bounds(level),
+            };
+            self.buffer.blit_image(
+                image.resource(), src_state.1,
+                image.resource(), dst_state.1,
+                filter,
+                Some(region));
+        }
+        Ok(())
+    }
+}
+
+impl<'a, B: Backend, C> Encoder<'a, B, C>
+    where C: Supports<Transfer> + Supports<Compute>
+{
+    /// Dispatch `groups` work groups of the given pipeline.
+    pub fn dispatch<D>(
+        &mut self,
+        pipeline: &D::Pipeline,
+        data: D,
+        groups: WorkGroupCount,
+    )
+        where D: pso::ComputePipelineData<B>
+    {
+        {
+            let mut buffer_states = Vec::new();
+            let mut image_states = Vec::new();
+            data.require(&mut buffer_states, &mut image_states, &mut self.handles);
+            self.require_state(PipelineStage::COMPUTE_SHADER, &buffer_states[..], &image_states[..]);
+        }
+        data.bind(self, pipeline);
+        self.buffer.dispatch(groups);
+    }
+
+    /// Dispatch the given pipeline with the work group count read from `buffer` at `offset`.
+    pub fn dispatch_indirect<D>(
+        &mut self,
+        pipeline: &D::Pipeline,
+        data: D,
+        buffer: &handle::raw::Buffer<B>,
+        offset: u64,
+    )
+        where D: pso::ComputePipelineData<B>
+    {
+        debug_assert!(buffer.info().usage.contains(b::Usage::INDIRECT),
+            "missing INDIRECT usage flag");
+
+        {
+            let mut buffer_states = Vec::new();
+            let mut image_states = Vec::new();
+            data.require(&mut buffer_states, &mut image_states, &mut self.handles);
+            buffer_states.push((buffer, b::Access::INDIRECT_COMMAND_READ));
+            self.require_state(
+                PipelineStage::DRAW_INDIRECT | PipelineStage::COMPUTE_SHADER,
+                &buffer_states[..],
+                &image_states[..]);
+        }
+        data.bind(self, pipeline);
+        self.buffer.dispatch_indirect(buffer.resource(), offset);
     }
-    */
 }
@@ -0,0 +1,1094 @@
+//! Render graph.
+//!
+//! A `GraphBuilder` collects passes together with a `PassDesc` declaring the
+//! images and buffers each pass reads and writes. Building the graph orders the
+//! passes, culls the ones not contributing to an output, allocates the transient
+//! images and creates the render passes and framebuffers. Recording the graph
+//! into an `Encoder` inserts the barriers between the passes.
+//!
+//! Accesses are resolved in the order the passes were added: a pass reads what
+//! the last pass added before it wrote, or the final contents if no writer was
+//! added before it. Passes writing the same resource keep their relative order.
+
+use std::collections::HashSet;
+use std::error::Error;
+use std::{fmt, mem};
+
+use hal::{buffer as b, image as i};
+use hal::command::{
+    ClearColor, ClearDepthStencil, ClearValue, Primary, RenderPassInlineEncoder,
+};
+use hal::pass::{
+    Attachment, AttachmentLoadOp, AttachmentOps, AttachmentStoreOp,
+    OwnedSubpassDesc, RenderPassDesc,
+};
+use hal::pso::{PipelineStage, Rect};
+
+use device::{FramebufferError, InitToken};
+use memory::Allocator;
+use {buffer, format, handle, image};
+use {Backend, Device, Encoder, Graphics, Supports, Transfer};
+
+/// Identifier of an image in a render graph.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct ImageId(usize);
+
+/// Identifier of a buffer in a render graph.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct BufferId(usize);
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+enum Resource {
+    Image(ImageId),
+    Buffer(BufferId),
+}
+
+/// Description of a transient image owned by the graph.
+///
+/// The usage of the image is derived from the accesses of the passes.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct ImageInfo {
+    /// Kind of the image.
+    pub kind: image::Kind,
+    /// Number of mip levels.
+    pub mip_levels: image::Level,
+    /// Format of the image.
+    pub format: format::Format,
+}
+
+/// Access of a pass to an image.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ImageAccess {
+    /// Usage the image needs to support.
+    pub usage: image::Usage,
+    /// Access flags and layout of the image during the pass.
+    pub state: i::State,
+    /// Pipeline stages accessing the image.
+    pub stages: PipelineStage,
+}
+
+impl ImageAccess {
+    /// Sampled from fragment shaders.
+    pub fn sampled() -> Self {
+        ImageAccess {
+            usage: image::Usage::SAMPLED,
+            state: (i::Access::SHADER_READ, i::Layout::ShaderReadOnlyOptimal),
+            stages: PipelineStage::FRAGMENT_SHADER,
+        }
+    }
+
+    /// Rendered to as a color attachment.
+    pub fn color_attachment() -> Self {
+        ImageAccess {
+            usage: image::Usage::COLOR_ATTACHMENT,
+            state: (
+                i::Access::COLOR_ATTACHMENT_READ | i::Access::COLOR_ATTACHMENT_WRITE,
+                i::Layout::ColorAttachmentOptimal,
+            ),
+            stages: PipelineStage::COLOR_ATTACHMENT_OUTPUT,
+        }
+    }
+
+    /// Rendered to as a depth-stencil attachment.
+    pub fn depth_stencil_attachment() -> Self {
+        ImageAccess {
+            usage: image::Usage::DEPTH_STENCIL_ATTACHMENT,
+            state: (
+                i::Access::DEPTH_STENCIL_ATTACHMENT_READ | i::Access::DEPTH_STENCIL_ATTACHMENT_WRITE,
+                i::Layout::DepthStencilAttachmentOptimal,
+            ),
+            stages: PipelineStage::EARLY_FRAGMENT_TESTS | PipelineStage::LATE_FRAGMENT_TESTS,
+        }
+    }
+
+    /// Source of transfer commands.
+    pub fn transfer_src() -> Self {
+        ImageAccess {
+            usage: image::Usage::TRANSFER_SRC,
+            state: (i::Access::TRANSFER_READ, i::Layout::TransferSrcOptimal),
+            stages: PipelineStage::TRANSFER,
+        }
+    }
+
+    /// Destination of transfer commands.
+    pub fn transfer_dst() -> Self {
+        ImageAccess {
+            usage: image::Usage::TRANSFER_DST,
+            state: (i::Access::TRANSFER_WRITE, i::Layout::TransferDstOptimal),
+            stages: PipelineStage::TRANSFER,
+        }
+    }
+}
+
+/// Access of a pass to a buffer.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BufferAccess {
+    /// Usage the buffer needs to support.
+    pub usage: buffer::Usage,
+    /// Access flags of the buffer during the pass.
+    pub access: b::Access,
+    /// Pipeline stages accessing the buffer.
+    pub stages: PipelineStage,
+}
+
+impl BufferAccess {
+    /// Read as vertex buffer.
+    pub fn vertex() -> Self {
+        BufferAccess {
+            usage: buffer::Usage::VERTEX,
+            access: b::Access::VERTEX_BUFFER_READ,
+            stages: PipelineStage::VERTEX_INPUT,
+        }
+    }
+
+    /// Read as index buffer.
+    pub fn index() -> Self {
+        BufferAccess {
+            usage: buffer::Usage::INDEX,
+            access: b::Access::INDEX_BUFFER_READ,
+            stages: PipelineStage::VERTEX_INPUT,
+        }
+    }
+
+    /// Source of transfer commands.
+    pub fn transfer_src() -> Self {
+        BufferAccess {
+            usage: buffer::Usage::TRANSFER_SRC,
+            access: b::Access::TRANSFER_READ,
+            stages: PipelineStage::TRANSFER,
+        }
+    }
+
+    /// Destination of transfer commands.
+    pub fn transfer_dst() -> Self {
+        BufferAccess {
+            usage: buffer::Usage::TRANSFER_DST,
+            access: b::Access::TRANSFER_WRITE,
+            stages: PipelineStage::TRANSFER,
+        }
+    }
+}
+
+/// Resources accessed by a pass.
+///
+/// Attachments are bound for the first level and layer of the image.
+/// An attachment with a clear value replaces the previous contents, otherwise
+/// they are loaded.
+#[derive(Clone, Debug)]
+pub struct PassDesc {
+    name: String,
+    colors: Vec<(ImageId, Option<ClearColor>)>,
+    depth_stencil: Option<(ImageId, Option<ClearDepthStencil>)>,
+    images: Vec<(ImageId, ImageAccess, bool)>,
+    buffers: Vec<(BufferId, BufferAccess, bool)>,
+}
+
+impl PassDesc {
+    /// Create an empty pass description.
+    pub fn new<S: Into<String>>(name: S) -> Self {
+        PassDesc {
+            name: name.into(),
+            colors: Vec::new(),
+            depth_stencil: None,
+            images: Vec::new(),
+            buffers: Vec::new(),
+        }
+    }
+
+    /// Name of the pass.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Add a color attachment, optionally cleared at the start of the pass.
+    pub fn with_color(mut self, id: ImageId, clear: Option<ClearColor>) -> Self {
+        self.colors.push((id, clear));
+        self
+    }
+
+    /// Set the depth-stencil attachment, optionally cleared at the start of the pass.
+    pub fn with_depth_stencil(mut self, id: ImageId, clear: Option<ClearDepthStencil>) -> Self {
+        self.depth_stencil = Some((id, clear));
+        self
+    }
+
+    /// Sample an image from fragment shaders.
+    pub fn with_sampled(self, id: ImageId) -> Self {
+        self.with_image_read(id, ImageAccess::sampled())
+    }
+
+    /// Read an image.
+    pub fn with_image_read(mut self, id: ImageId, access: ImageAccess) -> Self {
+        self.images.push((id, access, false));
+        self
+    }
+
+    /// Write an image. Writes are assumed to not cover the whole image.
+    pub fn with_image_write(mut self, id: ImageId, access: ImageAccess) -> Self {
+        self.images.push((id, access, true));
+        self
+    }
+
+    /// Read a buffer.
+    pub fn with_buffer_read(mut self, id: BufferId, access: BufferAccess) -> Self {
+        self.buffers.push((id, access, false));
+        self
+    }
+
+    /// Write a buffer. Writes are assumed to not cover the whole buffer.
+    pub fn with_buffer_write(mut self, id: BufferId, access: BufferAccess) -> Self {
+        self.buffers.push((id, access, true));
+        self
+    }
+
+    fn has_attachments(&self) -> bool {
+        !self.colors.is_empty() || self.depth_stencil.is_some()
+    }
+
+    fn image_accesses(&self) -> Vec<(ImageId, ImageAccess)> {
+        let attachments = self.colors
+            .iter()
+            .map(|&(id, _)| (id, ImageAccess::color_attachment()))
+            .chain(self.depth_stencil
+                .iter()
+                .map(|&(id, _)| (id, ImageAccess::depth_stencil_attachment())));
+        let others = self.images.iter().map(|&(id, access, _)| (id, access));
+        let mut accesses: Vec<(ImageId, ImageAccess)> = Vec::new();
+        for (id, access) in attachments.chain(others) {
+            match accesses.iter().find(|&&(other, _)| other == id) {
+                Some(&(_, ref other)) => {
+                    assert_eq!(other.state, access.state,
+                        "image {:?} is used with different states in pass {}", id, self.name);
+                    continue;
+                }
+                None => {}
+            }
+            accesses.push((id, access));
+        }
+        accesses
+    }
+
+    fn buffer_accesses(&self) -> Vec<(BufferId, BufferAccess)> {
+        let mut accesses: Vec<(BufferId, BufferAccess)> = Vec::new();
+        for &(id, access, _) in &self.buffers {
+            match accesses.iter_mut().find(|&&mut (other, _)| other == id) {
+                Some(&mut (_, ref mut other)) => {
+                    other.usage |= access.usage;
+                    other.access |= access.access;
+                    other.stages |= access.stages;
+                }
+                None => accesses.push((id, access)),
+            }
+        }
+        accesses
+    }
+}
+
+/// Use of a resource by a pass, as seen by the scheduler.
+#[derive(Clone, Copy, Debug)]
+struct Use {
+    resource: Resource,
+    read: bool,
+    write: bool,
+    /// The whole previous contents are replaced.
+    overwrite: bool,
+}
+
+fn merge_use(uses: &mut Vec<Use>, new: Use) {
+    match uses.iter_mut().find(|u| u.resource == new.resource) {
+        Some(u) => {
+            u.overwrite = (u.overwrite || new.overwrite) && !(u.read || new.read);
+            u.read |= new.read;
+            u.write |= new.write;
+        }
+        None => uses.push(new),
+    }
+}
+
+/// Passes contributing to the outputs of a graph.
+#[derive(Debug)]
+struct Schedule {
+    /// Execution order of the passes.
+    order: Vec<usize>,
+    /// Resources each pass depends on a previous pass for, indexed by pass.
+    dependencies: Vec<Vec<Resource>>,
+}
+
+/// Order the passes contributing to `outputs`.
+///
+/// `passes` holds the resource uses of each pass in declaration order.
+fn schedule(passes: &[Vec<Use>], outputs: &HashSet<Resource>) -> Result<Schedule, GraphError> {
+    // dependencies of each pass, flagged when the contents are consumed
+    let mut deps: Vec<Vec<(usize, bool, Resource)>> = vec![Vec::new(); passes.len()];
+    let mut resources: Vec<Resource> = Vec::new();
+    for uses in passes {
+        for u in uses {
+            if !resources.contains(&u.resource) {
+                resources.push(u.resource);
+            }
+        }
+    }
+
+    let mut last_writers = Vec::new();
+    for &resource in &resources {
+        let accesses: Vec<(usize, Use)> = passes
+            .iter()
+            .enumerate()
+            .filter_map(|(pass, uses)| {
+                uses.iter().find(|u| u.resource == resource).map(|&u| (pass, u))
+            })
+            .collect();
+        let last_writer = accesses.iter().rev().find(|&&(_, u)| u.write).map(|&(pass, _)| pass);
+        let mut writer = None;
+        let mut readers = Vec::new();
+        for &(pass, u) in &accesses {
+            if u.write {
+                if let Some(prev) = writer {
+                    deps[pass].push((prev, !u.overwrite || u.read, resource));
+                }
+                for reader in readers.drain(..) {
+                    deps[pass].push((reader, false, resource));
+                }
+                writer = Some(pass);
+            } else {
+                match writer.or(last_writer) {
+                    Some(w) => deps[pass].push((w, true, resource)),
+                    None => {}
+                }
+                if writer.is_some() {
+                    readers.push(pass);
+                }
+            }
+        }
+        if outputs.contains(&resource) {
+            last_writers.extend(last_writer);
+        }
+    }
+
+    let mut alive = vec![false; passes.len()];
+    while let Some(pass) = last_writers.pop() {
+        if !mem::replace(&mut alive[pass], true) {
+            last_writers.extend(deps[pass].iter().filter(|&&(_, keep, _)| keep).map(|&(dep, _, _)| dep));
+        }
+    }
+
+    let mut order = Vec::new();
+    let mut scheduled = vec![false; passes.len()];
+    let remaining = alive.iter().filter(|&&a| a).count();
+    while order.len() < remaining {
+        let next = (0 .. passes.len()).find(|&pass| {
+            alive[pass] && !scheduled[pass] &&
+                deps[pass].iter().all(|&(dep, _, _)| !alive[dep] || scheduled[dep])
+        });
+        match next {
+            Some(pass) => {
+                scheduled[pass] = true;
+                order.push(pass);
+            }
+            None => return Err(GraphError::Cycle),
+        }
+    }
+
+    let dependencies = deps
+        .iter()
+        .map(|deps| {
+            let mut resources = Vec::new();
+            for &(dep, _, resource) in deps {
+                if alive[dep] && !resources.contains(&resource) {
+                    resources.push(resource);
+                }
+            }
+            resources
+        })
+        .collect();
+    Ok(Schedule { order, dependencies })
+}
+
+/// Physical image slot of each transient image, given as `(info, first, last, usage)`
+/// sorted by first use.
+///
+/// An image takes the slot of an identical one whose last use comes before its
+/// first use, or the next new slot.
+fn pool_slots(transients: &[(ImageInfo, usize, usize, image::Usage)]) -> Vec<usize> {
+    // description and last use of the image in each slot
+    let mut pool: Vec<(ImageInfo, image::Usage, usize)> = Vec::new();
+    let mut slots = Vec::with_capacity(transients.len());
+    for &(info, first, last, usage) in transients {
+        let reusable = pool.iter().position(|&(other, other_usage, other_last)| {
+            other == info && other_usage == usage && other_last < first
+        });
+        match reusable {
+            Some(slot) => {
+                pool[slot].2 = last;
+                slots.push(slot);
+            }
+            None => {
+                pool.push((info, usage, last));
+                slots.push(pool.len() - 1);
+            }
+        }
+    }
+    slots
+}
+
+/// Aspects of the views of an image, sampling depth-stencil images reads the depth.
+fn view_aspects(aspects: format::Aspects) -> format::Aspects {
+    if aspects.contains(format::Aspects::DEPTH) {
+        format::Aspects::DEPTH
+    } else {
+        aspects
+    }
+}
+
+/// Stages and states required by a pass, flagged for the resources it depends
+/// on a previous pass for.
+fn pass_states(
+    buffers: &[(BufferId, BufferAccess)],
+    images: &[(ImageId, ImageAccess)],
+    dependencies: &[Resource],
+) -> (PipelineStage, Vec<(BufferId, b::State, bool)>, Vec<(ImageId, i::State, bool)>) {
+    let mut stages = PipelineStage::empty();
+    let buffer_states = buffers
+        .iter()
+        .map(|&(id, access)| {
+            stages |= access.stages;
+            (id, access.access, dependencies.contains(&Resource::Buffer(id)))
+        })
+        .collect();
+    let image_states = images
+        .iter()
+        .map(|&(id, access)| {
+            stages |= access.stages;
+            (id, access.state, dependencies.contains(&Resource::Image(id)))
+        })
+        .collect();
+    (stages, buffer_states, image_states)
+}
+
+/// Error building a render graph.
+#[derive(Debug)]
+pub enum GraphError {
+    /// The dependencies between the passes form a cycle.
+    Cycle,
+    /// A transient image could not be created.
+    Image(image::CreationError),
+    /// An image view could not be created.
+    View(image::ViewError),
+    /// A framebuffer could not be created.
+    Framebuffer(FramebufferError),
+}
+
+impl fmt::Display for GraphError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            GraphError::Cycle => write!(f, "{}", self.description()),
+            GraphError::Image(ref e) => write!(f, "{}: {}", self.description(), e),
+            GraphError::View(ref e) => write!(f, "{}: {}", self.description(), e),
+            GraphError::Framebuffer(ref e) => write!(f, "{}: {}", self.description(), e),
+        }
+    }
+}
+
+impl Error for GraphError {
+    fn description(&self) -> &str {
+        match *self {
+            GraphError::Cycle => "Passes depend on each other cyclically",
+            GraphError::Image(_) => "Failed to create a transient image",
+            GraphError::View(_) => "Failed to create an image view",
+            GraphError::Framebuffer(_) => "Failed to create a framebuffer",
+        }
+    }
+
+    fn cause(&self) -> Option<&Error> {
+        match *self {
+            GraphError::Cycle => None,
+            GraphError::Image(ref e) => Some(e),
+            GraphError::View(ref e) => Some(e),
+            GraphError::Framebuffer(ref e) => Some(e),
+        }
+    }
+}
+
+impl From<image::CreationError> for GraphError {
+    fn from(e: image::CreationError) -> Self {
+        GraphError::Image(e)
+    }
+}
+
+impl From<image::ViewError> for GraphError {
+    fn from(e: image::ViewError) -> Self {
+        GraphError::View(e)
+    }
+}
+
+impl From<FramebufferError> for GraphError {
+    fn from(e: FramebufferError) -> Self {
+        GraphError::Framebuffer(e)
+    }
+}
+
+/// Physical resources of a built graph, handed to the passes.
+pub struct Resources<B: Backend> {
+    images: Vec<Option<handle::raw::Image<B>>>,
+    image_views: Vec<Option<handle::raw::ImageView<B>>>,
+    buffers: Vec<handle::raw::Buffer<B>>,
+}
+
+impl<B: Backend> Resources<B> {
+    /// Image backing `id`.
+    ///
+    /// Panics if the image is not used by any remaining pass.
+    pub fn image(&self, id: ImageId) -> &handle::raw::Image<B> {
+        self.images[id.0].as_ref().expect("image is not used by the graph")
+    }
+
+    /// View of the whole image backing `id`.
+    ///
+    /// Views are only available for images used as sampled, storage or input attachment.
+    /// The views of depth-stencil images only cover the depth aspect.
+    pub fn image_view(&self, id: ImageId) -> &handle::raw::ImageView<B> {
+        self.image_views[id.0].as_ref().expect("image has no view")
+    }
+
+    /// Buffer backing `id`.
+    pub fn buffer(&self, id: BufferId) -> &handle::raw::Buffer<B> {
+        &self.buffers[id.0]
+    }
+}
+
+enum Record<'a, B: Backend, C> {
+    Encoder(Box<FnMut(&mut Encoder<B, C>, &Resources<B>) + 'a>),
+    RenderPass(Box<FnMut(&mut RenderPassInlineEncoder<B, Primary>, &Resources<B>) + 'a>),
+}
+
+enum ImageNode<B: Backend> {
+    Imported(handle::raw::Image<B>),
+    Transient(ImageInfo),
+}
+
+struct Target<B: Backend> {
+    render_pass: handle::raw::RenderPass<B>,
+    framebuffer: handle::raw::Framebuffer<B>,
+    clear_values: Vec<ClearValue>,
+    area: Rect,
+}
+
+struct Pass<'a, B: Backend, C> {
+    images: Vec<(ImageId, ImageAccess)>,
+    buffers: Vec<(BufferId, BufferAccess)>,
+    // resources written before or read before being written by this pass
+    dependencies: Vec<Resource>,
+    target: Option<Target<B>>,
+    record: Record<'a, B, C>,
+}
+
+/// Collects the resources and passes of a render graph.
+pub struct GraphBuilder<'a, B: Backend, C> {
+    images: Vec<ImageNode<B>>,
+    buffers: Vec<handle::raw::Buffer<B>>,
+    passes: Vec<(PassDesc, Record<'a, B, C>)>,
+    outputs: HashSet<Resource>,
+}
+
+impl<'a, B: Backend, C> GraphBuilder<'a, B, C> {
+    /// Create an empty graph.
+    pub fn new() -> Self {
+        GraphBuilder {
+            images: Vec::new(),
+            buffers: Vec::new(),
+            passes: Vec::new(),
+            outputs: HashSet::new(),
+        }
+    }
+
+    /// Declare a transient image, allocated when the graph is built.
+    pub fn create_image(&mut self, info: ImageInfo) -> ImageId {
+        self.images.push(ImageNode::Transient(info));
+        ImageId(self.images.len() - 1)
+    }
+
+    /// Use an existing image in the graph.
+    pub fn import_image(&mut self, image: handle::raw::Image<B>) -> ImageId {
+        self.images.push(ImageNode::Imported(image));
+        ImageId(self.images.len() - 1)
+    }
+
+    /// Use an existing buffer in the graph.
+    pub fn import_buffer(&mut self, buffer: handle::raw::Buffer<B>) -> BufferId {
+        self.buffers.push(buffer);
+        BufferId(self.buffers.len() - 1)
+    }
+
+    /// Keep the passes writing the final contents of an image.
+    pub fn mark_output_image(&mut self, id: ImageId) {
+        self.outputs.insert(Resource::Image(id));
+    }
+
+    /// Keep the passes writing the final contents of a buffer.
+    pub fn mark_output_buffer(&mut self, id: BufferId) {
+        self.outputs.insert(Resource::Buffer(id));
+    }
+
+    /// Add a pass recording directly into the encoder.
+    ///
+    /// The pass must not declare attachments.
+    pub fn add_pass<F>(&mut self, desc: PassDesc, record: F)
+    where
+        F: FnMut(&mut Encoder<B, C>, &Resources<B>) + 'a,
+    {
+        assert!(!desc.has_attachments(), "pass {} declares attachments", desc.name);
+        self.passes.push((desc, Record::Encoder(Box::new(record))));
+    }
+
+    /// Add a pass recording into a render pass bound to its attachments.
+    pub fn add_render_pass<F>(&mut self, desc: PassDesc, record: F)
+    where
+        F: FnMut(&mut RenderPassInlineEncoder<B, Primary>, &Resources<B>) + 'a,
+    {
+        assert!(desc.has_attachments(), "render pass {} has no attachments", desc.name);
+        self.passes.push((desc, Record::RenderPass(Box::new(record))));
+    }
+
+    fn image_info(&self, id: ImageId) -> ImageInfo {
+        match self.images[id.0] {
+            ImageNode::Imported(ref image) => ImageInfo {
+                kind: image.info().kind,
+                mip_levels: image.info().mip_levels,
+                format: image.info().format,
+            },
+            ImageNode::Transient(info) => info,
+        }
+    }
+
+    fn uses(&self, desc: &PassDesc) -> Vec<Use> {
+        let mut uses = Vec::new();
+        let attachments = desc.colors
+            .iter()
+            .map(|&(id, ref clear)| (id, clear.is_some()))
+            .chain(desc.depth_stencil.iter().map(|&(id, ref clear)| (id, clear.is_some())));
+        for (id, clear) in attachments {
+            let info = self.image_info(id);
+            let whole = info.mip_levels == 1 && info.kind.num_layers() == 1;
+            merge_use(&mut uses, Use {
+                resource: Resource::Image(id),
+                read: !clear,
+                write: true,
+                overwrite: clear && whole,
+            });
+        }
+        for &(id, _, write) in &desc.images {
+            merge_use(&mut uses, Use {
+                resource: Resource::Image(id),
+                read: !write,
+                write,
+                overwrite: false,
+            });
+        }
+        for &(id, _, write) in &desc.buffers {
+            merge_use(&mut uses, Use {
+                resource: Resource::Buffer(id),
+                read: !write,
+                write,
+                overwrite: false,
+            });
+        }
+        uses
+    }
+
+    /// Order and cull the passes and create the resources they need.
+    pub fn build<A>(self, device: &mut Device<B>, allocator: &mut A) -> Result<Graph<'a, B, C>, GraphError>
+    where
+        A: Allocator<B>,
+    {
+        let uses: Vec<_> = self.passes.iter().map(|&(ref desc, _)| self.uses(desc)).collect();
+        let Schedule { order, mut dependencies } = schedule(&uses, &self.outputs)?;
+
+        // lifetime and usage of the images in the remaining passes
+        let mut lifetimes: Vec<Option<(usize, usize, image::Usage)>> = vec![None; self.images.len()];
+        for (position, &pass) in order.iter().enumerate() {
+            for (id, access) in self.passes[pass].0.image_accesses() {
+                let lifetime = lifetimes[id.0].get_or_insert((position, position, image::Usage::empty()));
+                lifetime.1 = position;
+                lifetime.2 |= access.usage;
+            }
+        }
+
+        let mut images = vec![None; self.images.len()];
+        let mut transients = Vec::new();
+        for (id, lifetime) in lifetimes.iter().enumerate() {
+            let (first, last, usage) = match *lifetime {
+                Some(lifetime) => lifetime,
+                None => continue,
+            };
+            match self.images[id] {
+                ImageNode::Imported(ref image) => {
+                    debug_assert!(image.info().usage.contains(usage),
+                        "imported image {} does not support {:?}", id, usage);
+                    images[id] = Some(image.clone());
+                }
+                ImageNode::Transient(info) => transients.push((id, (info, first, last, usage))),
+            }
+        }
+        transients.sort_by_key(|&(_, (_, first, _, _))| first);
+
+        let mut init_tokens = Vec::new();
+        let mut pool: Vec<handle::raw::Image<B>> = Vec::new();
+        let slots = pool_slots(&transients.iter().map(|&(_, transient)| transient).collect::<Vec<_>>());
+        for (&(id, (info, _, _, usage)), slot) in transients.iter().zip(slots) {
+            if slot == pool.len() {
+                let (image, token) = device.create_image_raw(
+                    allocator, usage, info.kind, info.mip_levels, info.format)?;
+                init_tokens.push(token);
+                pool.push(image);
+            }
+            images[id] = Some(pool[slot].clone());
+        }
+
+        let mut image_views = vec![None; self.images.len()];
+        let view_usage = image::Usage::SAMPLED | image::Usage::STORAGE | image::Usage::INPUT_ATTACHMENT;
+        for (id, image) in images.iter().enumerate() {
+            let image = match *image {
+                Some(ref image) => image,
+                None => continue,
+            };
+            if !lifetimes[id].unwrap().2.intersects(view_usage) {
+                continue;
+            }
+            let info = image.info();
+            let layers = info.kind.num_layers();
+            let kind = match info.kind {
+                image::Kind::D1(_, 1) => image::ViewKind::D1,
+                image::Kind::D1(..) => image::ViewKind::D1Array,
+                image::Kind::D2(_, _, 1, _) => image::ViewKind::D2,
+                image::Kind::D2(..) => image::ViewKind::D2Array,
+                image::Kind::D3(..) => image::ViewKind::D3,
+            };
+            let range = image::SubresourceRange {
+                aspects: view_aspects(info.aspects),
+                levels: 0 .. info.mip_levels,
+                layers: 0 .. layers,
+            };
+            image_views[id] = Some(device.create_image_view_raw(image, kind, info.format, range)?);
+        }
+
+        let mut records: Vec<_> = self.passes.into_iter().map(Some).collect();
+        let mut passes = Vec::with_capacity(order.len());
+        for pass in order {
+            let (desc, record) = records[pass].take().unwrap();
+            let target = if desc.has_attachments() {
+                let attachments = desc.colors
+                    .iter()
+                    .map(|&(id, clear)| (id, clear.map(ClearValue::Color), false))
+                    .chain(desc.depth_stencil
+                        .iter()
+                        .map(|&(id, clear)| (id, clear.map(ClearValue::DepthStencil), true)));
+                let mut render_pass_desc = RenderPassDesc::new();
+                let mut subpass = OwnedSubpassDesc::default();
+                let mut views = Vec::new();
+                let mut clear_values = Vec::new();
+                let mut extent = None;
+                for (index, (id, clear, depth)) in attachments.enumerate() {
+                    let image = images[id.0].as_ref().unwrap();
+                    let info = image.info();
+                    let layout = if depth {
+                        i::Layout::DepthStencilAttachmentOptimal
+                    } else {
+                        i::Layout::ColorAttachmentOptimal
+                    };
+                    let load = if clear.is_some() { AttachmentLoadOp::Clear } else { AttachmentLoadOp::Load };
+                    let ops = AttachmentOps::new(load, AttachmentStoreOp::Store);
+                    let stencil_ops = if info.aspects.contains(format::Aspects::STENCIL) {
+                        ops
+                    } else {
+                        AttachmentOps::DONT_CARE
+                    };
+                    render_pass_desc = render_pass_desc.with_attachment(Attachment {
+                        format: Some(info.format),
+                        samples: info.kind.num_samples(),
+                        ops,
+                        stencil_ops,
+                        layouts: layout .. layout,
+                    });
+                    if depth {
+                        subpass.depth_stencil = Some((index, layout));
+                    } else {
+                        subpass.colors.push((index, layout));
+                    }
+                    let range = image::SubresourceRange {
+                        aspects: info.aspects,
+                        levels: 0 .. 1,
+                        layers: 0 .. 1,
+                    };
+                    views.push(device.create_image_view_raw(image, image::ViewKind::D2, info.format, range)?);
+                    // clear values are indexed by attachment
+                    clear_values.push(clear.unwrap_or(ClearValue::Color(ClearColor::Float([0.0; 4]))));
+                    let image_extent = info.kind.extent();
+                    debug_assert!(extent.map_or(true, |e| e == image_extent),
+                        "attachments of pass {} differ in size", desc.name);
+                    extent = Some(image_extent);
+                }
+                let extent = image::Extent { depth: 1, .. extent.unwrap() };
+                let render_pass = device.render_pass(&render_pass_desc.with_subpass(subpass));
+                let view_refs: Vec<_> = views.iter().collect();
+                let framebuffer = device.create_framebuffer_raw(render_pass.resource(), &view_refs, extent)?;
+                Some(Target {
+                    render_pass,
+                    framebuffer,
+                    clear_values,
+                    area: Rect { x: 0, y: 0, w: extent.width as _, h: extent.height as _ },
+                })
+            } else {
+                None
+            };
+            passes.push(Pass {
+                images: desc.image_accesses(),
+                buffers: desc.buffer_accesses(),
+                dependencies: mem::replace(&mut dependencies[pass], Vec::new()),
+                target,
+                record,
+            });
+        }
+
+        Ok(Graph {
+            passes,
+            resources: Resources {
+                images,
+                image_views,
+                buffers: self.buffers,
+            },
+            init_tokens,
+        })
+    }
+}
+
+/// Ordered passes with their resources, ready to be recorded.
+pub struct Graph<'a, B: Backend, C> {
+    passes: Vec<Pass<'a, B, C>>,
+    resources: Resources<B>,
+    init_tokens: Vec<InitToken<B>>,
+}
+
+impl<'a, B: Backend, C> Graph<'a, B, C>
+    where C: Supports<Transfer> + Supports<Graphics>
+{
+    /// Physical resources of the graph.
+    pub fn resources(&self) -> &Resources<B> {
+        &self.resources
+    }
+
+    /// Number of passes remaining after culling.
+    pub fn num_passes(&self) -> usize {
+        self.passes.len()
+    }
+
+    /// Record the passes into `encoder`, transitioning the resources between them.
+    pub fn record(&mut self, encoder: &mut Encoder<B, C>) {
+        let Graph { ref mut passes, ref resources, ref mut init_tokens } = *self;
+        if !init_tokens.is_empty() {
+            encoder.init_resources(mem::replace(init_tokens, Vec::new()));
+        }
+
+        for pass in passes {
+            {
+                let (stages, buffers, images) = pass_states(&pass.buffers, &pass.images, &pass.dependencies);
+                let buffer_states: Vec<_> = buffers
+                    .into_iter()
+                    .map(|(id, access, dependent)| (resources.buffer(id), access, dependent))
+                    .collect();
+                let mut image_states = Vec::new();
+                for (id, state, dependent) in images {
+                    let image = resources.image(id);
+                    let info = image.info();
+                    for level in 0 .. info.mip_levels {
+                        for layer in 0 .. info.kind.num_layers() {
+                            let subresource = image::Subresource { aspects: info.aspects, level, layer };
+                            image_states.push((image, subresource, state, dependent));
+                        }
+                    }
+                }
+                // the previous accesses need to complete even if the state is unchanged
+                if !stages.is_empty() {
+                    encoder.require_dependent_state(stages, &buffer_states, &image_states);
+                }
+            }
+            for &(id, _) in &pass.buffers {
+                encoder.handles().add(resources.buffer(id).clone());
+            }
+
+            match pass.record {
+                Record::Encoder(ref mut record) => record(encoder, resources),
+                Record::RenderPass(ref mut record) => {
+                    let target = pass.target.as_ref().unwrap();
+                    encoder.handles().add(target.framebuffer.clone());
+                    let mut render_pass = encoder.mut_buffer().begin_render_pass_inline(
+                        target.render_pass.resource(),
+                        target.framebuffer.resource(),
+                        target.area,
+                        &target.clear_values,
+                    );
+                    record(&mut render_pass, resources);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read(id: usize) -> Use {
+        Use { resource: Resource::Image(ImageId(id)), read: true, write: false, overwrite: false }
+    }
+
+    fn clear(id: usize) -> Use {
+        Use { resource: Resource::Image(ImageId(id)), read: false, write: true, overwrite: true }
+    }
+
+    fn modify(id: usize) -> Use {
+        Use { resource: Resource::Image(ImageId(id)), read: true, write: true, overwrite: false }
+    }
+
+    fn outputs(ids: &[usize]) -> HashSet<Resource> {
+        ids.iter().map(|&id| Resource::Image(ImageId(id))).collect()
+    }
+
+    #[test]
+    fn orders_readers_after_writers() {
+        // the post-process pass is declared first and reads the final scene
+        let passes = vec![
+            vec![read(0), clear(1)],
+            vec![clear(0)],
+            vec![modify(0)],
+        ];
+        assert_eq!(schedule(&passes, &outputs(&[1])).unwrap().order, vec![1, 2, 0]);
+    }
+
+    #[test]
+    fn culls_unused_passes() {
+        let passes = vec![
+            vec![clear(0)],
+            vec![clear(1)],
+            vec![read(0), clear(2)],
+            // overwrites the output, so the first writer is culled too
+            vec![clear(2)],
+        ];
+        assert_eq!(schedule(&passes, &outputs(&[2])).unwrap().order, vec![3]);
+        assert_eq!(schedule(&passes, &outputs(&[1])).unwrap().order, vec![1]);
+    }
+
+    #[test]
+    fn orders_writers_after_readers() {
+        let passes = vec![
+            vec![clear(0)],
+            vec![read(0), clear(1)],
+            vec![clear(0)],
+            vec![read(0), modify(1)],
+        ];
+        assert_eq!(schedule(&passes, &outputs(&[1])).unwrap().order, vec![0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn depends_on_previous_attachment_writes() {
+        // two passes rendering to the same attachment, then sampling it
+        let passes = vec![
+            vec![clear(0)],
+            vec![modify(0)],
+            vec![read(0), clear(1)],
+        ];
+        let schedule = schedule(&passes, &outputs(&[1])).unwrap();
+        assert_eq!(schedule.order, vec![0, 1, 2]);
+        let image = Resource::Image(ImageId(0));
+        assert!(schedule.dependencies[0].is_empty());
+        assert_eq!(schedule.dependencies[1], vec![image]);
+        assert_eq!(schedule.dependencies[2], vec![image]);
+    }
+
+    #[test]
+    fn detects_cycles() {
+        let passes = vec![
+            vec![read(1), clear(0)],
+            vec![read(0), clear(1)],
+        ];
+        match schedule(&passes, &outputs(&[0, 1])) {
+            Err(GraphError::Cycle) => {}
+            other => panic!("unexpected schedule {:?}", other),
+        }
+    }
+
+    fn transient(width: image::Size, first: usize, last: usize) -> (ImageInfo, usize, usize, image::Usage) {
+        let info = ImageInfo {
+            kind: image::Kind::D2(width, width, 1, 1),
+            mip_levels: 1,
+            format: format::Format::Rgba8Unorm,
+        };
+        (info, first, last, image::Usage::COLOR_ATTACHMENT | image::Usage::SAMPLED)
+    }
+
+    #[test]
+    fn reuses_transient_images() {
+        let transients = vec![
+            transient(64, 0, 1),
+            // overlaps the first one
+            transient(64, 1, 2),
+            transient(64, 2, 3),
+            // differs in size
+            transient(32, 3, 3),
+            // both 64 images are free again, the first slot is taken
+            transient(64, 4, 4),
+        ];
+        assert_eq!(pool_slots(&transients), vec![0, 1, 0, 2, 0]);
+        let mut storage = transient(64, 5, 5);
+        storage.3 = image::Usage::STORAGE;
+        let mut transients = transients;
+        transients.push(storage);
+        assert_eq!(pool_slots(&transients)[5], 3);
+    }
+
+    #[test]
+    fn samples_depth_of_depth_stencil_images() {
+        let depth_stencil = format::Aspects::DEPTH | format::Aspects::STENCIL;
+        assert_eq!(view_aspects(depth_stencil), format::Aspects::DEPTH);
+        assert_eq!(view_aspects(format::Aspects::DEPTH), format::Aspects::DEPTH);
+        assert_eq!(view_aspects(format::Aspects::COLOR), format::Aspects::COLOR);
+    }
+
+    #[test]
+    fn forces_barriers_on_dependencies() {
+        // two passes rendering to the same attachment, then one sampling it
+        // and reading a vertex buffer uploaded outside of the graph
+        let passes = vec![
+            vec![clear(0)],
+            vec![modify(0)],
+            vec![read(0), clear(1), Use {
+                resource: Resource::Buffer(BufferId(0)),
+                read: true,
+                write: false,
+                overwrite: false,
+            }],
+        ];
+        let schedule = schedule(&passes, &outputs(&[1])).unwrap();
+
+        // the attachment is written twice in the same state
+        let attachment = [(ImageId(0), ImageAccess::color_attachment())];
+        let (_, _, images) = pass_states(&[], &attachment, &schedule.dependencies[0]);
+        assert_eq!(images, vec![(ImageId(0), ImageAccess::color_attachment().state, false)]);
+        let (stages, _, images) = pass_states(&[], &attachment, &schedule.dependencies[1]);
+        assert_eq!(stages, PipelineStage::COLOR_ATTACHMENT_OUTPUT);
+        assert_eq!(images, vec![(ImageId(0), ImageAccess::color_attachment().state, true)]);
+
+        let buffers = [(BufferId(0), BufferAccess::vertex())];
+        let images = [
+            (ImageId(0), ImageAccess::sampled()),
+            (ImageId(1), ImageAccess::color_attachment()),
+        ];
+        let (stages, buffers, images) = pass_states(&buffers, &images, &schedule.dependencies[2]);
+        assert_eq!(stages, PipelineStage::VERTEX_INPUT | PipelineStage::FRAGMENT_SHADER |
+            PipelineStage::COLOR_ATTACHMENT_OUTPUT);
+        assert_eq!(buffers, vec![(BufferId(0), b::Access::VERTEX_BUFFER_READ, false)]);
+        assert_eq!(images, vec![
+            (ImageId(0), ImageAccess::sampled().state, true),
+            (ImageId(1), ImageAccess::color_attachment().state, false),
+        ]);
+    }
+}
@@ -2,7 +2,7 @@ use std::iter::Extend;
 use std::sync::{mpsc, Arc};
 
 use memory::{Typed, Provider, Dependency};
-use Backend;
+use {pso, Backend};
 
 pub(crate) fn garbage<B: Backend>(device: &Arc<B::Device>)
     -> (GarbageSender<B>, GarbageCollector<B>)
@@ -55,14 +55,15 @@ impl<B: Backend> InnerGarbageCollector<B> {
                 RenderPass(rp) => dev.destroy_render_pass(rp),
                 PipelineLayout(pl) => dev.destroy_pipeline_layout(pl),
                 GraphicsPipeline(pl) => dev.destroy_graphics_pipeline(pl),
+                ComputePipeline(pl) => dev.destroy_compute_pipeline(pl),
                 Framebuffer(fb) => dev.destroy_framebuffer(fb),
                 Buffer(b) => dev.destroy_buffer(b),
                 BufferView(bv) => dev.destroy_buffer_view(bv),
                 Image(i) => dev.destroy_image(i),
                 ImageView(iv) => dev.destroy_image_view(iv),
                 Sampler(s) => dev.destroy_sampler(s),
-                DescriptorPool(dp) => dev.destroy_descriptor_pool(dp),
                 DescriptorSetLayout(dsl) => dev.destroy_descriptor_set_layout(dsl),
+                Memory(m) => dev.free_memory(m),
             }
         }
     }
@@ -79,6 +80,8 @@ macro_rules! define_resources {
         #[derive(Debug)]
         pub enum Garbage<B: Backend> {
             $( $name(B::$name), )*
+            // freed after the resources sent before it
+            Memory(B::Memory),
         }
 
         #[derive(Clone)]
@@ -231,14 +234,13 @@ define_resources! {
     RenderPass: (),
     PipelineLayout: (),
     GraphicsPipeline: (),
-    // ComputePipeline
+    ComputePipeline: (),
     Framebuffer: ::handle::FramebufferInfo<B>,
     Buffer: ::buffer::Info,
     BufferView: ::handle::raw::Buffer<B>,
     Image: ::image::Info,
     ImageView: ::handle::raw::Image<B>,
     Sampler: ::image::SamplerInfo,
-    DescriptorPool: (),
     DescriptorSetLayout: (),
     // Fence
     // Semaphore
@@ -258,23 +260,37 @@ pub struct FramebufferInfo<B: Backend> {
 }
 
 
-pub struct Bag<B: Backend>(Vec<Any<B>>);
+/// Resources kept alive until the commands using them have been executed.
+pub struct Bag<B: Backend> {
+    handles: Vec<Any<B>>,
+    // owned, descriptor sets can't be shared
+    descriptor_sets: Vec<pso::RawDescriptorSet<B>>,
+}
 
 impl<B: Backend> Bag<B> {
     pub fn new() -> Self {
-        Bag(Vec::new())
+        Bag {
+            handles: Vec::new(),
+            descriptor_sets: Vec::new(),
+        }
     }
 
     pub fn add<H: Into<Any<B>>>(&mut self, handle: H) {
-        self.0.push(handle.into());
+        self.handles.push(handle.into());
+    }
+
+    pub fn add_descriptor_set(&mut self, set: pso::RawDescriptorSet<B>) {
+        self.descriptor_sets.push(set);
     }
 
     pub fn append(&mut self, other: &mut Bag<B>) {
-        self.0.append(&mut other.0);
+        self.handles.append(&mut other.handles);
+        self.descriptor_sets.append(&mut other.descriptor_sets);
     }
 
     pub fn clear(&mut self) {
-        self.0.clear();
+        self.handles.clear();
+        self.descriptor_sets.clear();
     }
 }
 
@@ -282,6 +298,6 @@ impl<'a, B: Backend, H: Into<Any<B>>> Extend<H> for Bag<B> {
     fn extend<I>(&mut self, iter: I)
         where I: IntoIterator<Item = H>
     {
-        self.0.extend(iter.into_iter().map(|handle| handle.into()));
+        self.handles.extend(iter.into_iter().map(|handle| handle.into()));
     }
 }
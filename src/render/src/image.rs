@@ -16,6 +16,7 @@ pub struct Info {
     pub kind: Kind,
     pub mip_levels: Level,
     pub format: hal::format::Format,
+    pub features: hal::format::ImageFeature,
     pub origin: Origin,
     pub(crate) stable_state: hal::image::State,
 }
@@ -104,11 +104,12 @@ pub mod traits {
 // public re-exports
 pub use hal::format;
 pub use hal::{Backend, Frame, Primitive};
-pub use hal::queue::{Supports, Transfer, General, Graphics};
-pub use hal::{VertexCount, InstanceCount};
+pub use hal::queue::{Supports, Transfer, General, Graphics, Compute};
+pub use hal::{VertexCount, VertexOffset, InstanceCount, DrawCount};
 // pub use hal::{ShaderSet, VertexShader, HullShader, DomainShader, GeometryShader, PixelShader};
 pub use encoder::Encoder;
 pub use device::Device;
+pub use slice::{Slice, IndexBuffer, Indirect};
 
 pub mod handle;
 mod device;
@@ -116,9 +117,16 @@ pub mod encoder;
 pub mod memory;
 pub mod allocators;
 pub mod buffer;
+pub mod descriptors;
 pub mod image;
+pub mod graph;
+pub mod mipmap;
+pub mod mapped;
+pub mod upload;
+pub mod transient;
 /// Pipeline states
 pub mod pso;
+pub mod slice;
 /*
 /// Shaders
 pub mod shade;
@@ -127,6 +135,8 @@ pub mod shade;
 pub mod macros;
 
 use std::collections::VecDeque;
+use std::error::Error;
+use std::{fmt, mem};
 use hal::{
     Capability, CommandQueue, PhysicalDevice, Surface, Swapchain,
     Device as Device_,
@@ -165,13 +175,42 @@ impl<B: Backend, C: hal::Capability> Queue<B, C> {
     }
 }
 
+/// Sizes of the host buffers of a `Context`.
+#[derive(Clone, Copy, Debug)]
+pub struct ContextConfig {
+    /// Size in bytes of the ring buffer of the uploader.
+    pub upload_size: u64,
+    /// Size in bytes of the buffer of the transient allocator.
+    pub transient_size: u64,
+}
+
+impl Default for ContextConfig {
+    fn default() -> Self {
+        ContextConfig {
+            upload_size: 16 << 20,
+            transient_size: 4 << 20,
+        }
+    }
+}
+
 pub struct Context<B: Backend, C> {
     surface: B::Surface,
+    physical_device: B::PhysicalDevice,
     device: Device<B>,
     queue: Queue<B, C>,
-    swapchain: B::Swapchain,
+    // option for owned recreation
+    swapchain: Option<B::Swapchain>,
+    color_format: format::Format,
+    // extent of the backbuffers
+    extent: hal::window::Extent2D,
+    backbuffers: Vec<handle::raw::Image<B>>,
+    // opaque framebuffer of the surface, with the image rendered in its place
+    framebuffer: Option<(B::Framebuffer, handle::raw::Image<B>)>,
+    init_tokens: Vec<device::InitToken<B>>,
     frame_bundles: VecDeque<FrameBundle<B, C>>,
     frame_acquired: Option<FrameBundle<B, C>>,
+    uploader: upload::Uploader<B>,
+    transient: transient::TransientAllocator<B>,
     garbage: handle::GarbageCollector<B>,
 }
 
@@ -179,6 +218,25 @@ pub struct Backbuffer<B: Backend, Cf: AsFormat> {
     pub color: handle::Image<B, Cf>,
 }
 
+/// Error returned when the swapchain was out of date and has been recreated.
+///
+/// The new backbuffers have to be retrieved with `Context::backbuffers`.
+/// While the surface has no area, the swapchain is only recreated by a later call.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SwapchainRecreated;
+
+impl fmt::Display for SwapchainRecreated {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.description())
+    }
+}
+
+impl Error for SwapchainRecreated {
+    fn description(&self) -> &str {
+        "The swapchain was out of date and has been recreated"
+    }
+}
+
 use self::Signal::*;
 #[derive(PartialEq)]
 enum Signal {
@@ -203,6 +261,12 @@ struct FrameBundle<B: Backend, C> {
     handles: handle::Bag<B>,
     access_info: encoder::AccessInfo<B>,
     encoder_pools: Vec<encoder::PoolDependency<B, C>>,
+    // ring space of the uploads submitted with the frame
+    upload_release: Option<upload::Release>,
+    // transient space used by the frame
+    transient_release: Option<transient::Release>,
+    // descriptor sets dropped during the frame
+    descriptor_release: Option<descriptors::Release>,
     // wait until the backbuffer image is ready
     wait_semaphore: B::Semaphore,
     // signal when the frame is done
@@ -210,90 +274,249 @@ struct FrameBundle<B: Backend, C> {
     signal_fence: Sync<B::Fence>,
 }
 
+impl<B: Backend, C> FrameBundle<B, C> {
+    fn new(device: &B::Device) -> Self {
+        FrameBundle {
+            handles: handle::Bag::new(),
+            access_info: encoder::AccessInfo::new(),
+            encoder_pools: Vec::new(),
+            upload_release: None,
+            transient_release: None,
+            descriptor_release: None,
+            wait_semaphore: device.create_semaphore(),
+            signal_semaphore: device.create_semaphore(),
+            signal_fence: Sync::reached(
+                device.create_fence(true)),
+        }
+    }
+
+    fn destroy(self, device: &B::Device) {
+        device.destroy_semaphore(self.wait_semaphore);
+        device.destroy_semaphore(self.signal_semaphore);
+        device.destroy_fence(self.signal_fence.inner);
+    }
+}
+
 impl<B: Backend, C> Context<B, C>
     where C: Capability + Supports<Transfer>
 {
     pub fn init<Cf>(
-        mut surface: B::Surface, mut adapter: hal::Adapter<B>
+        surface: B::Surface, adapter: hal::Adapter<B>
+    ) -> Result<(Self, Vec<Backbuffer<B, Cf>>), failure::Error>
+    where
+        Cf: AsFormat,
+    {
+        Self::init_with_config(surface, adapter, ContextConfig::default())
+    }
+
+    pub fn init_with_config<Cf>(
+        surface: B::Surface, mut adapter: hal::Adapter<B>, config: ContextConfig
     ) -> Result<(Self, Vec<Backbuffer<B, Cf>>), failure::Error>
     where
         Cf: AsFormat,
     {
         let memory_properties = adapter.physical_device.memory_properties();
+        let limits = adapter.physical_device.limits();
+        let format_properties: Vec<_> = (1 .. format::NUM_FORMATS)
+            .map(|index| {
+                let format: format::Format = unsafe { mem::transmute(index as u32) };
+                adapter.physical_device.format_properties(Some(format))
+            })
+            .collect();
         let (device, queues) = adapter.open_with(1, |family| {
             surface.supports_queue_family(family)
         })?;
 
         let queue = Queue::new(queues);
 
-        let swap_config = hal::SwapchainConfig::new()
-            .with_color(Cf::SELF); // TODO: check support
+        let (mut device, garbage) = Device::new(
+            device,
+            memory_properties.memory_types,
+            memory_properties.memory_heaps,
+            format_properties,
+        );
 
-        let extent = match surface.kind() {
-            hal::image::Kind::D2(width, height, _, _) => hal::window::Extent2D {width, height},
-            _ => unimplemented!(),
-        };
-        let (swapchain, backbuffer) = device.create_swapchain(&mut surface, swap_config, None, &extent);
+        let uploader = upload::Uploader::new(&mut device, &limits, config.upload_size)?;
+        let transient = transient::TransientAllocator::new(&mut device, &limits, config.transient_size)?;
 
-        let backbuffer_images = match backbuffer {
-            hal::Backbuffer::Images(images) => images,
-            hal::Backbuffer::Framebuffer(_) => unimplemented!(), //TODO
+        let extent = hal::window::Extent2D::from(surface.kind().extent());
+        let mut context = Context {
+            surface,
+            physical_device: adapter.physical_device,
+            device,
+            queue,
+            swapchain: None,
+            color_format: Cf::SELF,
+            extent,
+            backbuffers: Vec::new(),
+            framebuffer: None,
+            init_tokens: Vec::new(),
+            frame_bundles: VecDeque::new(),
+            frame_acquired: None,
+            uploader,
+            transient,
+            garbage,
         };
+        let extent = context.surface_extent().unwrap_or(context.extent);
+        context.create_swapchain(extent)?;
+        let backbuffers = context.backbuffers();
 
-        let frame_bundles = backbuffer_images
-            .iter()
-            .map(|_| FrameBundle {
-                handles: handle::Bag::new(),
-                access_info: encoder::AccessInfo::new(),
-                encoder_pools: Vec::new(),
-                wait_semaphore: device.create_semaphore(),
-                signal_semaphore: device.create_semaphore(),
-                signal_fence: Sync::reached(
-                    device.create_fence(true)),
-            }).collect();
+        Ok((context, backbuffers))
+    }
 
-        let backbuffers = backbuffer_images
+    // Extent of a new swapchain, `None` while the surface has no area (e.g. minimized window).
+    fn surface_extent(&self) -> Option<hal::window::Extent2D> {
+        let (capabilities, _) = self.surface.capabilities_and_formats(&self.physical_device);
+        let extent = capabilities.current_extent.unwrap_or_else(|| {
+            // the extent is defined by the swapchain, keep the current one
+            let bounds = capabilities.extents;
+            hal::window::Extent2D {
+                width: self.extent.width.max(bounds.start.width).min(bounds.end.width),
+                height: self.extent.height.max(bounds.start.height).min(bounds.end.height),
+            }
+        });
+        if extent.width == 0 || extent.height == 0 {
+            None
+        } else {
+            Some(extent)
+        }
+    }
+
+    fn create_swapchain(&mut self, extent: hal::window::Extent2D) -> Result<(), failure::Error> {
+        let swap_config = hal::SwapchainConfig::new()
+            .with_color(self.color_format); // TODO: check support
+
+        let old_swapchain = self.swapchain.take();
+        let (swapchain, backbuffer) = self.device.raw.create_swapchain(
+            &mut self.surface,
+            swap_config,
+            old_swapchain,
+            &extent,
+        );
+        self.swapchain = Some(swapchain);
+        self.extent = extent;
+
+        let (backbuffer_images, framebuffer) = match backbuffer {
+            hal::Backbuffer::Images(images) => (images, None),
+            hal::Backbuffer::Framebuffer(framebuffer) => (Vec::new(), Some(framebuffer)),
+        };
+
+        let kind = image::Kind::D2(extent.width, extent.height, 1, 1);
+        let format = self.color_format;
+        let features = self.device.format_properties(format).optimal_tiling;
+        self.backbuffers = backbuffer_images
             .into_iter()
             .map(|raw| {
                 let stable_access = hal::image::Access::empty();
                 let stable_layout = hal::image::Layout::Present;
-                let handle = handle::inner::Image::without_garbage(
+                handle::inner::Image::without_garbage(
                     raw,
                     image::Info {
                         aspects: format::Aspects::COLOR,
                         usage: image::Usage::TRANSFER_SRC | image::Usage::COLOR_ATTACHMENT,
-                        kind: surface.kind(),
+                        kind,
                         mip_levels: 1,
-                        format: Cf::SELF,
+                        format,
+                        features,
                         origin: image::Origin::Backbuffer,
                         stable_state: (stable_access, stable_layout),
                     },
-                );
-                Backbuffer {
-                    color: Typed::new(handle.into()),
-                }
+                ).into()
             }).collect();
 
-        let (device, garbage) = Device::new(
-            device,
-            memory_properties.memory_types,
-            memory_properties.memory_heaps,
-        );
-
-        let context = Context {
-            surface,
-            device,
-            queue,
-            swapchain,
-            frame_bundles,
-            frame_acquired: None,
-            garbage,
+        self.init_tokens.clear();
+        self.framebuffer = match framebuffer {
+            Some(framebuffer) => {
+                let mut allocator = allocators::BoxedAllocator::new(memory::Usage::Data, &self.device);
+                let (image, token) = self.device.create_image_raw(
+                    &mut allocator,
+                    image::Usage::TRANSFER_SRC | image::Usage::COLOR_ATTACHMENT,
+                    kind,
+                    1,
+                    format,
+                )?;
+                self.init_tokens.push(token);
+                self.backbuffers.push(image.clone());
+                Some((framebuffer, image))
+            }
+            None => None,
         };
 
-        Ok((context, backbuffers))
+        // an opaque framebuffer is presented from a single image
+        let device: &B::Device = &self.device.raw;
+        self.frame_bundles = self.backbuffers
+            .iter()
+            .map(|_| FrameBundle::new(device))
+            .collect();
+        Ok(())
     }
 
-    pub fn acquire_frame(&mut self) -> Frame {
+    fn recreate_swapchain(&mut self, extent: hal::window::Extent2D) -> Result<(), failure::Error> {
+        self.wait_idle();
+        let bundles: Vec<_> = self.frame_bundles.drain(..).collect();
+        for mut bundle in bundles {
+            self.release_bundle(&mut bundle);
+            bundle.destroy(&self.device.raw);
+        }
+        self.garbage.collect();
+        self.create_swapchain(extent)
+    }
+
+    /// Recreate the swapchain with the given extent, returning the new backbuffers.
+    ///
+    /// Waits for all the frames in flight to complete. The extent must not be
+    /// zero, resizing has to be skipped while the window is minimized.
+    pub fn resize<Cf>(
+        &mut self,
+        extent: hal::window::Extent2D,
+    ) -> Result<Vec<Backbuffer<B, Cf>>, failure::Error>
+    where
+        Cf: AsFormat,
+    {
+        assert!(self.frame_acquired.is_none());
+        assert!(extent.width != 0 && extent.height != 0, "resizing to an empty extent");
+        self.recreate_swapchain(extent)?;
+        Ok(self.backbuffers())
+    }
+
+    /// Extent of the backbuffers of the current swapchain.
+    pub fn backbuffer_extent(&self) -> hal::window::Extent2D {
+        self.extent
+    }
+
+    /// Backbuffers of the current swapchain, indexed by frame id.
+    pub fn backbuffers<Cf: AsFormat>(&self) -> Vec<Backbuffer<B, Cf>> {
+        assert_eq!(Cf::SELF, self.color_format, "backbuffer format mismatch");
+        self.backbuffers
+            .iter()
+            .map(|image| Backbuffer { color: Typed::new(image.clone()) })
+            .collect()
+    }
+
+    // Make the resources used by an executed frame available again.
+    fn release_bundle(&mut self, bundle: &mut FrameBundle<B, C>) {
+        if let Some(release) = bundle.upload_release.take() {
+            self.uploader.release(release);
+        }
+        if let Some(release) = bundle.transient_release.take() {
+            self.transient.release(release);
+        }
+        if let Some(release) = bundle.descriptor_release.take() {
+            self.device.descriptor_allocator().release(release);
+        }
+
+        bundle.handles.clear();
+        bundle.access_info.end_gpu_access();
+        bundle.access_info.clear();
+        bundle.encoder_pools.clear();
+    }
+
+    /// Acquire the next frame.
+    ///
+    /// If the swapchain is out of date, it is recreated with the current extent
+    /// of the surface instead. The recreation is skipped while the surface has
+    /// no area, so `SwapchainRecreated` is returned until it can be done.
+    pub fn acquire_frame(&mut self) -> Result<Frame, SwapchainRecreated> {
         assert!(self.frame_acquired.is_none());
 
         let mut bundle = self.frame_bundles.pop_front()
@@ -304,31 +527,74 @@ impl<B: Backend, C> Context<B, C>
         }
         self.device.raw.reset_fence(&bundle.signal_fence.inner);
         bundle.signal_fence.signal = Reached;
+        self.release_bundle(&mut bundle);
 
-        bundle.handles.clear();
-        bundle.access_info.end_gpu_access();
-        bundle.access_info.clear();
-        bundle.encoder_pools.clear();
-
-        let frame = self.swapchain.acquire_frame(
+        let frame = self.swapchain.as_mut().unwrap().acquire_frame(
             hal::FrameSync::Semaphore(&mut bundle.wait_semaphore)
-        ).expect("Failed to acquire frame.");
-        self.frame_acquired = Some(bundle);
+        );
+        match frame {
+            Ok(frame) => {
+                self.frame_acquired = Some(bundle);
+                self.garbage.collect();
+                Ok(frame)
+            }
+            Err(()) => {
+                self.frame_bundles.push_front(bundle);
+                if let Some(extent) = self.surface_extent() {
+                    self.recreate_swapchain(extent)
+                        .expect("Failed to recreate the swapchain.");
+                }
+                Err(SwapchainRecreated)
+            }
+        }
+    }
 
-        self.garbage.collect();
-        frame
+    /// Uploader whose requests are submitted with the next presented frame.
+    pub fn uploader(&self) -> &upload::Uploader<B> {
+        &self.uploader
+    }
+
+    /// Allocator of uniform data only valid for the currently acquired frame.
+    pub fn transient(&self) -> &transient::TransientAllocator<B> {
+        &self.transient
     }
 
     pub fn acquire_encoder_pool(&mut self) -> encoder::Pool<B, C> {
         self.queue.acquire_encoder_pool(&self.device.raw)
     }
 
+    /// Submit and present the acquired frame.
+    ///
+    /// If the swapchain is out of date, it is recreated with the current extent
+    /// of the surface after the submission.
     // TODO: allow submissions before present
-    pub fn present(&mut self, submits: Vec<encoder::Submit<B, C>>) {
+    pub fn present(&mut self, submits: Vec<encoder::Submit<B, C>>) -> Result<(), SwapchainRecreated> {
         let mut bundle = self.frame_acquired.take()
             .expect("no acquired frame");
 
-        let inner_submits: Vec<_> = submits.into_iter()
+        let upload = if self.uploader.has_pending() || !self.init_tokens.is_empty() {
+            let mut pool = self.queue.acquire_encoder_pool(&self.device.raw);
+            let mut encoder = pool.acquire_encoder();
+            if !self.init_tokens.is_empty() {
+                encoder.init_resources(mem::replace(&mut self.init_tokens, Vec::new()));
+            }
+            bundle.upload_release = self.uploader.record(&mut encoder);
+            Some(encoder.finish())
+        } else {
+            None
+        };
+        let blit = if let Some((ref framebuffer, ref image)) = self.framebuffer {
+            let mut pool = self.queue.acquire_encoder_pool(&self.device.raw);
+            let mut encoder = pool.acquire_encoder();
+            encoder.blit_to_framebuffer(image, framebuffer);
+            Some(encoder.finish())
+        } else {
+            None
+        };
+        bundle.transient_release = Some(self.transient.flush());
+        bundle.descriptor_release = Some(self.device.descriptor_allocator().flush());
+
+        let inner_submits: Vec<_> = upload.into_iter().chain(submits).chain(blit)
             .map(|mut submit| {
                 bundle.handles.append(&mut submit.handles);
                 bundle.access_info.append(&mut submit.access_info);
@@ -349,12 +615,20 @@ impl<B: Backend, C> Context<B, C>
         }
         bundle.signal_fence.signal = Pending;
 
-        self.swapchain.present(
+        let result = self.swapchain.as_mut().unwrap().present(
             &mut self.queue.group.queues[0],
             Some(&bundle.signal_semaphore),
-        ).expect("Failed to present.");
-
+        );
         self.frame_bundles.push_back(bundle);
+
+        if result.is_err() {
+            if let Some(extent) = self.surface_extent() {
+                self.recreate_swapchain(extent)
+                    .expect("Failed to recreate the swapchain.");
+            }
+            return Err(SwapchainRecreated);
+        }
+        Ok(())
     }
 }
 
@@ -400,9 +674,7 @@ impl<B: Backend, C> Drop for Context<B, C> {
         self.garbage.collect();
 
         for bundle in self.frame_bundles.drain(..) {
-            self.device.raw.destroy_semaphore(bundle.wait_semaphore);
-            self.device.raw.destroy_semaphore(bundle.signal_semaphore);
-            self.device.raw.destroy_fence(bundle.signal_fence.inner);
+            bundle.destroy(&self.device.raw);
         }
     }
 }
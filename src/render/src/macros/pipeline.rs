@@ -47,33 +47,24 @@ macro_rules! gfx_graphics_pipeline {
                     $( desc_layouts.extend(<$cmp as pso::Component<'a, B>>::descriptor_layout(&self.$cmp_name)); )*
                     let layout = device.create_pipeline_layout_raw(&desc_layouts[..], &[]);
                     let render_pass = {
-                        let mut attachments = Vec::new();
-                        let mut color_attachments = Vec::new();
+                        let mut desc = cpass::RenderPassDesc::new();
+                        let mut subpass = cpass::OwnedSubpassDesc::default();
                         $(
                             if let Some(attach) = <$cmp as pso::Component<'a, B>>::attachment(&self.$cmp_name) {
-                                let attach_id = attachments.len();
-                                attachments.push(cpass::Attachment {
+                                subpass.colors.push((desc.attachments.len(), attach.required_layout));
+                                desc.attachments.push(cpass::Attachment {
                                     format: Some(attach.format),
                                     samples: 1,
                                     ops: attach.ops,
                                     stencil_ops: attach.stencil_ops,
                                     layouts: attach.required_layout .. attach.required_layout,
                                 });
-                                color_attachments.push((attach_id, attach.required_layout));
                             }
                         )*
-                        let subpass = cpass::SubpassDesc {
-                            colors: &color_attachments[..],
-                            depth_stencil: None, //TODO
-                            inputs: &[],
-                            preserves: &[],
-                            resolves: &[],
-                        };
-
-                        device.create_render_pass_raw(&attachments[..], &[subpass], &[])
+                        //TODO: depth_stencil
+                        device.render_pass(&desc.with_subpass(subpass))
                     };
 
-
                     let pipeline = {
                         let subpass = cpass::Subpass {
                             index: 0,
@@ -106,6 +97,25 @@ macro_rules! gfx_graphics_pipeline {
             impl<'a, B: Backend> pso::GraphicsPipelineData<B> for Data<'a, B> {
                 type Pipeline = Meta<B>;
 
+                fn require<'b>(
+                    &'b self,
+                    buffer_states: &mut Vec<(&'b handle::raw::Buffer<B>, $crate::hal::buffer::State)>,
+                    image_states: &mut Vec<(
+                        &'b handle::raw::Image<B>,
+                        $crate::hal::image::Subresource,
+                        $crate::hal::image::State,
+                    )>,
+                    handles: &mut handle::Bag<B>,
+                ) {
+                    $(
+                        <$cmp as pso::Component<'a, B>>::require(
+                            &self.$cmp_name,
+                            buffer_states,
+                            image_states,
+                            handles);
+                    )*
+                }
+
                 fn begin_renderpass<'b, 'c, C>(
                     self,
                     encoder: &'b mut Encoder<'c, B, C>,
@@ -113,21 +123,6 @@ macro_rules! gfx_graphics_pipeline {
                 ) -> RenderPassInlineEncoder<'b, B, Primary>
                     where Self: 'a, 'c: 'b, C: Supports<Transfer> + Supports<Graphics>
                 {
-                    let mut buffer_states = Vec::new();
-                    let mut image_states = Vec::new();
-                    $(
-                        <$cmp as pso::Component<'a, B>>::require(
-                            &self.$cmp_name,
-                            &mut buffer_states,
-                            &mut image_states,
-                            encoder.handles());
-                    )*
-                    encoder.require_state(
-                        cpso::PipelineStage::VERTEX_INPUT,
-                        &buffer_states[..],
-                        &image_states[..]
-                    );
-
                     let cmd_buffer = encoder.mut_buffer();
                     cmd_buffer.set_viewports(0, self.viewports);
                     cmd_buffer.set_scissors(0, self.scissors);
@@ -141,7 +136,11 @@ macro_rules! gfx_graphics_pipeline {
                     $(
                         descs.extend(<$cmp as pso::Component<'a, B>>::descriptor_set(&self.$cmp_name));
                     )*
-                    cmd_buffer.bind_graphics_descriptor_sets(meta.layout.resource(), 0, descs, &[]);
+                    let mut offsets = Vec::new();
+                    $(
+                        <$cmp as pso::Component<'a, B>>::dynamic_offsets(&self.$cmp_name, &mut offsets);
+                    )*
+                    cmd_buffer.bind_graphics_descriptor_sets(meta.layout.resource(), 0, descs, &offsets[..]);
                     // TODO: difference with viewport ?
                     let extent = self.framebuffer.info().extent;
                     let render_rect = pso::Rect {
@@ -161,3 +160,98 @@ macro_rules! gfx_graphics_pipeline {
         }
     }
 }
+
+#[macro_export]
+macro_rules! gfx_compute_pipeline {
+    ($name:ident {
+        $( $cmp_name:ident: $cmp:ty, )*
+    }) => {
+        #[allow(missing_docs)]
+        pub mod $name {
+            #[allow(unused_imports)]
+            use super::*;
+            use $crate::{pso, handle};
+            use $crate::{Backend, Supports, Transfer, Compute, Encoder, Device};
+            use $crate::hal::pso as cpso;
+
+            pub struct Meta<B: Backend> {
+                layout: handle::raw::PipelineLayout<B>,
+                pipeline: handle::raw::ComputePipeline<B>,
+            }
+
+            pub struct Init<'a, B: Backend> {
+                $( pub $cmp_name: <$cmp as pso::Component<'a, B>>::Init, )*
+            }
+
+            pub struct Data<'a, B: Backend> {
+                $( pub $cmp_name: <$cmp as pso::Component<'a, B>>::Data, )*
+            }
+
+            impl<'a, B: Backend> pso::ComputePipelineInit<B> for Init<'a, B> {
+                type Pipeline = Meta<B>;
+
+                fn create<'b>(
+                    self,
+                    device: &mut Device<B>,
+                    shader: cpso::EntryPoint<'b, B>,
+                ) -> Result<Self::Pipeline, pso::CreationError> {
+                    let mut desc_layouts = Vec::new();
+                    $( desc_layouts.extend(<$cmp as pso::Component<'a, B>>::descriptor_layout(&self.$cmp_name)); )*
+                    let layout = device.create_pipeline_layout_raw(&desc_layouts[..], &[]);
+                    let pipeline = {
+                        let pipeline_desc = cpso::ComputePipelineDesc::new(shader, layout.resource());
+                        device.create_compute_pipeline_raw(pipeline_desc)?
+                    };
+                    Ok(Meta { layout, pipeline })
+                }
+            }
+
+            impl<B: Backend> pso::ComputePipelineMeta<B> for Meta<B> {
+                fn layout(&self) -> &B::PipelineLayout { self.layout.resource() }
+            }
+
+            impl<'a, B: Backend> pso::ComputePipelineData<B> for Data<'a, B> {
+                type Pipeline = Meta<B>;
+
+                fn require<'b>(
+                    &'b self,
+                    buffer_states: &mut Vec<(&'b handle::raw::Buffer<B>, $crate::hal::buffer::State)>,
+                    image_states: &mut Vec<(
+                        &'b handle::raw::Image<B>,
+                        $crate::hal::image::Subresource,
+                        $crate::hal::image::State,
+                    )>,
+                    handles: &mut handle::Bag<B>,
+                ) {
+                    $(
+                        <$cmp as pso::Component<'a, B>>::require(
+                            &self.$cmp_name,
+                            buffer_states,
+                            image_states,
+                            handles);
+                    )*
+                }
+
+                fn bind<'b, 'c, C>(
+                    self,
+                    encoder: &'b mut Encoder<'c, B, C>,
+                    meta: &'b Self::Pipeline
+                )
+                    where Self: 'a, 'c: 'b, C: Supports<Transfer> + Supports<Compute>
+                {
+                    let cmd_buffer = encoder.mut_buffer();
+                    cmd_buffer.bind_compute_pipeline(meta.pipeline.resource());
+                    let mut descs = Vec::new();
+                    $(
+                        descs.extend(<$cmp as pso::Component<'a, B>>::descriptor_set(&self.$cmp_name));
+                    )*
+                    let mut offsets = Vec::new();
+                    $(
+                        <$cmp as pso::Component<'a, B>>::dynamic_offsets(&self.$cmp_name, &mut offsets);
+                    )*
+                    cmd_buffer.bind_compute_descriptor_sets(meta.layout.resource(), 0, descs, &offsets[..]);
+                }
+            }
+        }
+    }
+}
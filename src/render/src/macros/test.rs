@@ -23,5 +23,34 @@ gfx_graphics_pipeline! {
     }
 }
 
+gfx_descriptors! {
+    particles {
+        positions: pso::StorageBuffer,
+        velocities: pso::StorageBuffer,
+        params: pso::UniformBuffer,
+    }
+}
+
+gfx_compute_pipeline! {
+    simulate {
+        desc: particles::Component,
+    }
+}
+
+gfx_descriptors! {
+    object {
+        transform: pso::UniformBufferDynamic,
+    }
+}
+
+gfx_graphics_pipeline! {
+    objects {
+        desc: object::Component,
+        offsets: pso::DynamicOffsets,
+        color: pso::RenderTarget<format::Rgba8Srgb>,
+        vertices: pso::VertexBuffer<Vertex>,
+    }
+}
+
 #[test]
 fn test_macros() {}
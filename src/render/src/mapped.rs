@@ -0,0 +1,331 @@
+//! Persistently mapped host buffers.
+//!
+//! A `MappedBuffer` owns a buffer in upload memory which stays mapped for its
+//! whole lifetime, its space is handed out as a ring. It backs both the
+//! `Uploader` and the `TransientAllocator`.
+
+use std::error::Error;
+use std::ops::Range;
+use std::sync::Arc;
+use std::{cmp, fmt};
+
+use hal::{self, Device as Device_};
+use hal::mapping::Persistent;
+
+use handle::Garbage;
+use handle::inner::Buffer;
+use memory::{self, Memory};
+use {buffer, handle};
+use {Backend, Device};
+
+/// Error of an allocation in a mapped buffer or of the creation of the buffer.
+#[derive(Debug)]
+pub enum MappedBufferError {
+    /// The buffer has not enough free space left for the request.
+    OutOfSpace,
+    /// The buffer could not be created.
+    Creation(buffer::CreationError),
+    /// No memory type is host visible for the buffer.
+    NoMemoryType,
+    /// The memory of the buffer could not be allocated.
+    OutOfMemory(hal::device::OutOfMemory),
+    /// The buffer could not be bound to its memory.
+    Binding(hal::device::BindError),
+    /// The memory of the buffer could not be mapped.
+    Mapping(hal::mapping::Error),
+}
+
+impl fmt::Display for MappedBufferError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            MappedBufferError::OutOfSpace |
+            MappedBufferError::NoMemoryType => write!(f, "{}", self.description()),
+            MappedBufferError::Creation(ref e) => write!(f, "{}: {}", self.description(), e),
+            MappedBufferError::OutOfMemory(ref e) => write!(f, "{}: {}", self.description(), e),
+            MappedBufferError::Binding(ref e) => write!(f, "{}: {}", self.description(), e),
+            MappedBufferError::Mapping(ref e) => write!(f, "{}: {}", self.description(), e),
+        }
+    }
+}
+
+impl Error for MappedBufferError {
+    fn description(&self) -> &str {
+        match *self {
+            MappedBufferError::OutOfSpace => "Not enough space left in the mapped buffer",
+            MappedBufferError::Creation(_) => "Failed to create the mapped buffer",
+            MappedBufferError::NoMemoryType => "No host visible memory type for the mapped buffer",
+            MappedBufferError::OutOfMemory(_) => "Failed to allocate the mapped buffer",
+            MappedBufferError::Binding(_) => "Failed to bind the mapped buffer to its memory",
+            MappedBufferError::Mapping(_) => "Failed to map the mapped buffer",
+        }
+    }
+
+    fn cause(&self) -> Option<&Error> {
+        match *self {
+            MappedBufferError::OutOfSpace |
+            MappedBufferError::NoMemoryType => None,
+            MappedBufferError::Creation(ref e) => Some(e),
+            MappedBufferError::OutOfMemory(ref e) => Some(e),
+            MappedBufferError::Binding(ref e) => Some(e),
+            MappedBufferError::Mapping(ref e) => Some(e),
+        }
+    }
+}
+
+impl From<buffer::CreationError> for MappedBufferError {
+    fn from(e: buffer::CreationError) -> Self {
+        MappedBufferError::Creation(e)
+    }
+}
+
+impl From<hal::device::OutOfMemory> for MappedBufferError {
+    fn from(e: hal::device::OutOfMemory) -> Self {
+        MappedBufferError::OutOfMemory(e)
+    }
+}
+
+impl From<hal::device::BindError> for MappedBufferError {
+    fn from(e: hal::device::BindError) -> Self {
+        MappedBufferError::Binding(e)
+    }
+}
+
+impl From<hal::mapping::Error> for MappedBufferError {
+    fn from(e: hal::mapping::Error) -> Self {
+        MappedBufferError::Mapping(e)
+    }
+}
+
+pub(crate) fn align(value: u64, alignment: u64) -> u64 {
+    (value + alignment - 1) / alignment * alignment
+}
+
+pub(crate) fn lcm(a: u64, b: u64) -> u64 {
+    let (mut x, mut y) = (a, b);
+    while y != 0 {
+        let r = x % y;
+        x = y;
+        y = r;
+    }
+    a / x * b
+}
+
+/// Space bookkeeping of a ring buffer.
+#[derive(Debug)]
+pub(crate) struct RingSpace {
+    size: u64,
+    // positions grow monotonically, the ring offset is `position % size`
+    // bytes in `tail .. head` are in use
+    head: u64,
+    tail: u64,
+}
+
+impl RingSpace {
+    pub(crate) fn new(size: u64) -> Self {
+        RingSpace { size, head: 0, tail: 0 }
+    }
+
+    /// Returns the ring offset of `size` bytes, if there is enough free space.
+    pub(crate) fn allocate(&mut self, size: u64, alignment: u64) -> Result<u64, MappedBufferError> {
+        let offset = self.head % self.size;
+        let aligned = align(offset, alignment);
+        let start = if aligned + size > self.size {
+            // wrap around, skipping the end of the ring
+            self.head - offset + self.size
+        } else {
+            self.head - offset + aligned
+        };
+        if start + size - self.tail > self.size {
+            return Err(MappedBufferError::OutOfSpace);
+        }
+        self.head = start + size;
+        Ok(start % self.size)
+    }
+
+    /// Make the space used up to position `end` available again.
+    pub(crate) fn release(&mut self, end: u64) {
+        self.tail = cmp::max(self.tail, end);
+    }
+
+    /// Ring ranges written between position `start` and the head.
+    fn ranges_since(&self, start: u64) -> Vec<Range<u64>> {
+        let len = self.head - start;
+        let start = start % self.size;
+        if len >= self.size {
+            vec![0 .. self.size]
+        } else if start + len > self.size {
+            vec![start .. self.size, 0 .. start + len - self.size]
+        } else {
+            vec![start .. start + len]
+        }
+    }
+}
+
+/// Buffer in upload memory, mapped for its whole lifetime and used as a ring.
+pub(crate) struct MappedBuffer<B: Backend> {
+    device: Arc<B::Device>,
+    buffer: handle::raw::Buffer<B>,
+    // options for owned drop, the memory is shared with the release of `buffer`
+    memory: Option<Arc<B::Memory>>,
+    mapping: Option<Persistent<B>>,
+    space: RingSpace,
+    // position up to which the writes have been flushed
+    flushed: u64,
+}
+
+impl<B: Backend> Drop for MappedBuffer<B> {
+    fn drop(&mut self) {
+        let memory = self.memory.take().unwrap();
+        self.device.release_persistent_mapping(&memory, self.mapping.take().unwrap());
+        // the memory is freed with the buffer handle, once it can't be used anymore
+        drop(memory);
+    }
+}
+
+impl<B: Backend> MappedBuffer<B> {
+    /// Create a buffer of `size` bytes in upload memory and map it.
+    pub(crate) fn new(
+        device: &mut Device<B>,
+        usage: buffer::Usage,
+        size: u64,
+    ) -> Result<Self, MappedBufferError> {
+        let unbound = device.raw.create_buffer(size, usage)?;
+        let requirements = device.raw.get_buffer_requirements(&unbound);
+        let memory_type = device.find_upload_memory(requirements.type_mask)
+            .ok_or(MappedBufferError::NoMemoryType)?;
+        let memory = device.raw.allocate_memory(memory_type, requirements.size)?;
+        let raw = match device.raw.bind_buffer_memory(&memory, 0, unbound) {
+            Ok(raw) => raw,
+            Err(e) => {
+                device.raw.free_memory(memory);
+                return Err(e.into());
+            }
+        };
+        let mapping = match device.raw.acquire_persistent_mapping(&memory, 0 .. size) {
+            Ok(mapping) => mapping,
+            Err(e) => {
+                device.raw.destroy_buffer(raw);
+                device.raw.free_memory(memory);
+                return Err(e.into());
+            }
+        };
+        // the buffer is sent to the garbage before its memory on release,
+        // so the memory is only freed after the buffer has been destroyed
+        let memory = Arc::new(memory);
+        let release = {
+            let mut memory = Some(memory.clone());
+            let garbage = device.garbage.clone();
+            Box::new(move || {
+                let memory = Arc::try_unwrap(memory.take().unwrap())
+                    .unwrap_or_else(|_| panic!("Mapped buffer memory released while in use"));
+                garbage.send(Garbage::Memory(memory))
+                    .unwrap_or_else(|e| error!("Could not free mapped buffer memory: {}", e));
+            })
+        };
+        let info = buffer::Info::new(usage, Memory::new(release, memory::Usage::Upload), size, 1);
+        let buffer = handle::raw::Buffer::from(Buffer::new(raw, info, device.garbage.clone()));
+
+        Ok(MappedBuffer {
+            device: device.raw.clone(),
+            buffer,
+            memory: Some(memory),
+            mapping: Some(mapping),
+            space: RingSpace::new(size),
+            flushed: 0,
+        })
+    }
+
+    pub(crate) fn buffer(&self) -> &handle::raw::Buffer<B> {
+        &self.buffer
+    }
+
+    /// Position up to which space has been allocated.
+    pub(crate) fn head(&self) -> u64 {
+        self.space.head
+    }
+
+    /// Returns the offset of `size` free bytes.
+    pub(crate) fn allocate(&mut self, size: u64, alignment: u64) -> Result<u64, MappedBufferError> {
+        self.space.allocate(size, alignment)
+    }
+
+    pub(crate) fn write<T: Copy>(&mut self, offset: u64, data: &[T]) {
+        self.mapping.as_mut().unwrap()
+            .write(offset, data)
+            .expect("write out of mapped buffer bounds");
+    }
+
+    /// Make the writes since the last flush visible to the device.
+    pub(crate) fn flush(&mut self) {
+        if self.space.head == self.flushed {
+            return;
+        }
+        let memory = self.memory.as_ref().unwrap();
+        let mapping = self.mapping.as_ref().unwrap();
+        for range in self.space.ranges_since(self.flushed) {
+            self.device.flush_persistent_mapping(memory, mapping, range);
+        }
+        self.flushed = self.space.head;
+    }
+
+    /// Make the space allocated up to position `end` available again.
+    pub(crate) fn release(&mut self, end: u64) {
+        self.space.release(end);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RingSpace;
+
+    #[test]
+    fn wrapping_allocations() {
+        let mut space = RingSpace::new(256);
+        assert_eq!(space.allocate(100, 1).ok(), Some(0));
+        assert_eq!(space.allocate(100, 64).ok(), Some(128));
+        // doesn't fit at the end, and the start is still in use
+        assert_eq!(space.allocate(64, 1).ok(), None);
+        space.release(100);
+        assert_eq!(space.allocate(64, 1).ok(), Some(0));
+        assert_eq!(space.head, 320);
+        assert_eq!(space.ranges_since(228), vec![228 .. 256, 0 .. 64]);
+        assert_eq!(space.ranges_since(100), vec![100 .. 256, 0 .. 64]);
+        assert_eq!(space.ranges_since(64), vec![0 .. 256]);
+    }
+
+    #[test]
+    fn exact_fill() {
+        let mut space = RingSpace::new(256);
+        assert_eq!(space.allocate(128, 1).ok(), Some(0));
+        assert_eq!(space.allocate(128, 1).ok(), Some(128));
+        assert_eq!(space.allocate(1, 1).ok(), None);
+        assert_eq!(space.ranges_since(0), vec![0 .. 256]);
+        space.release(128);
+        assert_eq!(space.allocate(128, 1).ok(), Some(0));
+        assert_eq!(space.allocate(1, 1).ok(), None);
+    }
+
+    #[test]
+    fn oversized_requests() {
+        let mut space = RingSpace::new(256);
+        assert_eq!(space.allocate(257, 1).ok(), None);
+        assert_eq!(space.head, 0);
+        // the alignment padding counts against the free space
+        assert_eq!(space.allocate(1, 1).ok(), Some(0));
+        space.release(1);
+        assert_eq!(space.allocate(256, 1).ok(), None);
+        assert_eq!(space.allocate(255, 1).ok(), Some(1));
+    }
+
+    #[test]
+    fn release_is_monotonic() {
+        let mut space = RingSpace::new(256);
+        assert_eq!(space.allocate(200, 1).ok(), Some(0));
+        space.release(200);
+        // releases of older submissions may come in late
+        space.release(100);
+        assert_eq!(space.tail, 200);
+        assert_eq!(space.allocate(200, 1).ok(), Some(0));
+        assert_eq!(space.head, 456);
+    }
+}
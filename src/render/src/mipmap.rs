@@ -0,0 +1,281 @@
+//! Mip chain generation by rendering, for image formats which can't be blitted.
+//!
+//! Each level is drawn from the previous one with a fullscreen triangle sampling it.
+//! The shaders are found in the `shaders` directory of the crate, the SPIR-V
+//! binaries have to be regenerated with `make_vulkan.sh` after editing them.
+
+use std::collections::HashMap;
+
+use hal::{self, Device as Device_};
+use hal::command::{ClearValue, DescriptorSetOffset};
+use hal::format::{ChannelType, ImageFeature};
+use hal::image::{Access, Filter, Layout, WrapMode};
+use hal::pass::{Attachment, AttachmentLoadOp, AttachmentOps, AttachmentStoreOp};
+use hal::pass::{OwnedSubpassDesc, RenderPassDesc, Subpass};
+use hal::pso::{
+    BlendState, ColorBlendDesc, ColorMask, Descriptor, DescriptorSetLayoutBinding,
+    DescriptorSetWrite, DescriptorType, EntryPoint, GraphicsPipelineDesc, GraphicsShaderSet,
+    PipelineStage, Rasterizer, Rect, ShaderStageFlags, Viewport,
+};
+
+use encoder::MipmapError;
+use {handle, image, format, pso};
+use {Backend, Device, Encoder, Primitive, Supports, Transfer, Graphics};
+
+const VERTEX_SHADER: &[u8] = include_bytes!("../shaders/mipmap.vert.spv");
+const FRAGMENT_SHADER: &[u8] = include_bytes!("../shaders/mipmap.frag.spv");
+
+fn bindings() -> Vec<DescriptorSetLayoutBinding> {
+    vec![
+        DescriptorSetLayoutBinding {
+            binding: 0,
+            ty: DescriptorType::SampledImage,
+            count: 1,
+            stage_flags: ShaderStageFlags::FRAGMENT,
+            immutable_samplers: false,
+        },
+        DescriptorSetLayoutBinding {
+            binding: 1,
+            ty: DescriptorType::Sampler,
+            count: 1,
+            stage_flags: ShaderStageFlags::FRAGMENT,
+            immutable_samplers: false,
+        },
+    ]
+}
+
+/// Pipelines generating the mip chain of images which can't be blitted.
+///
+/// Passed to `Encoder::generate_mipmap`, it renders the levels of color images
+/// whose format supports sampling and color attachments, except for integer formats
+/// and 3D images. The pipeline of a format is created on first use.
+pub struct MipmapFallback<B: Backend> {
+    device: Device<B>,
+    // option for owned drop
+    shaders: Option<(B::ShaderModule, B::ShaderModule)>,
+    set_layout: handle::raw::DescriptorSetLayout<B>,
+    layout: handle::raw::PipelineLayout<B>,
+    linear: handle::raw::Sampler<B>,
+    nearest: handle::raw::Sampler<B>,
+    pipelines: HashMap<format::Format, (handle::raw::RenderPass<B>, handle::raw::GraphicsPipeline<B>)>,
+}
+
+impl<B: Backend> MipmapFallback<B> {
+    /// Create the shaders and the pipeline layout shared by all formats.
+    pub fn new(device: &Device<B>) -> Result<Self, hal::device::ShaderError> {
+        let mut device = device.clone();
+        let vertex = device.raw.create_shader_module(VERTEX_SHADER)?;
+        let fragment = match device.raw.create_shader_module(FRAGMENT_SHADER) {
+            Ok(fragment) => fragment,
+            Err(e) => {
+                device.raw.destroy_shader_module(vertex);
+                return Err(e);
+            }
+        };
+        let set_layout = device.create_descriptor_set_layout(&bindings());
+        let layout = device.create_pipeline_layout_raw(&[set_layout.resource()], &[]);
+        let linear = device.create_sampler(image::SamplerInfo::new(Filter::Linear, WrapMode::Clamp));
+        let nearest = device.create_sampler(image::SamplerInfo::new(Filter::Nearest, WrapMode::Clamp));
+        Ok(MipmapFallback {
+            device,
+            shaders: Some((vertex, fragment)),
+            set_layout,
+            layout,
+            linear,
+            nearest,
+            pipelines: HashMap::new(),
+        })
+    }
+
+    /// Whether the levels of `image` can be rendered by the fallback.
+    pub fn supports(&self, info: &image::Info) -> bool {
+        let features = ImageFeature::SAMPLED | ImageFeature::COLOR_ATTACHMENT;
+        let integer = match info.format.base_format().1 {
+            ChannelType::Uint | ChannelType::Int => true,
+            _ => false,
+        };
+        let volume = match info.kind {
+            image::Kind::D3(..) => true,
+            _ => false,
+        };
+        info.aspects == format::Aspects::COLOR && info.features.contains(features) && !integer && !volume
+    }
+
+    fn pipeline(
+        &mut self,
+        format: format::Format,
+    ) -> Result<(handle::raw::RenderPass<B>, handle::raw::GraphicsPipeline<B>), pso::CreationError> {
+        if let Some(pipeline) = self.pipelines.get(&format) {
+            return Ok(pipeline.clone());
+        }
+
+        let layout = Layout::ColorAttachmentOptimal;
+        let mut subpass = OwnedSubpassDesc::default();
+        subpass.colors.push((0, layout));
+        let render_pass_desc = RenderPassDesc::new()
+            .with_attachment(Attachment {
+                format: Some(format),
+                samples: 1,
+                ops: AttachmentOps::new(AttachmentLoadOp::DontCare, AttachmentStoreOp::Store),
+                stencil_ops: AttachmentOps::DONT_CARE,
+                layouts: layout .. layout,
+            })
+            .with_subpass(subpass);
+        let render_pass = self.device.render_pass(&render_pass_desc);
+
+        let pipeline = {
+            let (ref vertex, ref fragment) = *self.shaders.as_ref().unwrap();
+            let entry = |module| EntryPoint::<B> { entry: "main", module, specialization: &[] };
+            let shaders = GraphicsShaderSet {
+                vertex: entry(vertex),
+                hull: None,
+                domain: None,
+                geometry: None,
+                fragment: Some(entry(fragment)),
+            };
+            let subpass = Subpass { index: 0, main_pass: render_pass.resource() };
+            let mut desc = GraphicsPipelineDesc::new(
+                shaders,
+                Primitive::TriangleList,
+                Rasterizer::FILL,
+                self.layout.resource(),
+                subpass,
+            );
+            desc.blender.targets.push(ColorBlendDesc(ColorMask::ALL, BlendState::Off));
+            self.device.create_graphics_pipeline_raw(desc)?
+        };
+
+        self.pipelines.insert(format, (render_pass.clone(), pipeline.clone()));
+        Ok((render_pass, pipeline))
+    }
+
+    fn create_level_view(
+        &mut self,
+        image: &handle::raw::Image<B>,
+        level: image::Level,
+        layer: image::Layer,
+    ) -> Result<handle::raw::ImageView<B>, image::ViewError> {
+        let info = image.info();
+        let range = image::SubresourceRange {
+            aspects: info.aspects,
+            levels: level .. level + 1,
+            layers: layer .. layer + 1,
+        };
+        self.device.create_image_view_raw(image, image::ViewKind::D2, info.format, range)
+    }
+
+    /// Render the levels of `image` from level 0, see `Encoder::generate_mipmap`.
+    pub(crate) fn record<C>(
+        &mut self,
+        encoder: &mut Encoder<B, C>,
+        image: &handle::raw::Image<B>,
+    ) -> Result<(), MipmapError>
+    where
+        C: Supports<Transfer> + Supports<Graphics>,
+    {
+        let info = image.info();
+        if !self.supports(info) {
+            return Err(MipmapError(info.format));
+        }
+        debug_assert!(info.usage.contains(image::Usage::SAMPLED | image::Usage::COLOR_ATTACHMENT),
+            "missing SAMPLED or COLOR_ATTACHMENT usage flag");
+
+        let (render_pass, pipeline) = self.pipeline(info.format).map_err(|e| {
+            error!("could not create the mipmap pipeline for {:?}: {:?}", info.format, e);
+            MipmapError(info.format)
+        })?;
+        let sampler = if info.features.contains(ImageFeature::SAMPLED_LINEAR) {
+            self.linear.clone()
+        } else {
+            self.nearest.clone()
+        };
+        {
+            let handles = encoder.handles();
+            handles.add(render_pass.clone());
+            handles.add(pipeline.clone());
+            handles.add(self.layout.clone());
+            handles.add(self.set_layout.clone());
+            handles.add(sampler.clone());
+        }
+
+        let src_state = (Access::SHADER_READ, Layout::ShaderReadOnlyOptimal);
+        let dst_state = (Access::COLOR_ATTACHMENT_WRITE, Layout::ColorAttachmentOptimal);
+        let stages = PipelineStage::FRAGMENT_SHADER | PipelineStage::COLOR_ATTACHMENT_OUTPUT;
+        let layers = info.kind.num_layers();
+        let no_offsets: &[DescriptorSetOffset] = &[];
+        let no_clears: &[ClearValue] = &[];
+        for level in 1 .. info.mip_levels {
+            let mut image_states = Vec::new();
+            for layer in 0 .. layers {
+                let subresource = |level| image::Subresource { aspects: info.aspects, level, layer };
+                image_states.push((image, subresource(level - 1), src_state));
+                image_states.push((image, subresource(level), dst_state));
+            }
+            encoder.require_state(stages, &[], &image_states[..]);
+
+            let sets = self.device.descriptor_allocator()
+                .allocate(&self.set_layout, &bindings(), layers as usize);
+            if sets.len() < layers as usize {
+                return Err(MipmapError(info.format));
+            }
+            let extent = info.kind.level_extent(level);
+            let rect = Rect { x: 0, y: 0, w: extent.width as _, h: extent.height as _ };
+            for (layer, set) in (0 .. layers).zip(sets) {
+                let views = self.create_level_view(image, level - 1, layer)
+                    .and_then(|src| self.create_level_view(image, level, layer).map(|dst| (src, dst)));
+                let (src, dst) = views.map_err(|e| {
+                    error!("could not create the views of {:?} level {}: {:?}", info.format, level, e);
+                    MipmapError(info.format)
+                })?;
+                let framebuffer = self.device
+                    .create_framebuffer_raw(render_pass.resource(), &[&dst], image::Extent { depth: 1, .. extent })
+                    .map_err(|e| {
+                        error!("could not create the framebuffer of {:?} level {}: {:?}", info.format, level, e);
+                        MipmapError(info.format)
+                    })?;
+                self.device.raw.write_descriptor_sets(vec![
+                    DescriptorSetWrite {
+                        set: set.resource(),
+                        binding: 0,
+                        array_offset: 0,
+                        descriptors: Some(Descriptor::Image(src.resource(), src_state.1)),
+                    },
+                    DescriptorSetWrite {
+                        set: set.resource(),
+                        binding: 1,
+                        array_offset: 0,
+                        descriptors: Some(Descriptor::Sampler(sampler.resource())),
+                    },
+                ]);
+
+                {
+                    let buffer = encoder.mut_buffer();
+                    buffer.set_viewports(0, &[Viewport { rect, depth: 0.0 .. 1.0 }]);
+                    buffer.set_scissors(0, &[rect]);
+                    buffer.bind_graphics_pipeline(pipeline.resource());
+                    buffer.bind_graphics_descriptor_sets(
+                        self.layout.resource(), 0, Some(set.resource()), no_offsets);
+                    let mut pass = buffer.begin_render_pass_inline(
+                        render_pass.resource(), framebuffer.resource(), rect, no_clears);
+                    pass.draw(0 .. 3, 0 .. 1);
+                }
+
+                let handles = encoder.handles();
+                handles.add(src);
+                handles.add(dst);
+                handles.add(framebuffer);
+                handles.add_descriptor_set(set);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<B: Backend> Drop for MipmapFallback<B> {
+    fn drop(&mut self) {
+        if let Some((vertex, fragment)) = self.shaders.take() {
+            self.device.raw.destroy_shader_module(vertex);
+            self.device.raw.destroy_shader_module(fragment);
+        }
+    }
+}
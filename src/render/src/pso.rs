@@ -4,11 +4,11 @@ use std::borrow::Borrow;
 use std::mem;
 use std::marker::PhantomData;
 
-use {hal, format, handle};
+use {hal, format, handle, descriptors};
 use hal::image::Layout;
 use hal::pass::{AttachmentOps, AttachmentLoadOp, AttachmentStoreOp};
 
-use {Backend, Device, Primitive, Supports, Transfer, Graphics, Encoder};
+use {Backend, Device, Primitive, Supports, Transfer, Graphics, Compute, Encoder};
 
 pub use hal::pso::{
     ColorValue, DepthValue, StencilValue, Rect, Viewport,
@@ -18,7 +18,7 @@ pub use hal::pso::{
 #[derive(Debug)]
 pub struct RawDescriptorSet<B: Backend> {
     pub(crate) resource: B::DescriptorSet,
-    pub(crate) pool: handle::raw::DescriptorPool<B>,
+    pub(crate) pool: descriptors::PoolRef<B>,
 }
 
 impl<B: Backend> RawDescriptorSet<B> {
@@ -102,6 +102,10 @@ define_descriptors! {
     [ 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12 ]
     SampledImage,
     Sampler,
+    StorageImage,
+    UniformBuffer,
+    UniformBufferDynamic,
+    StorageBuffer,
 }
 
 impl<B: Backend> Bind<B> for SampledImage {
@@ -170,6 +174,142 @@ impl<B: Backend> Bind<B> for Sampler {
     }
 }
 
+impl<B: Backend> Bind<B> for StorageImage {
+    type Handle = handle::raw::ImageView<B>;
+
+    fn write<'a, I>(views: I) -> Vec<hal::pso::Descriptor<'a, B>>
+    where
+        I: IntoIterator,
+        I::Item: Borrow<&'a Self::Handle>,
+    {
+        views
+            .into_iter()
+            .map(|view| {
+                let view: &'a Self::Handle = *view.borrow();
+                hal::pso::Descriptor::Image(view.resource(), Layout::General)
+            }).collect()
+    }
+
+    fn require<'a>(
+        view: &'a Self::Handle,
+        _: &mut Vec<(&'a handle::raw::Buffer<B>, hal::buffer::State)>,
+        images: &mut Vec<(&'a handle::raw::Image<B>, hal::image::Subresource, hal::image::State)>,
+        _: &mut handle::Bag<B>,
+    ) {
+        let img = view.info();
+        let levels = img.info().mip_levels;
+        let layers = img.info().kind.num_layers();
+        let state = (hal::image::Access::SHADER_READ | hal::image::Access::SHADER_WRITE, Layout::General);
+        for level in 0..levels {
+            for layer in 0..layers {
+                let subresource = hal::image::Subresource {
+                    aspects: img.info().aspects,
+                    level,
+                    layer
+                };
+                images.push((img, subresource, state));
+            }
+        }
+    }
+}
+
+impl<B: Backend> Bind<B> for UniformBuffer {
+    type Handle = handle::raw::Buffer<B>;
+
+    fn write<'a, I>(buffers: I) -> Vec<hal::pso::Descriptor<'a, B>>
+    where
+        I: IntoIterator,
+        I::Item: Borrow<&'a Self::Handle>,
+    {
+        buffers
+            .into_iter()
+            .map(|buffer| {
+                let buffer: &'a Self::Handle = *buffer.borrow();
+                hal::pso::Descriptor::Buffer(buffer.resource(), None .. None)
+            }).collect()
+    }
+
+    fn require<'a>(
+        buffer: &'a Self::Handle,
+        buffers: &mut Vec<(&'a handle::raw::Buffer<B>, hal::buffer::State)>,
+        _: &mut Vec<(&'a handle::raw::Image<B>, hal::image::Subresource, hal::image::State)>,
+        _: &mut handle::Bag<B>,
+    ) {
+        buffers.push((buffer, hal::buffer::Access::CONSTANT_BUFFER_READ));
+    }
+}
+
+/// Buffer bound to a dynamic descriptor.
+///
+/// The descriptor covers `size` bytes, starting at the offset given when binding
+/// the descriptor set.
+#[derive(Debug)]
+pub struct DynamicBuffer<B: Backend> {
+    pub buffer: handle::raw::Buffer<B>,
+    pub size: hal::buffer::Offset,
+}
+
+impl<B: Backend> Clone for DynamicBuffer<B> {
+    fn clone(&self) -> Self {
+        DynamicBuffer {
+            buffer: self.buffer.clone(),
+            size: self.size,
+        }
+    }
+}
+
+impl<B: Backend> Bind<B> for UniformBufferDynamic {
+    type Handle = DynamicBuffer<B>;
+
+    fn write<'a, I>(buffers: I) -> Vec<hal::pso::Descriptor<'a, B>>
+    where
+        I: IntoIterator,
+        I::Item: Borrow<&'a Self::Handle>,
+    {
+        buffers
+            .into_iter()
+            .map(|dynamic| {
+                let dynamic: &'a Self::Handle = *dynamic.borrow();
+                hal::pso::Descriptor::Buffer(dynamic.buffer.resource(), Some(0) .. Some(dynamic.size))
+            }).collect()
+    }
+
+    fn require<'a>(
+        dynamic: &'a Self::Handle,
+        buffers: &mut Vec<(&'a handle::raw::Buffer<B>, hal::buffer::State)>,
+        _: &mut Vec<(&'a handle::raw::Image<B>, hal::image::Subresource, hal::image::State)>,
+        _: &mut handle::Bag<B>,
+    ) {
+        buffers.push((&dynamic.buffer, hal::buffer::Access::CONSTANT_BUFFER_READ));
+    }
+}
+
+impl<B: Backend> Bind<B> for StorageBuffer {
+    type Handle = handle::raw::Buffer<B>;
+
+    fn write<'a, I>(buffers: I) -> Vec<hal::pso::Descriptor<'a, B>>
+    where
+        I: IntoIterator,
+        I::Item: Borrow<&'a Self::Handle>,
+    {
+        buffers
+            .into_iter()
+            .map(|buffer| {
+                let buffer: &'a Self::Handle = *buffer.borrow();
+                hal::pso::Descriptor::Buffer(buffer.resource(), None .. None)
+            }).collect()
+    }
+
+    fn require<'a>(
+        buffer: &'a Self::Handle,
+        buffers: &mut Vec<(&'a handle::raw::Buffer<B>, hal::buffer::State)>,
+        _: &mut Vec<(&'a handle::raw::Image<B>, hal::image::Subresource, hal::image::State)>,
+        _: &mut handle::Bag<B>,
+    ) {
+        buffers.push((buffer, hal::buffer::Access::SHADER_READ | hal::buffer::Access::SHADER_WRITE));
+    }
+}
+
 pub struct DescriptorSetBindRef<'a, 'b, B: Backend, T: Bind<B>> {
     pub set: &'a B::DescriptorSet,
     pub binding: DescriptorBinding,
@@ -233,9 +373,56 @@ pub trait GraphicsPipelineMeta<B: Backend> {
     fn render_pass(&self) -> &B::RenderPass;
 }
 
+pub trait ComputePipelineInit<B: Backend> {
+    type Pipeline;
+
+    fn create<'a>(
+        self,
+        &mut Device<B>,
+        hal::pso::EntryPoint<'a, B>,
+    ) -> Result<Self::Pipeline, CreationError>;
+}
+
+pub trait ComputePipelineMeta<B: Backend> {
+    fn layout(&self) -> &B::PipelineLayout;
+}
+
+pub trait ComputePipelineData<B: Backend> {
+    type Pipeline;
+
+    /// Collect the states of the bound resources, keeping them alive with `handles`.
+    fn require<'a>(
+        &'a self,
+        buffer_states: &mut Vec<(&'a handle::raw::Buffer<B>, hal::buffer::State)>,
+        image_states: &mut Vec<(&'a handle::raw::Image<B>, hal::image::Subresource, hal::image::State)>,
+        handles: &mut handle::Bag<B>,
+    );
+
+    /// Bind the pipeline with its descriptor sets.
+    ///
+    /// The states collected by `require` have to be required on the encoder beforehand.
+    fn bind<'a, 'b, C>(
+        self,
+        encoder: &'a mut Encoder<'b, B, C>,
+        pipeline: &'a Self::Pipeline
+    ) where Self: 'a, 'b: 'a, C: Supports<Transfer> + Supports<Compute>;
+}
+
 pub trait GraphicsPipelineData<B: Backend> {
     type Pipeline;
 
+    /// Collect the states of the bound resources, keeping them alive with `handles`.
+    fn require<'a>(
+        &'a self,
+        buffer_states: &mut Vec<(&'a handle::raw::Buffer<B>, hal::buffer::State)>,
+        image_states: &mut Vec<(&'a handle::raw::Image<B>, hal::image::Subresource, hal::image::State)>,
+        handles: &mut handle::Bag<B>,
+    );
+
+    /// Bind the pipeline with its resources and begin its render pass.
+    ///
+    /// The states collected by `require` have to be required on the encoder beforehand,
+    /// barriers can't be recorded inside of the render pass.
     fn begin_renderpass<'a, 'b, C>(
         self,
         encoder: &'a mut Encoder<'b, B, C>,
@@ -281,6 +468,8 @@ pub trait Component<'a, B: Backend> {
     {
         None
     }
+
+    fn dynamic_offsets(&Self::Data, &mut Vec<hal::command::DescriptorSetOffset>) {}
 }
 
 pub struct Attachment {
@@ -419,3 +608,16 @@ impl<'a, B, T, I> Component<'a, B> for VertexBuffer<T, I>
 }
 
 pub type InstanceBuffer<T> = VertexBuffer<T, Instanced>;
+
+/// Offsets of the dynamic descriptors, in the order of the descriptor sets
+/// and of their bindings.
+pub struct DynamicOffsets;
+
+impl<'a, B: Backend> Component<'a, B> for DynamicOffsets {
+    type Init = ();
+    type Data = &'a [hal::command::DescriptorSetOffset];
+
+    fn dynamic_offsets(data: &Self::Data, offsets: &mut Vec<hal::command::DescriptorSetOffset>) {
+        offsets.extend_from_slice(data);
+    }
+}
@@ -2,13 +2,13 @@
 //!
 //! See `Slice`-structure documentation for more information on this module.
 
-use hal::{handle, buffer};
-use hal::{Primitive, Backend, VertexCount};
-use hal::command::InstanceParams;
-use hal::device::Device;
-use hal::memory::Bind;
-use format::Format;
-use pso;
+use std::mem;
+use std::ops::Range;
+
+use hal::{Primitive, VertexCount, VertexOffset, InstanceCount, DrawCount};
+use hal::IndexType;
+use handle;
+use Backend;
 
 /// A `Slice` dictates in which and in what order vertices get processed. It is required for
 /// processing a PSO.
@@ -43,6 +43,8 @@ use pso;
 ///
 /// A `Slice` is required to process a PSO, as it contains the needed information on in what order
 /// to draw which vertices. As such, every `draw` call on an `Encoder` requires a `Slice`.
+///
+/// The draw parameters can also be sourced from a buffer on the device, see `Indirect`.
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
 pub struct Slice<B: Backend> {
     /// The start index of the index-buffer. Processing will start at this location in the
@@ -54,30 +56,73 @@ pub struct Slice<B: Backend> {
     /// This is the index of the first vertex in the `VertexBuffer`. This value will be added to
     /// every index in the index-buffer, effectively moving the start of the `VertexBuffer` to this
     /// base-vertex.
-    pub base_vertex: VertexCount,
-    /// Instancing configuration.
-    pub instances: Option<InstanceParams>,
+    pub base_vertex: VertexOffset,
+    /// The range of instances to draw.
+    pub instances: Range<InstanceCount>,
     /// Represents the type of index-buffer used.
     pub buffer: IndexBuffer<B>,
+    /// Draw parameters read from a buffer, replacing `start`, `end`,
+    /// `base_vertex` and `instances`.
+    pub indirect: Option<Indirect<B>>,
 }
 
-impl<B: Backend> Slice<B> {
-    /// Creates a new `Slice` to match the supplied vertex buffer, from start to end, in order.
-    pub fn new_match_vertex_buffer<V>(vbuf: &handle::Buffer<B, V>) -> Self
-                                      where V: pso::buffer::Structure<Format> {
+/// Vertex range of a non-indexed draw, offset by `base_vertex`.
+pub(crate) fn offset_vertices(vertices: Range<VertexCount>, base_vertex: VertexOffset) -> Range<VertexCount> {
+    let offset = |vertex: VertexCount| {
+        let vertex = vertex as i64 + base_vertex as i64;
+        assert!(vertex >= 0 && vertex <= VertexCount::max_value() as i64,
+            "vertex out of range: {}", vertex);
+        vertex as VertexCount
+    };
+    offset(vertices.start) .. offset(vertices.end)
+}
+
+impl<B: Backend> From<Range<VertexCount>> for Slice<B> {
+    fn from(vertices: Range<VertexCount>) -> Self {
         Slice {
-            start: 0,
-            end: vbuf.len() as u32,
+            start: vertices.start,
+            end: vertices.end,
             base_vertex: 0,
-            instances: None,
+            instances: 0 .. 1,
             buffer: IndexBuffer::Auto,
+            indirect: None,
+        }
+    }
+}
+
+impl<B: Backend> Slice<B> {
+    /// Creates a new `Slice` to match the supplied vertex buffer, from start to end, in order.
+    pub fn new_match_vertex_buffer<V>(vbuf: &handle::Buffer<B, V>) -> Self {
+        let len = vbuf.as_ref().info().size / mem::size_of::<V>() as u64;
+        Slice::from(0 .. len as VertexCount)
+    }
+
+    /// Creates a new `Slice` drawing the whole index buffer.
+    pub fn new_match_index_buffer<I>(ibuf: I) -> Self
+        where I: IntoIndexBuffer<B>
+    {
+        let buffer = ibuf.into_index_buffer();
+        let end = buffer.len().expect("no index buffer");
+        Slice {
+            buffer,
+            .. Slice::from(0 .. end)
         }
     }
 
+    /// Draws the given range of instances.
+    pub fn with_instances(self, instances: Range<InstanceCount>) -> Self {
+        Slice { instances, .. self }
+    }
+
+    /// Reads the draw parameters from a buffer.
+    pub fn with_indirect(self, indirect: Indirect<B>) -> Self {
+        Slice { indirect: Some(indirect), .. self }
+    }
+
     /// Calculates the number of primitives of the specified type in this `Slice`.
     pub fn get_prim_count(&self, prim: Primitive) -> u32 {
         use hal::Primitive as p;
-        let nv = (self.end - self.start) as u32;
+        let nv = self.end - self.start;
         match prim {
             p::PointList => nv,
             p::LineList => nv / 2,
@@ -135,25 +180,60 @@ pub enum IndexBuffer<B: Backend> {
     Index32(handle::Buffer<B, u32>),
 }
 
+impl<B: Backend> IndexBuffer<B> {
+    /// Number of indices in the buffer, `None` for `Auto`.
+    pub fn len(&self) -> Option<VertexCount> {
+        match *self {
+            IndexBuffer::Auto => None,
+            IndexBuffer::Index16(ref buffer) => Some((buffer.as_ref().info().size / 2) as _),
+            IndexBuffer::Index32(ref buffer) => Some((buffer.as_ref().info().size / 4) as _),
+        }
+    }
+
+    pub(crate) fn raw(&self) -> Option<(&handle::raw::Buffer<B>, IndexType)> {
+        match *self {
+            IndexBuffer::Auto => None,
+            IndexBuffer::Index16(ref buffer) => Some((buffer.as_ref(), IndexType::U16)),
+            IndexBuffer::Index32(ref buffer) => Some((buffer.as_ref(), IndexType::U32)),
+        }
+    }
+}
+
 impl<B: Backend> Default for IndexBuffer<B> {
     fn default() -> Self {
         IndexBuffer::Auto
     }
 }
+/// Draw parameters stored in a buffer.
+///
+/// The buffer contains `draw_count` structures of `DrawIndirectCommand` layout,
+/// or `DrawIndexedIndirectCommand` layout for indexed slices, `stride` bytes apart.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Indirect<B: Backend> {
+    /// Buffer holding the draw parameters.
+    pub buffer: handle::raw::Buffer<B>,
+    /// Offset of the first draw in bytes.
+    pub offset: u64,
+    /// Number of draws.
+    pub draw_count: DrawCount,
+    /// Distance between the draws in bytes.
+    pub stride: u32,
+}
+
 /// A helper trait to create `IndexBuffers` from different kinds of data.
 pub trait IntoIndexBuffer<B: Backend> {
     /// Turns self into an `IndexBuffer`.
-    fn into_index_buffer<F: Device<B> + ?Sized>(self, device: &mut F) -> IndexBuffer<B>;
+    fn into_index_buffer(self) -> IndexBuffer<B>;
 }
 
 impl<B: Backend> IntoIndexBuffer<B> for IndexBuffer<B> {
-    fn into_index_buffer<F: Device<B> + ?Sized>(self, _: &mut F) -> IndexBuffer<B> {
+    fn into_index_buffer(self) -> IndexBuffer<B> {
         self
     }
 }
 
 impl<B: Backend> IntoIndexBuffer<B> for () {
-    fn into_index_buffer<F: Device<B> + ?Sized>(self, _: &mut F) -> IndexBuffer<B> {
+    fn into_index_buffer(self) -> IndexBuffer<B> {
         IndexBuffer::Auto
     }
 }
@@ -161,20 +241,33 @@ impl<B: Backend> IntoIndexBuffer<B> for () {
 macro_rules! impl_index_buffer {
     ($prim_ty:ty, $buf_ty:ident) => (
         impl<B: Backend> IntoIndexBuffer<B> for handle::Buffer<B, $prim_ty> {
-            fn into_index_buffer<F: Device<B> + ?Sized>(self, _: &mut F) -> IndexBuffer<B> {
+            fn into_index_buffer(self) -> IndexBuffer<B> {
                 IndexBuffer::$buf_ty(self)
             }
         }
-
-        impl<'s, B: Backend> IntoIndexBuffer<B> for &'s [$prim_ty] {
-            fn into_index_buffer<F: Device<B> + ?Sized>(self, device: &mut F) -> IndexBuffer<B> {
-                device.create_buffer_immutable(self, buffer::Role::Index, Bind::empty())
-                      .unwrap()
-                      .into_index_buffer(device)
-            }
-        }
     )
 }
 
 impl_index_buffer!(u16, Index16);
 impl_index_buffer!(u32, Index32);
+
+#[cfg(test)]
+mod tests {
+    use super::offset_vertices;
+
+    #[test]
+    fn offset_by_base_vertex() {
+        assert_eq!(offset_vertices(0 .. 3, 0), 0 .. 3);
+        assert_eq!(offset_vertices(4 .. 10, 2), 6 .. 12);
+        assert_eq!(offset_vertices(4 .. 10, -4), 0 .. 6);
+        // starts beyond `VertexOffset` range
+        assert_eq!(offset_vertices(1 << 31 .. (1 << 31) + 3, -1), (1 << 31) - 1 .. (1 << 31) + 2);
+        assert_eq!(offset_vertices(0 .. 3, 1 << 30), 1 << 30 .. (1 << 30) + 3);
+    }
+
+    #[test]
+    #[should_panic]
+    fn offset_below_zero() {
+        offset_vertices(2 .. 5, -3);
+    }
+}
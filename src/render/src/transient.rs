@@ -0,0 +1,160 @@
+//! Per-frame allocation of transient uniform data.
+//!
+//! A `TransientAllocator` hands out aligned ranges of a persistently mapped
+//! uniform buffer, to be bound through `UniformBufferDynamic` descriptors with
+//! the allocated offsets. The data is only valid for the frame it is written
+//! in: the space of a frame is reused once its `Release` has been passed back,
+//! which `Context` does when the fence of the frame has signaled.
+
+use std::marker::PhantomData;
+use std::sync::{Arc, Mutex};
+use std::{cmp, mem};
+
+use hal::Limits;
+use hal::command::DescriptorSetOffset;
+
+use mapped::{lcm, MappedBuffer, MappedBufferError};
+use memory::Pod;
+use {buffer, pso};
+use {Backend, Device};
+
+/// Space used by the allocations of a frame.
+///
+/// Has to be passed to `TransientAllocator::release` once the frame has been
+/// executed, in the order the frames were submitted.
+#[derive(Debug)]
+#[must_use]
+pub struct Release {
+    end: u64,
+}
+
+/// Offset of transient data of type `T`.
+#[derive(Debug)]
+pub struct Transient<T> {
+    offset: DescriptorSetOffset,
+    phantom: PhantomData<T>,
+}
+
+impl<T> Clone for Transient<T> {
+    fn clone(&self) -> Self { *self }
+}
+
+impl<T> Copy for Transient<T> {}
+
+impl<T> Transient<T> {
+    /// Dynamic offset to bind the data with.
+    pub fn offset(&self) -> DescriptorSetOffset {
+        self.offset
+    }
+}
+
+/// Alignment of the allocations, so that each one can be bound and flushed on its own.
+fn alignment(limits: &Limits) -> u64 {
+    lcm(
+        cmp::max(limits.min_uniform_buffer_offset_alignment, 1),
+        cmp::max(limits.non_coherent_atom_size as u64, 1),
+    )
+}
+
+/// Linear allocator of transient uniform data in a persistently mapped buffer.
+///
+/// Cloning an allocator shares its buffer.
+pub struct TransientAllocator<B: Backend>(Arc<Mutex<Linear<B>>>);
+
+impl<B: Backend> Clone for TransientAllocator<B> {
+    fn clone(&self) -> Self {
+        TransientAllocator(self.0.clone())
+    }
+}
+
+struct Linear<B: Backend> {
+    mapped: MappedBuffer<B>,
+    alignment: u64,
+}
+
+impl<B: Backend> TransientAllocator<B> {
+    /// Create an allocator with a buffer of `size` bytes in upload memory.
+    pub fn new(device: &mut Device<B>, limits: &Limits, size: u64) -> Result<Self, MappedBufferError> {
+        let mapped = MappedBuffer::new(device, buffer::Usage::UNIFORM, size)?;
+        Ok(TransientAllocator(Arc::new(Mutex::new(Linear {
+            mapped,
+            alignment: alignment(limits),
+        }))))
+    }
+
+    /// Descriptor handle covering one `T` of the buffer,
+    /// to be written to a `UniformBufferDynamic` descriptor.
+    pub fn binding<T: Pod>(&self) -> pso::DynamicBuffer<B> {
+        pso::DynamicBuffer {
+            buffer: self.0.lock().unwrap().mapped.buffer().clone(),
+            size: mem::size_of::<T>() as u64,
+        }
+    }
+
+    /// Copy `data` into the buffer for the current frame.
+    pub fn allocate<T: Pod>(&self, data: &T) -> Result<Transient<T>, MappedBufferError> {
+        let mut linear = self.0.lock().unwrap();
+        let alignment = linear.alignment;
+        let offset = linear.mapped.allocate(mem::size_of::<T>() as u64, alignment)?;
+        linear.mapped.write(offset, &[*data]);
+        Ok(Transient {
+            offset: offset as DescriptorSetOffset,
+            phantom: PhantomData,
+        })
+    }
+
+    /// Make the writes of the current frame visible to the device.
+    ///
+    /// Returns the space used by the frame, has to be called before submitting it.
+    pub fn flush(&self) -> Release {
+        let mut linear = self.0.lock().unwrap();
+        linear.mapped.flush();
+        Release { end: linear.mapped.head() }
+    }
+
+    /// Make the space of an executed frame available again.
+    pub fn release(&self, release: Release) {
+        self.0.lock().unwrap().mapped.release(release.end);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hal::Limits;
+
+    use mapped::{MappedBufferError, RingSpace};
+    use super::alignment;
+
+    fn limits(offset_alignment: u64, atom_size: usize) -> Limits {
+        Limits {
+            min_uniform_buffer_offset_alignment: offset_alignment,
+            non_coherent_atom_size: atom_size,
+            .. Limits::default()
+        }
+    }
+
+    #[test]
+    fn allocation_alignment() {
+        assert_eq!(alignment(&limits(256, 64)), 256);
+        assert_eq!(alignment(&limits(64, 256)), 256);
+        assert_eq!(alignment(&limits(48, 64)), 192);
+        assert_eq!(alignment(&limits(16, 0)), 16);
+        assert_eq!(alignment(&limits(0, 0)), 1);
+    }
+
+    #[test]
+    fn out_of_space() {
+        let alignment = alignment(&limits(48, 64));
+        let mut space = RingSpace::new(512);
+        assert_eq!(space.allocate(16, alignment).ok(), Some(0));
+        assert_eq!(space.allocate(16, alignment).ok(), Some(192));
+        assert_eq!(space.allocate(16, alignment).ok(), Some(384));
+        match space.allocate(16, alignment) {
+            Err(MappedBufferError::OutOfSpace) => (),
+            other => panic!("unexpected allocation {:?}", other),
+        }
+        // the space of the first frame comes back once it has been executed
+        space.release(208);
+        assert_eq!(space.allocate(16, alignment).ok(), Some(0));
+    }
+}
@@ -0,0 +1,177 @@
+//! Staging uploads through a host-visible ring buffer.
+//!
+//! An `Uploader` copies the data of upload requests into a persistently mapped
+//! ring buffer right away and records the copies to their destination in a
+//! batch, usually once per frame. The ring space of a batch is reused once the
+//! batch has been executed, which is signaled by passing its `Release` back.
+
+use std::sync::{Arc, Mutex};
+use std::{cmp, mem};
+
+use hal::{self, Limits};
+use hal::command::{BufferCopy, BufferImageCopy};
+
+use mapped::{align, lcm, MappedBuffer, MappedBufferError};
+use {buffer, handle, image};
+use {Backend, Device, Encoder, Supports, Transfer};
+
+/// Ring space used by a recorded batch of uploads.
+///
+/// Has to be passed to `Uploader::release` once the batch has been executed,
+/// in the order the batches were recorded.
+#[derive(Debug)]
+#[must_use]
+pub struct Release {
+    end: u64,
+}
+
+/// Batches uploads through a persistently mapped ring buffer.
+///
+/// Cloning an uploader shares its ring buffer and pending requests.
+pub struct Uploader<B: Backend>(Arc<Mutex<Ring<B>>>);
+
+impl<B: Backend> Clone for Uploader<B> {
+    fn clone(&self) -> Self {
+        Uploader(self.0.clone())
+    }
+}
+
+struct Ring<B: Backend> {
+    mapped: MappedBuffer<B>,
+    offset_alignment: u64,
+    pitch_alignment: u64,
+    buffer_copies: Vec<(handle::raw::Buffer<B>, BufferCopy)>,
+    image_copies: Vec<(handle::raw::Image<B>, BufferImageCopy)>,
+}
+
+impl<B: Backend> Uploader<B> {
+    /// Create an uploader with a ring buffer of `size` bytes in upload memory.
+    pub fn new(device: &mut Device<B>, limits: &Limits, size: u64) -> Result<Self, MappedBufferError> {
+        let mapped = MappedBuffer::new(device, buffer::Usage::TRANSFER_SRC, size)?;
+        Ok(Uploader(Arc::new(Mutex::new(Ring {
+            mapped,
+            offset_alignment: lcm(cmp::max(limits.min_buffer_copy_offset_alignment, 1), 4),
+            pitch_alignment: cmp::max(limits.min_buffer_copy_pitch_alignment, 1),
+            buffer_copies: Vec::new(),
+            image_copies: Vec::new(),
+        }))))
+    }
+
+    /// Request `data` to be copied to `buffer`, starting at element `offset`.
+    pub fn upload_buffer<MTB>(
+        &self,
+        buffer: &MTB,
+        offset: u64,
+        data: &[MTB::Data],
+    ) -> Result<(), MappedBufferError>
+        where MTB: buffer::MaybeTyped<B>
+    {
+        if data.is_empty() { return Ok(()); }
+        let buffer = buffer.as_ref();
+
+        debug_assert!(buffer.info().usage.contains(buffer::Usage::TRANSFER_DST),
+            "missing TRANSFER_DST usage flag");
+
+        let stride = mem::size_of::<MTB::Data>() as u64;
+        let size = data.len() as u64 * stride;
+        debug_assert!((offset * stride + size) <= buffer.info().size,
+            "out of buffer bounds");
+
+        let mut ring = self.0.lock().unwrap();
+        let alignment = ring.offset_alignment;
+        let src = ring.mapped.allocate(size, alignment)?;
+        ring.mapped.write(src, data);
+        ring.buffer_copies.push((buffer.clone(), BufferCopy {
+            src,
+            dst: offset * stride,
+            size,
+        }));
+        Ok(())
+    }
+
+    /// Request a subresource of `image` to be filled with `data`.
+    ///
+    /// `data` contains the tightly packed texel blocks of the whole level
+    /// of the subresource, row by row and slice by slice.
+    pub fn upload_image<I>(
+        &self,
+        image: I,
+        subresource: image::Subresource,
+        data: &[u8],
+    ) -> Result<(), MappedBufferError>
+        where I: AsRef<handle::raw::Image<B>>
+    {
+        let image = image.as_ref();
+        let info = image.info();
+
+        debug_assert!(info.usage.contains(image::Usage::TRANSFER_DST),
+            "missing TRANSFER_DST usage flag");
+
+        let desc = info.format.surface_desc();
+        let block_size = desc.bits as u64 / 8;
+        let (block_width, block_height) = (desc.dim.0 as u32, desc.dim.1 as u32);
+        let extent = info.kind.level_extent(subresource.level);
+        let rows = (extent.height + block_height - 1) / block_height;
+        let row_size = ((extent.width + block_width - 1) / block_width) as u64 * block_size;
+        let slice_size = row_size * rows as u64;
+        assert_eq!(data.len() as u64, slice_size * extent.depth as u64,
+            "data does not match the subresource size");
+
+        let mut ring = self.0.lock().unwrap();
+        let row_pitch = align(row_size, lcm(ring.pitch_alignment, block_size));
+        let slice_pitch = row_pitch * rows as u64;
+        let alignment = lcm(ring.offset_alignment, block_size);
+        let offset = ring.mapped.allocate(slice_pitch * extent.depth as u64, alignment)?;
+        for (i, row) in data.chunks(row_size as usize).enumerate() {
+            let (slice, y) = (i as u64 / rows as u64, i as u64 % rows as u64);
+            ring.mapped.write(offset + slice * slice_pitch + y * row_pitch, row);
+        }
+        ring.image_copies.push((image.clone(), BufferImageCopy {
+            buffer_offset: offset,
+            buffer_width: (row_pitch / block_size) as u32 * block_width,
+            buffer_height: rows * block_height,
+            image_layers: image::SubresourceLayers {
+                aspects: subresource.aspects,
+                level: subresource.level,
+                layers: subresource.layer .. subresource.layer + 1,
+            },
+            image_offset: hal::image::Offset { x: 0, y: 0, z: 0 },
+            image_extent: extent,
+        }));
+        Ok(())
+    }
+
+    /// Whether there are requests waiting to be recorded.
+    pub fn has_pending(&self) -> bool {
+        let ring = self.0.lock().unwrap();
+        !ring.buffer_copies.is_empty() || !ring.image_copies.is_empty()
+    }
+
+    /// Record the copies of all pending requests into `encoder`.
+    ///
+    /// Returns the ring space used by the batch, if any requests were pending.
+    pub fn record<C>(&self, encoder: &mut Encoder<B, C>) -> Option<Release>
+        where C: Supports<Transfer>
+    {
+        let mut ring = self.0.lock().unwrap();
+        if ring.buffer_copies.is_empty() && ring.image_copies.is_empty() {
+            return None;
+        }
+        ring.mapped.flush();
+
+        let ring = &mut *ring;
+        for (dst, region) in ring.buffer_copies.drain(..) {
+            encoder.copy_buffer(ring.mapped.buffer(), &dst, &[region]);
+        }
+        for (dst, region) in ring.image_copies.drain(..) {
+            encoder.copy_buffer_to_image(ring.mapped.buffer(), &dst, &[region]);
+        }
+        Some(Release { end: ring.mapped.head() })
+    }
+
+    /// Make the ring space of an executed batch available again.
+    pub fn release(&self, release: Release) {
+        let mut ring = self.0.lock().unwrap();
+        ring.mapped.release(release.end);
+    }
+}
@@ -110,6 +110,7 @@ impl Harness {
         _disabilities: Disabilities,
     ) -> usize {
         use hal::{PhysicalDevice};
+        use hal::adapter::{select_adapter, Criteria};
 
         let mut results = TestResults {
             pass: 0,
@@ -117,8 +118,9 @@ impl Harness {
             fail: 0,
         };
         for tg in &self.suite {
-            let mut adapters = instance.enumerate_adapters();
-            let adapter = adapters.remove(0);
+            let criteria = Criteria::<I::Backend, hal::General>::new();
+            let adapter = select_adapter(instance.enumerate_adapters(), &criteria)
+                .expect("No suitable adapter found");
             let features = adapter.physical_device.features();
             let limits = adapter.physical_device.limits();
             //println!("\t{:?}", adapter.info);
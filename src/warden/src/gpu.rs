@@ -60,12 +60,14 @@ impl<B: hal::Backend> Buffer<B> {
         memory::Barrier::Buffer {
             states: self.stable_state .. access,
             target: &self.handle,
+            families: None,
         }
     }
     fn barrier_from(&self, access: b::Access) -> memory::Barrier<B> {
         memory::Barrier::Buffer {
             states: access .. self.stable_state,
             target: &self.handle,
+            families: None,
         }
     }
 }
@@ -85,6 +87,7 @@ impl<B: hal::Backend> Image<B> {
             states: self.stable_state .. (access, layout),
             target: &self.handle,
             range: self.range.clone(),
+            families: None,
         }
     }
     fn barrier_from(&self, access: i::Access, layout: i::Layout) -> memory::Barrier<B> {
@@ -92,6 +95,7 @@ impl<B: hal::Backend> Image<B> {
             states: (access, layout) .. self.stable_state,
             target: &self.handle,
             range: self.range.clone(),
+            families: None,
         }
     }
 }
@@ -230,6 +234,7 @@ impl<B: hal::Backend> Scene<B, hal::General> {
                                 let buffer_barrier = memory::Barrier::Buffer {
                                     states: b::Access::empty() .. access,
                                     target: &buffer,
+                                    families: None,
                                 };
                                 init_cmd.pipeline_barrier(
                                     pso::PipelineStage::TOP_OF_PIPE .. pso::PipelineStage::BOTTOM_OF_PIPE,
@@ -265,6 +270,7 @@ impl<B: hal::Backend> Scene<B, hal::General> {
                             let pre_barrier = memory::Barrier::Buffer {
                                 states: b::Access::empty() .. b::Access::TRANSFER_WRITE,
                                 target: &buffer,
+                                families: None,
                             };
                             init_cmd.pipeline_barrier(
                                 pso::PipelineStage::TOP_OF_PIPE .. pso::PipelineStage::TRANSFER,
@@ -284,6 +290,7 @@ impl<B: hal::Backend> Scene<B, hal::General> {
                             let post_barrier = memory::Barrier::Buffer {
                                 states: b::Access::TRANSFER_WRITE .. final_state,
                                 target: &buffer,
+                                families: None,
                             };
                             init_cmd.pipeline_barrier(
                                 pso::PipelineStage::TRANSFER .. pso::PipelineStage::BOTTOM_OF_PIPE,
@@ -337,6 +344,7 @@ impl<B: hal::Backend> Scene<B, hal::General> {
                                         aspects,
                                         .. COLOR_RANGE.clone()
                                     },
+                                    families: None,
                                 };
                                 init_cmd.pipeline_barrier(
                                     pso::PipelineStage::TOP_OF_PIPE .. pso::PipelineStage::BOTTOM_OF_PIPE,
@@ -391,6 +399,7 @@ impl<B: hal::Backend> Scene<B, hal::General> {
                                         (i::Access::TRANSFER_WRITE, i::Layout::TransferDstOptimal),
                                 target: &image,
                                 range: COLOR_RANGE.clone(), //TODO
+                                families: None,
                             };
                             init_cmd.pipeline_barrier(
                                 pso::PipelineStage::TOP_OF_PIPE .. pso::PipelineStage::TRANSFER,
@@ -421,6 +430,7 @@ impl<B: hal::Backend> Scene<B, hal::General> {
                                 states: (i::Access::TRANSFER_WRITE, i::Layout::TransferDstOptimal) .. final_state,
                                 target: &image,
                                 range: COLOR_RANGE.clone(), //TODO
+                                families: None,
                             };
                             init_cmd.pipeline_barrier(
                                 pso::PipelineStage::TRANSFER .. pso::PipelineStage::BOTTOM_OF_PIPE,
@@ -1073,6 +1083,7 @@ impl<B: hal::Backend> Scene<B, hal::General> {
             let pre_barrier = memory::Barrier::Buffer {
                 states: buffer.stable_state .. b::Access::TRANSFER_READ,
                 target: &buffer.handle,
+                families: None,
             };
             cmd_buffer.pipeline_barrier(
                 pso::PipelineStage::TOP_OF_PIPE .. pso::PipelineStage::TRANSFER,
@@ -1094,6 +1105,7 @@ impl<B: hal::Backend> Scene<B, hal::General> {
             let post_barrier = memory::Barrier::Buffer {
                 states: b::Access::TRANSFER_READ .. buffer.stable_state,
                 target: &buffer.handle,
+                families: None,
             };
             cmd_buffer.pipeline_barrier(
                 pso::PipelineStage::TRANSFER .. pso::PipelineStage::BOTTOM_OF_PIPE,
@@ -1168,6 +1180,7 @@ impl<B: hal::Backend> Scene<B, hal::General> {
                 states: image.stable_state .. (i::Access::TRANSFER_READ, i::Layout::TransferSrcOptimal),
                 target: &image.handle,
                 range: COLOR_RANGE.clone(), //TODO
+                families: None,
             };
             cmd_buffer.pipeline_barrier(
                 pso::PipelineStage::TOP_OF_PIPE .. pso::PipelineStage::TRANSFER,
@@ -1202,6 +1215,7 @@ impl<B: hal::Backend> Scene<B, hal::General> {
                 states: (i::Access::TRANSFER_READ, i::Layout::TransferSrcOptimal) .. image.stable_state,
                 target: &image.handle,
                 range: COLOR_RANGE.clone(), //TODO
+                families: None,
             };
             cmd_buffer.pipeline_barrier(
                 pso::PipelineStage::TRANSFER .. pso::PipelineStage::BOTTOM_OF_PIPE,